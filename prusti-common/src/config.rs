@@ -17,6 +17,19 @@ pub struct ConfigFlags {
     pub print_desugared_specs: bool,
     /// Should Prusti print the type-checked specifications.
     pub print_typeckd_specs: bool,
+    /// Should Prusti print the locals captured by each specification closure.
+    pub dump_spec_captures: bool,
+    /// Should Prusti print the stable hash of each specification.
+    pub dump_spec_hashes: bool,
+    /// Should Prusti print the spans of each specification.
+    pub dump_spec_spans: bool,
+    /// Should Prusti print the spans, source text and quantified variables
+    /// of the parts of each specification.
+    pub dump_spec_details: bool,
+    /// An edit of the source code (position, removed and inserted bytes) to
+    /// which the spans of the specifications are remapped before printing
+    /// them.
+    pub remap_spec_spans: Option<(u32, u32, u32)>,
     /// Should Prusti print the items collected for verification.
     pub print_collected_verfication_items: bool,
     /// Should Prusti skip the verification part.
@@ -57,6 +70,11 @@ lazy_static! {
         settings.set_default("NO_VERIFY", false).unwrap();
         settings.set_default("FULL_COMPILATION", false).unwrap();
         settings.set_default("JSON_COMMUNICATION", false).unwrap();
+        settings.set_default("WARN_MISSING_TRIGGERS", false).unwrap();
+        settings.set_default("TRIGGER_STRATEGY", "none").unwrap();
+        settings.set_default("WARN_UNGUARDED_SUBTRACTIONS", false).unwrap();
+        settings.set_default("MAX_ASSERTION_DEPTH", 32).unwrap();
+        settings.set_default("SHOW_MACRO_EXPANSION_SPANS", false).unwrap();
 
         // Flags for debugging Prusti that can change verification results.
         settings.set_default("DISABLE_NAME_MANGLING", false).unwrap();
@@ -297,6 +315,35 @@ pub fn skip_unsupported_functions() -> bool {
     read_setting("SKIP_UNSUPPORTED_FUNCTIONS")
 }
 
+/// Warn about quantifiers that have no explicit triggers
+pub fn warn_missing_triggers() -> bool {
+    read_setting("WARN_MISSING_TRIGGERS")
+}
+
+/// How the triggers of quantifiers without explicit triggers are inferred:
+/// `none` (leave it to the verifier), `shallow` or `aggressive`
+pub fn trigger_strategy() -> String {
+    read_setting("TRIGGER_STRATEGY")
+}
+
+/// Warn about subtractions of unsigned integers in specifications that are not
+/// guarded against underflows
+pub fn warn_unguarded_subtractions() -> bool {
+    read_setting("WARN_UNGUARDED_SUBTRACTIONS")
+}
+
+/// The maximum nesting depth of a specification. Deeper specifications are
+/// reported as errors instead of being processed recursively.
+pub fn max_assertion_depth() -> usize {
+    read_setting("MAX_ASSERTION_DEPTH")
+}
+
+/// Report the errors of specifications generated by macros inside the macro
+/// expansion, instead of at the macro invocation
+pub fn show_macro_expansion_spans() -> bool {
+    read_setting("SHOW_MACRO_EXPANSION_SPANS")
+}
+
 /// Skip the verification
 pub fn no_verify() -> bool {
     read_setting("NO_VERIFY")
@@ -35,8 +35,8 @@ pub enum Expr {
     Unfolding(String, Vec<Expr>, Box<Expr>, PermAmount, MaybeEnumVariantIndex, Position),
     /// Cond: guard, then_expr, else_expr
     Cond(Box<Expr>, Box<Expr>, Box<Expr>, Position),
-    /// ForAll: variables, triggers, body
-    ForAll(Vec<LocalVar>, Vec<Trigger>, Box<Expr>, Position),
+    /// ForAll: variables, triggers, body, instantiation weight
+    ForAll(Vec<LocalVar>, Vec<Trigger>, Box<Expr>, Option<u32>, Position),
     /// let variable == (expr) in body
     LetExpr(LocalVar, Box<Expr>, Box<Expr>, Position),
     /// FuncApp: function_name, args, formal_args, return_type, Viper position
@@ -130,7 +130,7 @@ impl fmt::Display for Expr {
             Expr::Cond(ref guard, ref left, ref right, ref _pos) => {
                 write!(f, "({})?({}):({})", guard, left, right)
             }
-            Expr::ForAll(ref vars, ref triggers, ref body, ref _pos) => write!(
+            Expr::ForAll(ref vars, ref triggers, ref body, _, ref _pos) => write!(
                 f,
                 "forall {} {} :: {}",
                 vars.iter()
@@ -256,7 +256,7 @@ impl Expr {
             Expr::BinOp(_, _, _, p) => p,
             Expr::Unfolding(_, _, _, _, _, p) => p,
             Expr::Cond(_, _, _, p) => p,
-            Expr::ForAll(_, _, _, p) => p,
+            Expr::ForAll(_, _, _, _, p) => p,
             Expr::LetExpr(_, _, _, p) => p,
             Expr::FuncApp(_, _, _, _, p) => p,
             Expr::DomainFuncApp(_, _, p) => p,
@@ -284,7 +284,7 @@ impl Expr {
                 Expr::Unfolding(x, y, z, perm, variant, pos)
             },
             Expr::Cond(x, y, z, _) => Expr::Cond(x, y, z, pos),
-            Expr::ForAll(x, y, z, _) => Expr::ForAll(x, y, z, pos),
+            Expr::ForAll(x, y, z, w, _) => Expr::ForAll(x, y, z, w, pos),
             Expr::LetExpr(x, y, z, _) => Expr::LetExpr(x, y, z, pos),
             Expr::FuncApp(x, y, z, k, _) => Expr::FuncApp(x, y, z, k, pos),
             Expr::DomainFuncApp(x,y,_) => Expr::DomainFuncApp(x,y,pos),
@@ -418,7 +418,17 @@ impl Expr {
     }
 
     pub fn forall(vars: Vec<LocalVar>, triggers: Vec<Trigger>, body: Expr) -> Self {
-        Expr::ForAll(vars, triggers, box body, Position::default())
+        Expr::ForAll(vars, triggers, box body, None, Position::default())
+    }
+
+    /// A quantifier whose instantiations are limited by the given weight.
+    pub fn weighted_forall(
+        vars: Vec<LocalVar>,
+        triggers: Vec<Trigger>,
+        body: Expr,
+        weight: Option<u32>,
+    ) -> Self {
+        Expr::ForAll(vars, triggers, box body, weight, Position::default())
     }
 
     pub fn ite(guard: Expr, left: Expr, right: Expr) -> Self {
@@ -479,6 +489,55 @@ impl Expr {
         finder.found
     }
 
+    /// The function applications of the expression that can be used as trigger terms of a
+    /// quantifier over `vars`: they mention all the variables, and the variables only occur as
+    /// arguments of function applications, never under an operator. Applications that are
+    /// nested in a binder or in an `old` expression are not considered.
+    pub fn trigger_candidates(&self, vars: &[LocalVar]) -> Vec<Expr> {
+        pub struct TriggerCandidateFinder<'a> {
+            vars: &'a [LocalVar],
+            candidates: Vec<Expr>,
+        }
+        impl<'a> TriggerCandidateFinder<'a> {
+            fn mentions_var(&self, expr: &Expr) -> bool {
+                self.vars.iter().any(|var| expr.find(&Expr::local(var.clone())))
+            }
+            fn is_valid_arg(&self, arg: &Expr) -> bool {
+                match *arg {
+                    Expr::Local(..) => true,
+                    Expr::FuncApp(_, ref args, ..) |
+                    Expr::DomainFuncApp(_, ref args, _) => args.iter().all(|arg| self.is_valid_arg(arg)),
+                    _ => !self.mentions_var(arg),
+                }
+            }
+        }
+        impl<'a> ExprWalker for TriggerCandidateFinder<'a> {
+            fn walk(&mut self, expr: &Expr) {
+                match *expr {
+                    Expr::FuncApp(_, ref args, ..) |
+                    Expr::DomainFuncApp(_, ref args, _) => {
+                        let covers_vars = self.vars.iter()
+                            .all(|var| expr.find(&Expr::local(var.clone())));
+                        if covers_vars && args.iter().all(|arg| self.is_valid_arg(arg)) &&
+                            !self.candidates.contains(expr) {
+                            self.candidates.push(expr.clone());
+                        }
+                        default_walk_expr(self, expr)
+                    }
+                    Expr::ForAll(..) | Expr::LetExpr(..) | Expr::LabelledOld(..) => {}
+                    _ => default_walk_expr(self, expr),
+                }
+            }
+        }
+
+        let mut finder = TriggerCandidateFinder {
+            vars,
+            candidates: vec![],
+        };
+        finder.walk(self);
+        finder.candidates
+    }
+
     /// Extract all predicates places mentioned in the expression whose predicates have the given
     /// permission amount.
     pub fn extract_predicate_places(&self, perm_amount: PermAmount) -> Vec<Expr> {
@@ -982,11 +1041,12 @@ impl Expr {
                 vars: Vec<LocalVar>,
                 triggers: Vec<Trigger>,
                 body: Box<Expr>,
+                weight: Option<u32>,
                 pos: Position,
             ) -> Expr {
                 if vars.contains(&self.target.get_base()) {
                     // Do nothing
-                    Expr::ForAll(vars, triggers, body, pos)
+                    Expr::ForAll(vars, triggers, body, weight, pos)
                 } else {
                     Expr::ForAll(
                         vars,
@@ -995,6 +1055,7 @@ impl Expr {
                             .map(|x| x.replace_place(self.target, self.replacement))
                             .collect(),
                         self.fold_boxed(body),
+                        weight,
                         pos,
                     )
                 }
@@ -1102,6 +1163,7 @@ impl Expr {
                 vars: Vec<LocalVar>,
                 triggers: Vec<Trigger>,
                 body: Box<Expr>,
+                weight: Option<u32>,
                 pos: Position,
             ) -> Expr {
                 // TODO: the correct solution is the following:
@@ -1123,6 +1185,7 @@ impl Expr {
                         .map(|x| x.replace_multiple_places(self.replacements))
                         .collect(),
                     self.fold_boxed(body),
+                    weight,
                     pos,
                 )
             }
@@ -1367,9 +1430,12 @@ impl PartialEq for Expr {
                 Expr::Cond(box ref other_cond, box ref other_then, box ref other_else, _),
             ) => (self_cond, self_then, self_else) == (other_cond, other_then, other_else),
             (
-                Expr::ForAll(ref self_vars, ref self_triggers, box ref self_expr, _),
-                Expr::ForAll(ref other_vars, ref other_triggers, box ref other_expr, _),
-            ) => (self_vars, self_triggers, self_expr) == (other_vars, other_triggers, other_expr),
+                Expr::ForAll(ref self_vars, ref self_triggers, box ref self_expr, self_weight, _),
+                Expr::ForAll(ref other_vars, ref other_triggers, box ref other_expr, other_weight, _),
+            ) => {
+                (self_vars, self_triggers, self_expr, self_weight)
+                    == (other_vars, other_triggers, other_expr, other_weight)
+            }
             (
                 Expr::LetExpr(ref self_var, box ref self_def, box ref self_expr, _),
                 Expr::LetExpr(ref other_var, box ref other_def, box ref other_expr, _),
@@ -1416,8 +1482,8 @@ impl Hash for Expr {
             Expr::Cond(box ref cond, box ref then_expr, box ref else_expr, _) => {
                 (cond, then_expr, else_expr).hash(state)
             }
-            Expr::ForAll(ref vars, ref triggers, box ref expr, _) => {
-                (vars, triggers, expr).hash(state)
+            Expr::ForAll(ref vars, ref triggers, box ref expr, weight, _) => {
+                (vars, triggers, expr, weight).hash(state)
             }
             Expr::LetExpr(ref var, box ref def, box ref expr, _) => (var, def, expr).hash(state),
             Expr::FuncApp(ref name, ref args, _, _, _) => (name, args).hash(state),
@@ -166,9 +166,10 @@ pub trait ExprFolder: Sized {
         x: Vec<LocalVar>,
         y: Vec<Trigger>,
         z: Box<Expr>,
+        w: Option<u32>,
         p: Position,
     ) -> Expr {
-        Expr::ForAll(x, y, self.fold_boxed(z), p)
+        Expr::ForAll(x, y, self.fold_boxed(z), w, p)
     }
     fn fold_let_expr(
         &mut self,
@@ -256,7 +257,7 @@ pub fn default_fold_expr<T: ExprFolder>(this: &mut T, e: Expr) -> Expr {
             this.fold_unfolding(x, y, z, perm, variant, p)
         },
         Expr::Cond(x, y, z, p) => this.fold_cond(x, y, z, p),
-        Expr::ForAll(x, y, z, p) => this.fold_forall(x, y, z, p),
+        Expr::ForAll(x, y, z, w, p) => this.fold_forall(x, y, z, w, p),
         Expr::LetExpr(x, y, z, p) => this.fold_let_expr(x, y, z, p),
         Expr::FuncApp(x, y, z, k, p) => this.fold_func_app(x, y, z, k, p),
         Expr::DomainFuncApp(x, y, p) => this.fold_domain_func_app(x,y,p),
@@ -346,6 +347,7 @@ pub trait ExprWalker: Sized {
         vars: &Vec<LocalVar>,
         _triggers: &Vec<Trigger>,
         body: &Expr,
+        _weight: &Option<u32>,
         _pos: &Position
     ) {
         for var in vars {
@@ -423,7 +425,7 @@ pub fn default_walk_expr<T: ExprWalker>(this: &mut T, e: &Expr) {
             this.walk_unfolding(x, y, z, perm, variant, p)
         },
         Expr::Cond(ref x, ref y, ref z, ref p) => this.walk_cond(x, y, z, p),
-        Expr::ForAll(ref x, ref y, ref z, ref p) => this.walk_forall(x, y, z, p),
+        Expr::ForAll(ref x, ref y, ref z, ref w, ref p) => this.walk_forall(x, y, z, w, p),
         Expr::LetExpr(ref x, ref y, ref z, ref p) => this.walk_let_expr(x, y, z, p),
         Expr::FuncApp(ref x, ref y, ref z, ref k, ref p) => this.walk_func_app(x, y, z, k, p),
         Expr::DomainFuncApp(ref x, ref y,ref p) => this.walk_domain_func_app(x,y,p),
@@ -558,9 +560,10 @@ pub trait FallibleExprFolder: Sized {
         x: Vec<LocalVar>,
         y: Vec<Trigger>,
         z: Box<Expr>,
+        w: Option<u32>,
         p: Position,
     ) -> Result<Expr, Self::Error> {
-        Ok(Expr::ForAll(x, y, self.fallible_fold_boxed(z)?, p))
+        Ok(Expr::ForAll(x, y, self.fallible_fold_boxed(z)?, w, p))
     }
     fn fallible_fold_let_expr(
         &mut self,
@@ -667,7 +670,7 @@ pub fn default_fallible_fold_expr<U, T: FallibleExprFolder<Error=U>>(
             this.fallible_fold_unfolding(x, y, z, perm, variant, p)
         },
         Expr::Cond(x, y, z, p) => this.fallible_fold_cond(x, y, z, p),
-        Expr::ForAll(x, y, z, p) => this.fallible_fold_forall(x, y, z, p),
+        Expr::ForAll(x, y, z, w, p) => this.fallible_fold_forall(x, y, z, w, p),
         Expr::LetExpr(x, y, z, p) => this.fallible_fold_let_expr(x, y, z, p),
         Expr::FuncApp(x, y, z, k, p) => this.fallible_fold_func_app(x, y, z, k, p),
         Expr::DomainFuncApp(x, y, p) => this.fallible_fold_domain_func_app(x,y,p),
@@ -175,6 +175,7 @@ impl ast::ExprWalker for VarCollector {
         vars: &Vec<ast::LocalVar>,
         _triggers: &Vec<ast::Trigger>,
         body: &ast::Expr,
+        _weight: &Option<u32>,
         _pos: &ast::Position,
     ) {
         self.walk(body);
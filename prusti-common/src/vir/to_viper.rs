@@ -404,12 +404,21 @@ impl<'v> ToViper<'v, viper::Expr<'v>> for Expr {
                 right.to_viper(ast),
                 pos.to_viper(ast),
             ),
-            &Expr::ForAll(ref vars, ref triggers, ref body, ref pos) => ast.forall_with_pos(
+            &Expr::ForAll(ref vars, ref triggers, ref body, None, ref pos) => ast.forall_with_pos(
                 &vars.to_viper_decl(ast)[..],
                 &(triggers, pos).to_viper(ast),
                 body.to_viper(ast),
                 pos.to_viper(ast),
             ),
+            &Expr::ForAll(ref vars, ref triggers, ref body, Some(weight), ref pos) => {
+                ast.weighted_forall_with_pos(
+                    &vars.to_viper_decl(ast)[..],
+                    &(triggers, pos).to_viper(ast),
+                    body.to_viper(ast),
+                    weight,
+                    pos.to_viper(ast),
+                )
+            }
             &Expr::LetExpr(ref var, ref expr, ref body, ref pos) => ast.let_expr_with_pos(
                 var.to_viper_decl(ast),
                 expr.to_viper(ast),
@@ -33,11 +33,30 @@ pub fn trusted(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
     tokens
 }
 
+/// Ghost functions can only be used in specifications, which are dropped
+/// without Prusti, so the function is dropped as well.
+#[proc_macro_attribute]
+pub fn ghost(_attr: TokenStream, _tokens: TokenStream) -> TokenStream {
+    TokenStream::new()
+}
+
+/// Predicates can only be used in specifications, so they are dropped like
+/// ghost functions.
+#[proc_macro]
+pub fn predicate(_tokens: TokenStream) -> TokenStream {
+    TokenStream::new()
+}
+
 #[proc_macro]
 pub fn body_invariant(_tokens: TokenStream) -> TokenStream {
     (quote! { () }).into()
 }
 
+#[proc_macro]
+pub fn prusti_assert(_tokens: TokenStream) -> TokenStream {
+    (quote! { () }).into()
+}
+
 #[proc_macro]
 pub fn closure(tokens: TokenStream) -> TokenStream {
     prusti_specs::closure(tokens.into(), true).into()
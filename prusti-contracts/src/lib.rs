@@ -20,9 +20,18 @@ mod private {
     /// A macro for marking a function as trusted.
     pub use prusti_contracts_impl::trusted;
 
+    /// A macro for marking a function as ghost, i.e. usable only in specifications.
+    pub use prusti_contracts_impl::ghost;
+
+    /// A macro for defining a predicate, i.e. a named assertion usable in specifications.
+    pub use prusti_contracts_impl::predicate;
+
     /// A macro for writing a loop body invariant.
     pub use prusti_contracts_impl::body_invariant;
 
+    /// A macro for writing an assertion at a program point.
+    pub use prusti_contracts_impl::prusti_assert;
+
     /// A macro for defining a closure with a specification.
     pub use prusti_contracts_impl::closure;
 
@@ -53,9 +62,18 @@ mod private {
     /// A macro for marking a function as trusted.
     pub use prusti_contracts_internal::trusted;
 
+    /// A macro for marking a function as ghost, i.e. usable only in specifications.
+    pub use prusti_contracts_internal::ghost;
+
+    /// A macro for defining a predicate, i.e. a named assertion usable in specifications.
+    pub use prusti_contracts_internal::predicate;
+
     /// A macro for writing a loop body invariant.
     pub use prusti_contracts_internal::body_invariant;
 
+    /// A macro for writing an assertion at a program point.
+    pub use prusti_contracts_internal::prusti_assert;
+
     /// A macro for defining a closure with a specification.
     pub use prusti_contracts_internal::closure;
 
@@ -143,6 +143,12 @@ impl<'a, 'tcx> Procedure<'a, 'tcx> {
         !self.nonspec_basic_blocks.contains(&bbi)
     }
 
+    /// Check whether the block is used for typechecking a `prusti_assert!(..)`
+    /// statement, as opposed to a loop invariant
+    pub fn is_spec_assertion_block(&self, bbi: BasicBlockIndex) -> bool {
+        self.is_spec_block(bbi) && is_spec_assertion_basic_block(&self.mir[bbi], &self.tcx)
+    }
+
     /// Check whether the block is reachable
     pub fn is_reachable_block(&self, bbi: BasicBlockIndex) -> bool {
         self.reachable_basic_blocks.contains(&bbi)
@@ -289,6 +295,16 @@ fn blocks_definitely_leading_to<'a>(bb_graph: &HashMap<BasicBlock, BasicBlockNod
     blocks
 }
 
+fn is_spec_assertion_basic_block(bb_data: &BasicBlockData, tcx: &TyCtxt) -> bool {
+    bb_data.statements.iter().any(|stmt| {
+        if let StatementKind::Assign(box (_, Rvalue::Aggregate(box AggregateKind::Closure(def_id, _), _))) = &stmt.kind {
+            crate::utils::has_prusti_attr(tcx.get_attrs(*def_id), "assert_spec")
+        } else {
+            false
+        }
+    })
+}
+
 fn get_nonspec_basic_blocks(bb_graph: HashMap<BasicBlock, BasicBlockNode>, mir: &Mir, tcx: &TyCtxt) -> HashSet<BasicBlock>{
     let mut spec_basic_blocks: HashSet<BasicBlock> = HashSet::new();
     for (bb, _) in bb_graph.iter() {
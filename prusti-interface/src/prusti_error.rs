@@ -70,6 +70,18 @@ impl PrustiError {
         )
     }
 
+    /// Report a likely problem of the verified Rust code that does not prevent the
+    /// verification (e.g. a quantifier without triggers)
+    pub fn warning<S: ToString>(message: S, span: MultiSpan) -> Self {
+        check_message(message.to_string());
+        let mut warning = PrustiError::new(
+            format!("[Prusti: warning] {}", message.to_string()),
+            span
+        );
+        warning.set_warning();
+        warning
+    }
+
     /// Report an internal error of Prusti (e.g. failure of the fold-unfold)
     pub fn internal<S: ToString>(message: S, span: MultiSpan) -> Self {
         check_message(message.to_string());
@@ -122,6 +134,17 @@ impl PrustiError {
         self
     }
 
+    /// Set the span of an error that was created without one, e.g. deep in a
+    /// conversion that does not know where its input comes from.
+    ///
+    /// Note: this is a noop if the error already has a span
+    pub fn set_missing_span(mut self, span: MultiSpan) -> Self {
+        if self.span.primary_spans().is_empty() {
+            self.span = span;
+        }
+        self
+    }
+
     /// Convert the original error span to a note, and add a new error span.
     ///
     /// Note: this is a noop if `opt_span` is None
@@ -0,0 +1,954 @@
+//! Sanity checks on the typed specifications that are performed before the
+//! verification starts.
+
+use rustc_hir as hir;
+use rustc_ast::ast;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::mir;
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_span::{MultiSpan, Span};
+use rustc_span::symbol::Symbol;
+use std::collections::HashSet;
+use std::convert::TryInto;
+
+use crate::data::ProcedureDefId;
+use crate::environment::Environment;
+use crate::specs::typed::{self, AssertionVisitor, SortedSpecificationMap};
+use crate::PrustiError;
+use crate::utils::read_prusti_attrs;
+
+/// Report an error for every quantifier trigger that does not mention all the
+/// variables bound by its quantifier. Such a trigger can never be matched by
+/// the solver, so the quantifier would silently never be instantiated.
+pub fn check_trigger_coverage<'tcx>(
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    for (_, assertion) in specs.iter_sorted(env.tcx()) {
+        check_assertion_trigger_coverage(assertion, env);
+    }
+}
+
+fn check_assertion_trigger_coverage<'tcx>(
+    assertion: &typed::Assertion<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    match *assertion.kind {
+        typed::AssertionKind::Expr(_) |
+        typed::AssertionKind::Matches(..) => {}
+        typed::AssertionKind::And(ref assertions) |
+        typed::AssertionKind::Or(ref assertions) => {
+            for assertion in assertions {
+                check_assertion_trigger_coverage(assertion, env);
+            }
+        }
+        typed::AssertionKind::Implies(ref lhs, ref rhs) |
+        typed::AssertionKind::Iff(ref lhs, ref rhs) => {
+            check_assertion_trigger_coverage(lhs, env);
+            check_assertion_trigger_coverage(rhs, env);
+        }
+        typed::AssertionKind::TypeCond(_, ref body) |
+        typed::AssertionKind::BoundedForAll(_, _, _, ref body) |
+        typed::AssertionKind::Let(_, _, ref body) => {
+            check_assertion_trigger_coverage(body, env);
+        }
+        typed::AssertionKind::ForAll(_, ref trigger_set, ref body) |
+        typed::AssertionKind::Exists(_, ref trigger_set, ref body) => {
+            for trigger in trigger_set.triggers() {
+                if let Err(error) = check_trigger(trigger, env.tcx()) {
+                    error.emit(env);
+                }
+                for term in trigger.terms() {
+                    if let Err(error) = check_trigger_term_vars(term, env.tcx()) {
+                        error.emit(env);
+                    }
+                    if let Err(error) = check_trigger_term_shape(term, env.tcx()) {
+                        error.emit(env);
+                    }
+                }
+            }
+            check_assertion_trigger_coverage(body, env);
+        }
+    }
+}
+
+fn check_trigger<'tcx>(trigger: &typed::Trigger, tcx: TyCtxt<'tcx>) -> Result<(), PrustiError> {
+    let first_term = match trigger.terms().first() {
+        Some(term) => term,
+        None => return Ok(()),
+    };
+    // The type-checking closures of the trigger terms are defined directly
+    // inside the closure of the quantifier, whose arguments are the bound
+    // variables.
+    let quantifier_def_id = tcx.parent(first_term.expr.to_def_id())
+        .expect("a trigger term must be nested in its quantifier");
+    let bound_vars = quantifier_bound_vars(quantifier_def_id, tcx);
+
+    let mut mentioned_vars = HashSet::new();
+    for term in trigger.terms() {
+        if let Some(upvars) = tcx.upvars_mentioned(term.expr.to_def_id()) {
+            mentioned_vars.extend(upvars.keys().cloned());
+        }
+    }
+
+    let missing_vars: Vec<String> = bound_vars
+        .iter()
+        .filter(|(hir_id, _)| !mentioned_vars.contains(hir_id))
+        .map(|(_, name)| format!("`{}`", name))
+        .collect();
+    if missing_vars.is_empty() {
+        Ok(())
+    } else {
+        Err(PrustiError::incorrect(
+            format!(
+                "a trigger must mention all quantified variables, but it does not mention {}",
+                missing_vars.join(", "),
+            ),
+            MultiSpan::from_spans(
+                trigger.terms().iter().map(|term| tcx.def_span(term.expr)).collect()
+            ),
+        ))
+    }
+}
+
+/// Check that the variables mentioned by a trigger term are bound either by a
+/// quantifier (or `let`) that encloses the term, or by the specification
+/// function, whose arguments are the ones of the specified function. Any other
+/// variable would be free in the encoded trigger.
+fn check_trigger_term_vars<'tcx>(
+    term: &typed::Expression,
+    tcx: TyCtxt<'tcx>,
+) -> Result<(), PrustiError> {
+    let term_def_id = term.expr.to_def_id();
+    let upvars = match tcx.upvars_mentioned(term_def_id) {
+        Some(upvars) => upvars,
+        None => return Ok(()),
+    };
+    let mut bound_vars = HashSet::new();
+    let mut scope = term_def_id;
+    while tcx.is_closure(scope) {
+        scope = tcx.parent(scope).expect("a closure must have a parent");
+        bound_vars.extend(quantifier_bound_vars(scope, tcx).into_iter().map(|(hir_id, _)| hir_id));
+    }
+
+    let free_vars: Vec<String> = upvars
+        .keys()
+        .filter(|hir_id| !bound_vars.contains(hir_id))
+        .map(|&hir_id| format!("`{}`", tcx.hir().name(hir_id)))
+        .collect();
+    if free_vars.is_empty() {
+        Ok(())
+    } else {
+        Err(PrustiError::incorrect(
+            format!(
+                "a trigger term can only mention quantified variables and arguments \
+                of the function, but it mentions {}",
+                free_vars.join(", "),
+            ),
+            MultiSpan::from_span(tcx.def_span(term.expr)),
+        ))
+    }
+}
+
+/// Check that a trigger term is a function call, a method call, a field access
+/// or an indexing operation, possibly dereferenced. The verifier rejects
+/// triggers that are arithmetic or logical operations, literals or variables.
+fn check_trigger_term_shape<'tcx>(
+    term: &typed::Expression,
+    tcx: TyCtxt<'tcx>,
+) -> Result<(), PrustiError> {
+    let mut value = typed::closure_value(term, tcx);
+    // The closure of a trigger term evaluates the term as a statement.
+    if let hir::ExprKind::Block(block, _) = value.kind {
+        if let [hir::Stmt { kind: hir::StmtKind::Semi(term_value), .. }] = block.stmts {
+            value = term_value;
+        }
+    }
+    while let hir::ExprKind::Unary(hir::UnOp::UnDeref, inner) = value.kind {
+        value = inner;
+    }
+    match value.kind {
+        hir::ExprKind::Call(..) |
+        hir::ExprKind::MethodCall(..) |
+        hir::ExprKind::Field(..) |
+        hir::ExprKind::Index(..) => Ok(()),
+        _ => Err(PrustiError::incorrect(
+            "a trigger term must be a function call, a method call, a field access \
+            or an indexing operation".to_string(),
+            MultiSpan::from_span(tcx.def_span(term.expr)),
+        ).set_help("for example, use `f(x)` instead of `x + 1` as trigger")),
+    }
+}
+
+/// The `HirId`s and names of the variables bound by the quantifier whose
+/// closure is identified by `quantifier_def_id`.
+fn quantifier_bound_vars<'tcx>(
+    quantifier_def_id: DefId,
+    tcx: TyCtxt<'tcx>,
+) -> Vec<(hir::HirId, Symbol)> {
+    let hir = tcx.hir();
+    let hir_id = hir.local_def_id_to_hir_id(quantifier_def_id.expect_local());
+    let body = hir.body(hir.body_owned_by(hir_id));
+    body.params
+        .iter()
+        .filter_map(|param| {
+            if let hir::PatKind::Binding(_, var_hir_id, ident, _) = param.pat.kind {
+                Some((var_hir_id, ident.name))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Report an error for every call of an impure function in the body of a
+/// `#[pure]` function. The error points at the call itself, instead of failing
+/// later during the encoding of the pure function.
+pub fn check_pure_function_calls<'tcx>(
+    procedures: &[ProcedureDefId],
+    extern_specs: &typed::ExternSpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    for &def_id in procedures {
+        if !env.has_prusti_attribute(def_id, "pure") || env.has_prusti_attribute(def_id, "trusted") {
+            continue;
+        }
+        let mir = env.mir(def_id.expect_local());
+        for basic_block in mir.basic_blocks() {
+            if let Err(error) = check_pure_terminator(basic_block.terminator(), extern_specs, env) {
+                error.emit(env);
+            }
+        }
+    }
+}
+
+fn check_pure_terminator<'tcx>(
+    terminator: &mir::Terminator<'tcx>,
+    extern_specs: &typed::ExternSpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) -> Result<(), PrustiError> {
+    let (func, destination) = match terminator.kind {
+        mir::TerminatorKind::Call { ref func, ref destination, .. } => (func, destination),
+        _ => return Ok(()),
+    };
+    if destination.is_none() {
+        // Diverging calls (e.g. panics) are encoded as unreachable.
+        return Ok(());
+    }
+    let called_def_id = match *func {
+        mir::Operand::Constant(box mir::Constant { literal: ty::Const { ty, .. }, .. }) => {
+            match ty.kind() {
+                ty::TyKind::FnDef(def_id, _) => *def_id,
+                _ => return Ok(()),
+            }
+        }
+        _ => return Ok(()),
+    };
+    let spec_def_id = extern_specs.get(&called_def_id)
+        .map(|(_, spec_def_id, _)| *spec_def_id)
+        .unwrap_or(called_def_id);
+    if env.has_prusti_attribute(spec_def_id, "pure") {
+        return Ok(());
+    }
+    let called_name = env.tcx().def_path_str(called_def_id);
+    match called_name.as_str() {
+        // Comparisons of types with a structural equality are encoded
+        // natively.
+        "std::cmp::PartialEq::eq" | "std::cmp::PartialEq::ne" => Ok(()),
+        _ => Err(PrustiError::incorrect(
+            format!("use of impure function `{}` in pure function is not allowed", called_name),
+            MultiSpan::from_span(terminator.source_info.span),
+        )),
+    }
+}
+
+/// Report an error for every call of a `#[ghost]` function outside of a
+/// specification. Ghost functions are dropped when compiling without Prusti.
+pub fn check_ghost_function_calls<'tcx>(
+    procedures: &[ProcedureDefId],
+    env: &Environment<'tcx>,
+) {
+    for &def_id in procedures {
+        // Ghost functions and specifications are only used by the verification
+        if env.has_prusti_attribute(def_id, "ghost") || env.has_prusti_attribute(def_id, "spec_only") {
+            continue;
+        }
+        let mir = env.mir(def_id.expect_local());
+        for basic_block in mir.basic_blocks() {
+            let terminator = basic_block.terminator();
+            if let mir::TerminatorKind::Call { ref func, .. } = terminator.kind {
+                if let ty::TyKind::FnDef(called_def_id, _) = func.ty(&*mir, env.tcx()).kind() {
+                    if env.has_prusti_attribute(*called_def_id, "ghost") {
+                        PrustiError::incorrect(
+                            format!(
+                                "ghost function `{}` can only be used in specifications",
+                                env.get_item_name(*called_def_id),
+                            ),
+                            MultiSpan::from_span(terminator.source_info.span),
+                        ).emit(env);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Warn about every `#[pure] #[trusted]` function whose body has obvious side
+/// effects: writes to the memory of its arguments, mutable borrows of it, or
+/// calls of `#[trusted]` functions that take a mutable reference. The body of
+/// a trusted function is not verified, so the side effects would be silently
+/// ignored by the callers, which assume that a pure function has none.
+///
+/// This is not sound, e.g. side effects hidden behind unsafe code or impure
+/// library calls are not detected.
+pub fn check_trusted_pure_side_effects<'tcx>(
+    procedures: &[ProcedureDefId],
+    env: &Environment<'tcx>,
+) {
+    for &def_id in procedures {
+        if !env.has_prusti_attribute(def_id, "pure") || !env.has_prusti_attribute(def_id, "trusted") {
+            continue;
+        }
+        let mir = env.mir(def_id.expect_local());
+        let side_effects = find_side_effects(&mir, env);
+        if side_effects.is_empty() {
+            continue;
+        }
+        let mut span = MultiSpan::from_span(env.tcx().def_span(def_id));
+        for (side_effect_span, label) in side_effects {
+            span.push_span_label(side_effect_span, label);
+        }
+        PrustiError::warning(
+            format!("the trusted pure function `{}` has side effects", env.get_item_name(def_id)),
+            span,
+        ).set_help(
+            "pure functions are assumed to have no side effects; remove either `#[pure]` or \
+            the side effects"
+        ).emit(env);
+    }
+}
+
+/// The spans and descriptions of the side effects found in `mir`, see
+/// `check_trusted_pure_side_effects`.
+fn find_side_effects<'tcx>(
+    mir: &mir::Body<'tcx>,
+    env: &Environment<'tcx>,
+) -> Vec<(Span, String)> {
+    // Only the memory behind references is visible to the caller
+    let is_argument_memory = |place: &mir::Place<'tcx>| {
+        mir.args_iter().any(|arg| arg == place.local) &&
+            place.projection.iter().any(|elem| matches!(elem, mir::ProjectionElem::Deref))
+    };
+    let mut side_effects = vec![];
+    for basic_block in mir.basic_blocks() {
+        for statement in &basic_block.statements {
+            if let mir::StatementKind::Assign(box (ref place, ref rvalue)) = statement.kind {
+                if is_argument_memory(place) {
+                    side_effects.push((
+                        statement.source_info.span,
+                        "this modifies the memory of an argument".to_string(),
+                    ));
+                } else if let mir::Rvalue::Ref(_, mir::BorrowKind::Mut { .. }, ref borrowed) = *rvalue {
+                    if is_argument_memory(borrowed) {
+                        side_effects.push((
+                            statement.source_info.span,
+                            "this mutably borrows the memory of an argument".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        let terminator = basic_block.terminator();
+        if let mir::TerminatorKind::Call { ref func, ref args, .. } = terminator.kind {
+            let called_def_id = match func.ty(mir, env.tcx()).kind() {
+                ty::TyKind::FnDef(def_id, _) => *def_id,
+                _ => continue,
+            };
+            let takes_mut_ref = args.iter().any(|arg| {
+                matches!(arg.ty(mir, env.tcx()).kind(), ty::TyKind::Ref(_, _, hir::Mutability::Mut))
+            });
+            if takes_mut_ref &&
+                env.has_prusti_attribute(called_def_id, "trusted") &&
+                !env.has_prusti_attribute(called_def_id, "pure") {
+                side_effects.push((
+                    terminator.source_info.span,
+                    format!(
+                        "this calls the trusted function `{}`, which may modify its arguments",
+                        env.get_item_name(called_def_id),
+                    ),
+                ));
+            }
+        }
+    }
+    side_effects
+}
+
+/// Warn about every universal quantifier without triggers, which is likely to
+/// be a performance problem during the verification. Bounded quantifiers are
+/// not reported, because the encoding triggers them on the function calls of
+/// their body.
+///
+/// The triggers inferred by Prusti are selected according to the configured
+/// trigger strategy when the specifications are type-checked, so a
+/// quantifier is only reported if neither the user nor the strategy gave it
+/// triggers (Viper then infers them during the verification). This should
+/// run on the final typed specifications, right before the verification.
+pub fn check_missing_triggers<'tcx>(
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    let mut visitor = MissingTriggersVisitor { env };
+    for (_, assertion) in specs.iter_sorted(env.tcx()) {
+        visitor.visit_assertion(assertion);
+    }
+}
+
+struct MissingTriggersVisitor<'a, 'tcx> {
+    env: &'a Environment<'tcx>,
+}
+
+impl<'a, 'tcx> MissingTriggersVisitor<'a, 'tcx> {
+    fn report(&self, quantifier_def_id: DefId) {
+        let tcx = self.env.tcx();
+        PrustiError::warning(
+            "the quantifier has no triggers, which can make the verification slow",
+            MultiSpan::from_span(tcx.def_span(quantifier_def_id)),
+        )
+            .set_help("consider adding explicit triggers with `triggers=[..]`")
+            .emit(self.env);
+    }
+}
+
+impl<'a, 'tcx> AssertionVisitor<'tcx> for MissingTriggersVisitor<'a, 'tcx> {
+    fn visit_forall(
+        &mut self,
+        vars: &typed::ForAllVars<'tcx>,
+        trigger_set: &typed::TriggerSet,
+        body: &typed::Assertion<'tcx>,
+    ) {
+        // Inferred triggers are as good as written ones, since they are
+        // checked in the same way.
+        if !vars.has_inferred_triggers() && trigger_set.triggers().is_empty() {
+            let mut finder = DirectExpressionFinder { depth: 0, found: None };
+            finder.visit_assertion(body);
+            if let Some((expr, depth)) = finder.found {
+                // The closure of an expression is nested in the closures of
+                // all the quantifiers that enclose it.
+                let mut quantifier_def_id = expr.expr.to_def_id();
+                for _ in 0..=depth {
+                    quantifier_def_id = self.env.tcx().parent(quantifier_def_id)
+                        .expect("a quantified expression must be nested in its quantifier");
+                }
+                self.report(quantifier_def_id);
+            }
+        }
+        self.visit_assertion(body);
+    }
+
+}
+
+/// Finds the first expression of an assertion, together with the number of
+/// quantifiers that enclose it within the assertion.
+struct DirectExpressionFinder {
+    depth: usize,
+    found: Option<(typed::Expression, usize)>,
+}
+
+impl<'tcx> AssertionVisitor<'tcx> for DirectExpressionFinder {
+    fn visit_assertion(&mut self, assertion: &typed::Assertion<'tcx>) {
+        if self.found.is_none() {
+            typed::walk_assertion(self, assertion);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &typed::Expression) {
+        if self.found.is_none() {
+            self.found = Some((expr.clone(), self.depth));
+        }
+    }
+
+    fn visit_forall(
+        &mut self,
+        _vars: &typed::ForAllVars<'tcx>,
+        _trigger_set: &typed::TriggerSet,
+        body: &typed::Assertion<'tcx>,
+    ) {
+        self.depth += 1;
+        self.visit_assertion(body);
+        self.depth -= 1;
+    }
+
+    fn visit_exists(
+        &mut self,
+        _vars: &typed::ForAllVars<'tcx>,
+        _trigger_set: &typed::TriggerSet,
+        body: &typed::Assertion<'tcx>,
+    ) {
+        self.depth += 1;
+        self.visit_assertion(body);
+        self.depth -= 1;
+    }
+
+    fn visit_bounded_forall(
+        &mut self,
+        _vars: &typed::ForAllVars<'tcx>,
+        lower: &typed::Expression,
+        upper: &typed::Expression,
+        body: &typed::Assertion<'tcx>,
+    ) {
+        self.depth += 1;
+        typed::walk_bounded_forall(self, lower, upper, body);
+        self.depth -= 1;
+    }
+}
+
+/// Report an error for every `old(..)` whose argument refers to `result`.
+/// The result does not exist in the pre-state, so such an expression is
+/// meaningless and would otherwise fail with a confusing error during the
+/// encoding.
+pub fn check_old_result<'tcx>(
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    let mut visitor = OldResultVisitor { env };
+    for (_, assertion) in specs.iter_sorted(env.tcx()) {
+        visitor.visit_assertion(assertion);
+    }
+}
+
+struct OldResultVisitor<'a, 'tcx> {
+    env: &'a Environment<'tcx>,
+}
+
+impl<'a, 'tcx> AssertionVisitor<'tcx> for OldResultVisitor<'a, 'tcx> {
+    fn visit_expr(&mut self, expr: &typed::Expression) {
+        if !expr.contains_old {
+            return;
+        }
+        let tcx = self.env.tcx();
+        let result = match typed::result_param(expr, tcx) {
+            Some(result) => result,
+            None => return,
+        };
+        let hir_map = tcx.hir();
+        let body_id = hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(expr.expr));
+        let mut finder = OldResultFinder { tcx, result, in_old: false, spans: Vec::new() };
+        finder.visit_body(hir_map.body(body_id));
+        for span in finder.spans {
+            PrustiError::incorrect(
+                "`old(result)` is not allowed, because `result` does not exist in the pre-state",
+                MultiSpan::from_span(span),
+            ).emit(self.env);
+        }
+    }
+}
+
+/// Collects the spans of the `old(..)` calls whose arguments mention the
+/// variable `result`.
+struct OldResultFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    result: hir::HirId,
+    in_old: bool,
+    spans: Vec<Span>,
+}
+
+impl<'tcx> OldResultFinder<'tcx> {
+    fn is_old_call(&self, ex: &'tcx hir::Expr<'tcx>) -> bool {
+        if let hir::ExprKind::Call(ref callee_expr, _) = ex.kind {
+            if let hir::ExprKind::Path(ref qself) = callee_expr.kind {
+                let res = self.tcx.typeck(callee_expr.hir_id.owner).qpath_res(qself, callee_expr.hir_id);
+                if let hir::def::Res::Def(_, def_id) = res {
+                    return self.tcx.def_path_str(def_id) == "prusti_contracts::old";
+                }
+            }
+        }
+        false
+    }
+
+    fn mentions_result(&self, ex: &'tcx hir::Expr<'tcx>) -> bool {
+        let mut finder = OldResultFinder {
+            tcx: self.tcx,
+            result: self.result,
+            in_old: true,
+            spans: Vec::new(),
+        };
+        finder.visit_expr(ex);
+        !finder.spans.is_empty()
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for OldResultFinder<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> intravisit::NestedVisitorMap<Self::Map> {
+        intravisit::NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        if self.in_old {
+            // Looking for `result` in the argument of an `old(..)` call.
+            if let hir::ExprKind::Path(hir::QPath::Resolved(None, path)) = ex.kind {
+                if path.res == hir::def::Res::Local(self.result) {
+                    self.spans.push(ex.span);
+                }
+            }
+            intravisit::walk_expr(self, ex);
+        } else if self.is_old_call(ex) && self.mentions_result(ex) {
+            self.spans.push(ex.span);
+        } else {
+            intravisit::walk_expr(self, ex);
+        }
+    }
+}
+
+/// Report an error for every call of a closure in a specification, e.g. of a
+/// closure argument `f` in `#[requires(forall(|v: i32| f(v) >= v))]`.
+/// Specifications of closures are not supported, so the behavior of a closure
+/// is unknown to the verifier and such a call cannot be encoded.
+pub fn check_closure_calls<'tcx>(
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    let mut visitor = ClosureCallVisitor { env };
+    for (_, assertion) in specs.iter_sorted(env.tcx()) {
+        visitor.visit_assertion(assertion);
+    }
+}
+
+struct ClosureCallVisitor<'a, 'tcx> {
+    env: &'a Environment<'tcx>,
+}
+
+impl<'a, 'tcx> AssertionVisitor<'tcx> for ClosureCallVisitor<'a, 'tcx> {
+    fn visit_expr(&mut self, expr: &typed::Expression) {
+        let tcx = self.env.tcx();
+        let hir_map = tcx.hir();
+        let body_id = hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(expr.expr));
+        let mut finder = ClosureCallFinder { tcx, spans: Vec::new() };
+        finder.visit_body(hir_map.body(body_id));
+        for span in finder.spans {
+            PrustiError::unsupported(
+                "calls of closures in specifications are not supported",
+                MultiSpan::from_span(span),
+            ).set_help(
+                "specifications cannot describe closures yet; \
+                consider taking a `#[pure]` function instead"
+            ).emit(self.env);
+        }
+    }
+}
+
+/// Collects the spans of the calls whose callee is not a function item, i.e.
+/// a closure, a function pointer or a value of a type implementing `Fn`.
+struct ClosureCallFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    spans: Vec<Span>,
+}
+
+impl<'tcx> Visitor<'tcx> for ClosureCallFinder<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> intravisit::NestedVisitorMap<Self::Map> {
+        intravisit::NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        if let hir::ExprKind::Call(ref callee_expr, _) = ex.kind {
+            let callee_ty = self.tcx.typeck(callee_expr.hir_id.owner).expr_ty(callee_expr);
+            if !matches!(callee_ty.kind(), ty::TyKind::FnDef(..)) {
+                self.spans.push(ex.span);
+            }
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+/// Warn about every subtraction of unsigned integers in the pre- and
+/// postconditions of `procedures` whose minuend is not compared to anything
+/// by a guard of the subtraction, such as `index - 1` without `index > 0`.
+/// Such a subtraction underflows when the minuend is too small. The guards are
+/// the preconditions of the procedure, the left-hand sides of enclosing
+/// implications, the preceding conjuncts, and the conditions of enclosing
+/// `&&` and `if` expressions.
+pub fn check_unsigned_subtractions<'tcx>(
+    procedures: &[ProcedureDefId],
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    let tcx = env.tcx();
+    for &def_id in procedures {
+        let attrs = tcx.get_attrs(def_id);
+        let lookup = |attr_name| -> Vec<&typed::Assertion<'tcx>> {
+            read_prusti_attrs(attr_name, attrs)
+                .into_iter()
+                .filter_map(|raw_spec_id| raw_spec_id.try_into().ok())
+                .filter_map(|spec_id: typed::SpecificationId| specs.get(&spec_id))
+                .collect()
+        };
+        let preconditions = lookup("pre_spec_id_ref");
+        let postconditions = lookup("post_spec_id_ref");
+
+        let mut checker = SubtractionChecker { env, guards: Vec::new() };
+        for precondition in &preconditions {
+            checker.check_assertion(precondition);
+            checker.push_assertion_guards(precondition);
+        }
+        for postcondition in &postconditions {
+            checker.check_assertion(postcondition);
+        }
+    }
+}
+
+struct SubtractionChecker<'a, 'tcx> {
+    env: &'a Environment<'tcx>,
+    /// The source of the operands of the comparisons that guard the
+    /// assertion or expression being checked.
+    guards: Vec<String>,
+}
+
+impl<'a, 'tcx> SubtractionChecker<'a, 'tcx> {
+    fn check_assertion(&mut self, assertion: &typed::Assertion<'tcx>) {
+        let depth = self.guards.len();
+        match *assertion.kind {
+            typed::AssertionKind::Expr(ref expr) => self.check_expression(expr),
+            typed::AssertionKind::Matches(ref scrutinee, _) => self.check_expression(scrutinee),
+            typed::AssertionKind::And(ref conjuncts) => {
+                for conjunct in conjuncts {
+                    self.check_assertion(conjunct);
+                    self.push_assertion_guards(conjunct);
+                }
+            }
+            typed::AssertionKind::Or(ref disjuncts) => {
+                for disjunct in disjuncts {
+                    self.check_assertion(disjunct);
+                }
+            }
+            typed::AssertionKind::Implies(ref lhs, ref rhs) => {
+                self.check_assertion(lhs);
+                self.push_assertion_guards(lhs);
+                self.check_assertion(rhs);
+            }
+            typed::AssertionKind::Iff(ref lhs, ref rhs) => {
+                self.check_assertion(lhs);
+                self.check_assertion(rhs);
+            }
+            typed::AssertionKind::TypeCond(_, ref body) |
+            typed::AssertionKind::ForAll(_, _, ref body) |
+            typed::AssertionKind::Exists(_, _, ref body) => self.check_assertion(body),
+            typed::AssertionKind::BoundedForAll(_, ref lower, ref upper, ref body) => {
+                for bound in &[lower, upper] {
+                    self.check_expression(bound);
+                    self.push_closure_guards(bound.expr);
+                }
+                self.check_assertion(body);
+            }
+            typed::AssertionKind::Let(_, ref value, ref body) => {
+                self.check_expression(value);
+                self.check_assertion(body);
+            }
+        }
+        self.guards.truncate(depth);
+    }
+
+    /// Adds the comparisons of `assertion` to the guards, if it is an
+    /// expression or a conjunction of expressions.
+    fn push_assertion_guards(&mut self, assertion: &typed::Assertion<'tcx>) {
+        match *assertion.kind {
+            typed::AssertionKind::Expr(ref expr) => self.push_closure_guards(expr.expr),
+            typed::AssertionKind::And(ref conjuncts) => {
+                for conjunct in conjuncts {
+                    self.push_assertion_guards(conjunct);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_closure_guards(&mut self, closure: LocalDefId) {
+        let body = closure_body(closure, self.env.tcx());
+        self.push_guards(&body.value);
+    }
+
+    /// Adds the operands of the comparisons of the conjunction `cond` to the
+    /// guards.
+    fn push_guards(&mut self, cond: &hir::Expr<'tcx>) {
+        match cond.kind {
+            hir::ExprKind::Binary(op, lhs, rhs) => match op.node {
+                hir::BinOpKind::And => {
+                    self.push_guards(lhs);
+                    self.push_guards(rhs);
+                }
+                hir::BinOpKind::Gt | hir::BinOpKind::Ge | hir::BinOpKind::Lt |
+                hir::BinOpKind::Le | hir::BinOpKind::Ne => {
+                    for operand in &[lhs, rhs] {
+                        if let Some(source) = self.source(operand) {
+                            self.guards.push(source);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            hir::ExprKind::Block(block, _) if block.stmts.is_empty() => {
+                if let Some(expr) = block.expr {
+                    self.push_guards(expr);
+                }
+            }
+            hir::ExprKind::DropTemps(inner) => self.push_guards(inner),
+            _ => {}
+        }
+    }
+
+    fn check_expression(&mut self, expr: &typed::Expression) {
+        let body = closure_body(expr.expr, self.env.tcx());
+        self.visit_expr(&body.value);
+    }
+
+    fn source(&self, expr: &hir::Expr<'tcx>) -> Option<String> {
+        self.env.tcx().sess.source_map().span_to_snippet(expr.span).ok()
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for SubtractionChecker<'a, 'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> intravisit::NestedVisitorMap<Self::Map> {
+        // The closures of nested expressions are checked on their own.
+        intravisit::NestedVisitorMap::None
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        let depth = self.guards.len();
+        match ex.kind {
+            hir::ExprKind::Binary(op, lhs, rhs) if op.node == hir::BinOpKind::And => {
+                self.visit_expr(lhs);
+                self.push_guards(lhs);
+                self.visit_expr(rhs);
+            }
+            hir::ExprKind::Match(cond, arms, hir::MatchSource::IfDesugar { .. }) => {
+                // The first arm is the `then` branch.
+                self.visit_expr(cond);
+                self.push_guards(cond);
+                self.visit_expr(arms[0].body);
+                self.guards.truncate(depth);
+                for arm in &arms[1..] {
+                    self.visit_expr(&arm.body);
+                }
+            }
+            hir::ExprKind::Binary(op, lhs, rhs) if op.node == hir::BinOpKind::Sub => {
+                let tcx = self.env.tcx();
+                let ty = tcx.typeck(ex.hir_id.owner).expr_ty(ex);
+                let is_unsigned = matches!(ty.kind(), ty::TyKind::Uint(_));
+                // Subtractions of constants (`2 - 1`) and of zero can not
+                // underflow.
+                let is_checked = is_unsigned
+                    && !matches!(lhs.kind, hir::ExprKind::Lit(_))
+                    && !is_zero_literal(rhs);
+                if let Some(minuend) = self.source(lhs).filter(|_| is_checked) {
+                    if !self.guards.contains(&minuend) {
+                        PrustiError::warning(
+                            format!(
+                                "the subtraction may underflow, because `{}` is unsigned \
+                                and not known to be large enough",
+                                minuend,
+                            ),
+                            MultiSpan::from_span(ex.span),
+                        )
+                            .set_help(format!(
+                                "consider guarding the subtraction with a condition such as `{} > 0`",
+                                minuend,
+                            ))
+                            .emit(self.env);
+                    }
+                }
+                intravisit::walk_expr(self, ex);
+            }
+            _ => intravisit::walk_expr(self, ex),
+        }
+        self.guards.truncate(depth);
+    }
+}
+
+fn is_zero_literal(expr: &hir::Expr) -> bool {
+    match expr.kind {
+        hir::ExprKind::Lit(ref lit) => matches!(lit.node, ast::LitKind::Int(0, _)),
+        _ => false,
+    }
+}
+
+/// Warn about every precondition or postcondition of `procedures` that
+/// duplicates an earlier one of the same procedure, e.g. a `#[requires]` that
+/// was accidentally written twice. Two assertions are duplicates if they have
+/// the same structure, their bound variables have the same types and the
+/// source text at each of their spans is the same.
+pub fn check_duplicate_contracts<'tcx>(
+    procedures: &[ProcedureDefId],
+    specs: &typed::SpecificationMap<'tcx>,
+    env: &Environment<'tcx>,
+) {
+    let tcx = env.tcx();
+    for &def_id in procedures {
+        let attrs = tcx.get_attrs(def_id);
+        let lookup = |attr_name| -> Vec<&typed::Assertion<'tcx>> {
+            read_prusti_attrs(attr_name, attrs)
+                .into_iter()
+                .filter_map(|raw_spec_id| raw_spec_id.try_into().ok())
+                .filter_map(|spec_id: typed::SpecificationId| specs.get(&spec_id))
+                .collect()
+        };
+        let mir = env.mir(def_id.expect_local());
+        let contracts = [("pre_spec_id_ref", "precondition"), ("post_spec_id_ref", "postcondition")];
+        for &(attr_name, kind) in &contracts {
+            let assertions = lookup(attr_name);
+            let spans: Vec<Vec<Span>> = assertions.iter()
+                .map(|assertion| typed::Spanned::get_spans(*assertion, &mir, tcx))
+                .collect();
+            for (index, assertion) in assertions.iter().enumerate() {
+                let original = (0..index).find(|&earlier| {
+                    is_duplicate_contract(assertions[earlier], assertion, &spans[earlier], &spans[index], env)
+                });
+                if let Some(original) = original {
+                    let mut span = MultiSpan::from_spans(spans[index].clone());
+                    if let Some(&original_span) = spans[original].first() {
+                        span.push_span_label(original_span, format!("the {} is first written here", kind));
+                    }
+                    PrustiError::warning(format!("duplicate {}", kind), span)
+                        .set_help(format!("remove the duplicate {}", kind))
+                        .emit(env);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `duplicate`, with the spans `duplicate_spans`, is a copy of
+/// `original`, see `check_duplicate_contracts`.
+fn is_duplicate_contract<'tcx>(
+    original: &typed::Assertion<'tcx>,
+    duplicate: &typed::Assertion<'tcx>,
+    original_spans: &[Span],
+    duplicate_spans: &[Span],
+    env: &Environment<'tcx>,
+) -> bool {
+    // The expressions are compared by their source text below
+    let same_structure = original.alpha_eq_with(
+        duplicate,
+        &|(_, lhs_ty), (_, rhs_ty)| lhs_ty == rhs_ty,
+        &|_, _, _| true,
+    );
+    let snippet = |span: &Span| env.codemap().span_to_snippet(*span).ok();
+    same_structure &&
+        !original_spans.is_empty() &&
+        original_spans.len() == duplicate_spans.len() &&
+        original_spans.iter().zip(duplicate_spans).all(|(original, duplicate)| {
+            let original = snippet(original);
+            original.is_some() && original == snippet(duplicate)
+        })
+}
+
+/// The body of the closure `closure`.
+fn closure_body<'tcx>(closure: LocalDefId, tcx: TyCtxt<'tcx>) -> &'tcx hir::Body<'tcx> {
+    let hir_map = tcx.hir();
+    hir_map.body(hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(closure)))
+}
@@ -17,7 +17,8 @@ pub struct ExternSpecResolver<'tcx> {
     /// Maps real functions (keyed by their `DefId`) to Prusti-generated fake
     /// functions with specifications. The mapping may also optionally contain
     /// the `DefId` of the implementing type to account for trait
-    /// implementations.
+    /// implementations. The span of the external specification is kept for
+    /// diagnostics.
     extern_fn_map: ExternSpecificationMap<'tcx>,
 
     /// Duplicate specifications detected, keyed by the `DefId` of the function
@@ -57,7 +58,7 @@ impl<'tcx> ExternSpecResolver<'tcx> {
         let current_def_id = self.tcx.hir().local_def_id(id).to_def_id();
         if let Some((def_id, impl_ty, span)) = visitor.spec_found {
             match self.extern_fn_map.get(&def_id) {
-                Some((existing_impl_ty, _)) if existing_impl_ty == &impl_ty => {
+                Some((existing_impl_ty, _, _)) if existing_impl_ty == &impl_ty => {
                     match self.spec_duplicates.get_mut(&def_id) {
                         Some(dups) => {
                             dups.push((current_def_id, span));
@@ -69,7 +70,7 @@ impl<'tcx> ExternSpecResolver<'tcx> {
                 }
                 _ => {
                     // TODO: what if def_id was present, but impl_ty was different?
-                    self.extern_fn_map.insert(def_id, (impl_ty, current_def_id));
+                    self.extern_fn_map.insert(def_id, (impl_ty, current_def_id, span));
                 }
             }
         }
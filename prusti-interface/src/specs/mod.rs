@@ -1,16 +1,20 @@
-use prusti_specs::specifications::{json::Assertion as JsonAssertion, SpecType};
+use prusti_specs::specifications::{json::Specification as JsonSpecification, SpecType};
 use rustc_ast::ast;
 use rustc_hir::{intravisit, ItemKind};
 use rustc_middle::hir::map::Map;
 use rustc_middle::ty::TyCtxt;
-use rustc_span::Span;
+use rustc_span::{MultiSpan, Span};
 use rustc_span::symbol::Symbol;
 use rustc_hir::def_id::LocalDefId;
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::io::Read;
+use prusti_common::config;
 use crate::environment::Environment;
+use crate::PrustiError;
 use crate::utils::{has_spec_only_attr, has_extern_spec_attr, read_prusti_attr, has_prusti_attr};
 
+pub mod checks;
 pub mod external;
 pub mod typed;
 
@@ -19,13 +23,17 @@ use std::fmt;
 use crate::specs::external::ExternSpecResolver;
 use prusti_specs::specifications::common::SpecificationId;
 
-struct SpecItem {
+/// A specification item, whose JSON specification is only deserialized when
+/// it is reconstructed, so that the JSON of all the items is never held in
+/// memory at once.
+struct SpecItem<'tcx> {
     spec_id: typed::SpecificationId,
     spec_type: SpecType,
-    specification: JsonAssertion,
+    attrs: &'tcx [ast::Attribute],
+    span: Span,
 }
 
-impl fmt::Debug for SpecItem {
+impl<'tcx> fmt::Debug for SpecItem<'tcx> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SpecItem")
          .field("spec_id", &self.spec_id)
@@ -40,7 +48,7 @@ struct Item<'tcx> {
 
 pub struct SpecCollector<'tcx> {
     tcx: TyCtxt<'tcx>,
-    spec_items: Vec<SpecItem>,
+    spec_items: Vec<SpecItem<'tcx>>,
     typed_expressions: HashMap<String, LocalDefId>,
     extern_resolver: ExternSpecResolver<'tcx>,
 }
@@ -55,20 +63,83 @@ impl<'tcx> SpecCollector<'tcx> {
         }
     }
 
-    pub fn determine_typed_procedure_specs(self) -> typed::SpecificationMap<'tcx> {
-        let typed_expressions = self.typed_expressions;
-        let tcx = self.tcx;
-        self.spec_items
-            .into_iter()
-            .map(|spec_item| {
-                let assertion = reconstruct_typed_assertion(
-                    spec_item.specification,
-                    &typed_expressions,
-                    tcx
-                );
-                (spec_item.spec_id, assertion)
-            })
-            .collect()
+    /// Reconstruct the typed specifications of all collected specification
+    /// items. Specifications that cannot be reconstructed are reported as
+    /// errors, in which case the compilation is aborted.
+    /// Each specification item is parsed and reconstructed with
+    /// `load_specs_streaming`, so that its JSON is dropped before the next
+    /// item is read.
+    pub fn determine_typed_procedure_specs(mut self, env: &Environment<'tcx>) -> typed::SpecificationMap<'tcx> {
+        let mut errors_found = false;
+        let mut typed_specs = typed::SpecificationMap::new();
+        for spec_item in std::mem::take(&mut self.spec_items) {
+            let json_string = read_prusti_attr("assertion", spec_item.attrs)
+                .expect("could not find prusti::assertion");
+            self.load_specs_streaming(json_string.as_bytes(), spec_item.span, |assertion| {
+                match assertion {
+                    Ok(assertion) => {
+                        debug_assert!(
+                            typed::round_trips(&assertion, &self.typed_expressions, self.tcx),
+                            "the typed specification does not survive a round trip through JSON",
+                        );
+                        // Two items with the same id would otherwise silently
+                        // replace one another's specification.
+                        let item_specs = std::iter::once((spec_item.spec_id, assertion)).collect();
+                        if let Err(conflicts) = typed::merge_specs(&mut typed_specs, item_specs) {
+                            PrustiError::incorrect(
+                                format!(
+                                    "the specification id {} is used by several specification items",
+                                    conflicts[0],
+                                ),
+                                MultiSpan::from_span(spec_item.span),
+                            ).emit(env);
+                            errors_found = true;
+                        }
+                    }
+                    Err(error) => {
+                        error.emit(env);
+                        errors_found = true;
+                    }
+                }
+            });
+        }
+        if errors_found {
+            self.tcx.sess.abort_if_errors();
+        }
+        typed_specs
+    }
+
+    /// Reconstruct the typed specifications read from `reader`, which
+    /// contains a sequence of JSON specifications as emitted in the
+    /// `prusti::assertion` attributes. Each specification is reconstructed and
+    /// passed to `callback` before the next one is parsed. The expressions of
+    /// the specifications are resolved among the typed expressions collected in
+    /// this crate. Reading stops at the first malformed specification, which is
+    /// passed to `callback` as an internal error. All errors are reported at
+    /// `span`, the span of the item that the specifications were read from.
+    pub fn load_specs_streaming<R: Read>(
+        &self,
+        reader: R,
+        span: Span,
+        mut callback: impl FnMut(Result<typed::Assertion<'tcx>, PrustiError>),
+    ) {
+        for specification in JsonSpecification::read_all(reader) {
+            match specification {
+                Ok(specification) => callback(reconstruct_typed_assertion(
+                    specification,
+                    span,
+                    &self.typed_expressions,
+                    self.tcx,
+                )),
+                Err(error) => {
+                    callback(Err(PrustiError::internal(
+                        format!("cannot parse the specification: {}", error),
+                        MultiSpan::from_span(span),
+                    )));
+                    return;
+                }
+            }
+        }
     }
 
     pub fn determine_extern_procedure_specs(&self, env: &Environment<'tcx>) -> typed::ExternSpecificationMap<'tcx> {
@@ -78,17 +149,30 @@ impl<'tcx> SpecCollector<'tcx> {
 }
 
 fn reconstruct_typed_assertion<'tcx>(
-    assertion: JsonAssertion,
+    specification: JsonSpecification,
+    span: Span,
     typed_expressions: &HashMap<String, LocalDefId>,
     tcx: TyCtxt<'tcx>
-) -> typed::Assertion<'tcx> {
-    assertion.to_typed(typed_expressions, tcx)
-}
-
-fn deserialize_spec_from_attrs(attrs: &[ast::Attribute]) -> JsonAssertion {
-    let json_string = read_prusti_attr("assertion", attrs)
-        .expect("could not find prusti::assertion");
-    JsonAssertion::from_json_string(&json_string)
+) -> Result<typed::Assertion<'tcx>, PrustiError> {
+    typed::check_specification_version(&specification, span)?;
+    // The reconstruction is recursive, so refuse the specifications that are
+    // nested too deeply instead of overflowing the stack.
+    let depth = specification.assertion.depth();
+    let max_depth = config::max_assertion_depth();
+    if depth > max_depth {
+        return Err(PrustiError::incorrect(
+            format!(
+                "the specification is nested too deeply ({} levels, the limit is {})",
+                depth,
+                max_depth,
+            ),
+            MultiSpan::from_span(span),
+        ).set_help("the limit can be raised with the MAX_ASSERTION_DEPTH flag"));
+    }
+    // The conversion does not know the item that the specification was read
+    // from, so its errors are reported at the item.
+    specification.to_typed(typed_expressions, tcx)
+        .map_err(|error| error.set_missing_span(MultiSpan::from_span(span)))
 }
 
 impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
@@ -124,30 +208,33 @@ impl<'tcx> intravisit::Visitor<'tcx> for SpecCollector<'tcx> {
         if let Some(raw_spec_id) = read_prusti_attr("spec_id", fn_kind.attrs()) {
             let spec_id: SpecificationId = raw_spec_id.try_into()
                 .expect("failed conversion to SpecificationId");
-            let specification = deserialize_spec_from_attrs(fn_kind.attrs());
 
             // Detect the kind of specification
             let spec_type = if has_prusti_attr(fn_kind.attrs(), "loop_body_invariant_spec") {
                 SpecType::Invariant
+            } else if has_prusti_attr(fn_kind.attrs(), "assert_spec") {
+                SpecType::Assert
             } else {
                 let fn_name = match fn_kind {
                     intravisit::FnKind::ItemFn(ref ident, ..) |
                     intravisit::FnKind::Method(ref ident, ..) => ident.name.to_ident_string(),
                     intravisit::FnKind::Closure(..) => unreachable!(
                         "a closure is annotated with prusti::spec_id but not with \
-                        prusti::loop_body_invariant_spec"
+                        prusti::loop_body_invariant_spec or prusti::assert_spec"
                     ),
                 };
                 if fn_name.starts_with("prusti_pre_item_") {
                     SpecType::Precondition
                 } else if fn_name.starts_with("prusti_post_item_") {
                     SpecType::Postcondition
+                } else if fn_name.starts_with("prusti_pred_item_") {
+                    SpecType::Predicate
                 } else {
                     unreachable!()
                 }
             };
 
-            let spec_item = SpecItem {spec_id, spec_type, specification};
+            let spec_item = SpecItem {spec_id, spec_type, attrs: fn_kind.attrs(), span};
             self.spec_items.push(spec_item);
         }
     }
@@ -1,13 +1,33 @@
 use prusti_specs::specifications::common;
 use prusti_specs::specifications::json;
+use prusti_specs::specifications::triggers::TriggerStrategy;
+use rustc_hir as hir;
 use rustc_hir::BodyId;
-use rustc_hir::def_id::LocalDefId;
+use rustc_hir::def_id::{DefId, LocalDefId, LOCAL_CRATE};
+use rustc_hir::definitions::DefPathHash;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_index::vec::Idx;
+use rustc_ast::ast;
+use rustc_data_structures::stable_hasher::{HashStable, StableHasher};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ich::StableHashingContext;
+use rustc_middle::mir::interpret::{ConstValue, Scalar};
 use rustc_middle::{mir, ty::{self, TyCtxt}};
-use rustc_span::Span;
-use std::collections::HashMap;
+use rustc_middle::ty::fold::TypeFoldable;
+use rustc_span::{BytePos, FileName, Span, MultiSpan, Symbol};
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::source_map::SourceMap;
+use std::cell::{RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 
-pub use common::{ExpressionId, SpecType, SpecificationId};
+pub use common::{ExpressionId, SpecType, SpecificationId, SpecificationIdGenerator};
 use crate::data::ProcedureDefId;
+use crate::PrustiError;
+use crate::utils::{has_spec_only_attr, read_prusti_attr};
+use prusti_common::config;
 
 /// A specification that has no types associated with it.
 pub type Specification<'tcx> = common::Specification<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
@@ -17,16 +37,80 @@ pub type SpecificationSet<'tcx> = common::SpecificationSet<ExpressionId, LocalDe
 pub type LoopSpecification<'tcx> = common::LoopSpecification<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
 /// A set of untyped specifications associated with a procedure.
 pub type ProcedureSpecification<'tcx> = common::ProcedureSpecification<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
-/// A map of untyped specifications for a specific crate.
-pub type SpecificationMap<'tcx> = HashMap<common::SpecificationId, Assertion<'tcx>>;
-/// A map of untyped external specifications.
-pub type ExternSpecificationMap<'tcx> = HashMap<ProcedureDefId, (Option<ProcedureDefId>, ProcedureDefId)>;
+/// A map of untyped specifications for a specific crate. It dereferences to
+/// the map from specification ids to assertions, and additionally memoizes
+/// the spans of the assertions, which `remap_spans` keeps up to date when the
+/// source code is edited.
+#[derive(Debug, Default)]
+pub struct SpecificationMap<'tcx> {
+    assertions: HashMap<common::SpecificationId, Assertion<'tcx>>,
+    spans: RefCell<SpannedCache>,
+}
+
+impl<'tcx> SpecificationMap<'tcx> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The memoized spans of the assertions of the map. Spans computed through
+    /// this cache are adjusted to all the edits passed to `remap_spans`.
+    pub fn span_cache(&self) -> RefMut<SpannedCache> {
+        self.spans.borrow_mut()
+    }
+}
+
+impl<'tcx> Deref for SpecificationMap<'tcx> {
+    type Target = HashMap<common::SpecificationId, Assertion<'tcx>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.assertions
+    }
+}
+
+impl<'tcx> DerefMut for SpecificationMap<'tcx> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.assertions
+    }
+}
+
+impl<'tcx> FromIterator<(common::SpecificationId, Assertion<'tcx>)> for SpecificationMap<'tcx> {
+    fn from_iter<I: IntoIterator<Item = (common::SpecificationId, Assertion<'tcx>)>>(iter: I) -> Self {
+        Self {
+            assertions: iter.into_iter().collect(),
+            spans: RefCell::default(),
+        }
+    }
+}
+
+impl<'tcx> IntoIterator for SpecificationMap<'tcx> {
+    type Item = (common::SpecificationId, Assertion<'tcx>);
+    type IntoIter = std::collections::hash_map::IntoIter<common::SpecificationId, Assertion<'tcx>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.assertions.into_iter()
+    }
+}
+
+/// Shifts the spans of the assertions of `map` after an edit of the source
+/// code, so that they still point at the same code without parsing and
+/// type-checking the specifications again. This applies to every span of the
+/// `Spanned` traversal that is computed through `map.span_cache()`.
+pub fn remap_spans(map: &mut SpecificationMap, edit: SourceEdit) {
+    map.spans.get_mut().remap_spans(edit);
+}
+
+/// A map of untyped external specifications. Each entry maps a function to the
+/// `DefId` of the implementing type (if any), the `DefId` of the function
+/// carrying the specification, and the span of the external specification.
+pub type ExternSpecificationMap<'tcx> = HashMap<ProcedureDefId, (Option<ProcedureDefId>, ProcedureDefId, Span)>;
 /// An assertion that has no types associated with it.
 pub type Assertion<'tcx> = common::Assertion<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
 /// An assertion kind that has no types associated with it.
 pub type AssertionKind<'tcx> = common::AssertionKind<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
 /// An expression that has no types associated with it.
 pub type Expression = common::Expression<ExpressionId, LocalDefId>;
+/// A pattern that has no types associated with it.
+pub type Pattern = common::Pattern<ExpressionId, LocalDefId>;
 /// A trigger set that has no types associated with it.
 pub type TriggerSet = common::TriggerSet<ExpressionId, LocalDefId>;
 /// For all variables that have no types associated with it.
@@ -35,6 +119,510 @@ pub type ForAllVars<'tcx> = common::ForAllVars<ExpressionId, (mir::Local, ty::Ty
 pub type Trigger = common::Trigger<ExpressionId, LocalDefId>;
 /// A pledge in the postcondition.
 pub type Pledge<'tcx> = common::Pledge<ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
+/// Whether a pledge in the postcondition is conditional.
+pub type PledgeKind<'a, 'tcx> = common::PledgeKind<'a, ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
+/// The assertions of a specification set grouped by their role.
+pub type PartitionedSpecs<'a, 'tcx> = common::PartitionedSpecs<'a, ExpressionId, LocalDefId, (mir::Local, ty::Ty<'tcx>)>;
+
+/// Constructs typed assertions, so that callers that generate specifications
+/// do not need to box the assertion kinds themselves.
+pub struct AssertionBuilder;
+
+impl AssertionBuilder {
+    /// The conjunction of `conjuncts`.
+    pub fn and<'tcx>(conjuncts: Vec<Assertion<'tcx>>) -> Assertion<'tcx> {
+        Self::from_kind(AssertionKind::And(conjuncts))
+    }
+
+    /// The implication `lhs ==> rhs`.
+    pub fn implies<'tcx>(lhs: Assertion<'tcx>, rhs: Assertion<'tcx>) -> Assertion<'tcx> {
+        Self::from_kind(AssertionKind::Implies(lhs, rhs))
+    }
+
+    fn from_kind<'tcx>(kind: AssertionKind<'tcx>) -> Assertion<'tcx> {
+        Assertion {
+            kind: Box::new(kind),
+        }
+    }
+}
+
+/// Merges the specifications of `other` into `base`, e.g. the specifications
+/// exported by a dependency into the ones of the current crate.
+///
+/// Existing specifications are never overwritten: if some ids of `other` are
+/// already present in `base`, those entries are skipped and their ids are
+/// returned as the error. All other entries are merged regardless.
+pub fn merge_specs<'tcx>(
+    base: &mut SpecificationMap<'tcx>,
+    other: SpecificationMap<'tcx>,
+) -> Result<(), Vec<SpecificationId>> {
+    let mut conflicts = Vec::new();
+    for (spec_id, assertion) in other {
+        if base.contains_key(&spec_id) {
+            conflicts.push(spec_id);
+        } else {
+            base.insert(spec_id, assertion);
+        }
+    }
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        conflicts.sort();
+        Err(conflicts)
+    }
+}
+
+/// Deterministic iteration over the entries of a `SpecificationMap`, so that
+/// diagnostics are reported in the same order in every run.
+pub trait SortedSpecificationMap<'tcx> {
+    /// Iterates over the entries ordered by the item that they are attached
+    /// to and then by their position in the source code. Unlike the
+    /// `SpecificationId`s, which are generated randomly, this order is the same
+    /// in every run. Only entries at the same position are ordered by id.
+    fn iter_sorted(&self, tcx: TyCtxt<'tcx>) -> std::vec::IntoIter<(&SpecificationId, &Assertion<'tcx>)>;
+}
+
+impl<'tcx> SortedSpecificationMap<'tcx> for SpecificationMap<'tcx> {
+    fn iter_sorted(&self, tcx: TyCtxt<'tcx>) -> std::vec::IntoIter<(&SpecificationId, &Assertion<'tcx>)> {
+        let mut entries: Vec<_> = self.iter()
+            .map(|(spec_id, assertion)| ((stable_spec_key(assertion, tcx), *spec_id), (spec_id, assertion)))
+            .collect();
+        entries.sort_by_key(|&(key, _)| key);
+        entries.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>().into_iter()
+    }
+}
+
+/// A key of the specification `assertion` that does not depend on the random
+/// names of the generated specification items: the `DefPathHash` of the item
+/// that the specification is attached to and the span of its first
+/// expression. The item is the one that encloses the specification function
+/// generated for a contract, or the function of a loop invariant. Assertions
+/// without expressions have no key.
+fn stable_spec_key<'tcx>(
+    assertion: &Assertion<'tcx>,
+    tcx: TyCtxt<'tcx>,
+) -> Option<(DefPathHash, BytePos, BytePos)> {
+    let expr = assertion.expressions().next()?;
+    let item = annotated_item(expr, tcx)?;
+    let span = tcx.def_span(expr.expr);
+    Some((tcx.def_path_hash(item), span.lo(), span.hi()))
+}
+
+/// The item that the specification of `expr` is attached to.
+fn annotated_item<'tcx>(expr: &Expression, tcx: TyCtxt<'tcx>) -> Option<DefId> {
+    let item = tcx.closure_base_def_id(expr.expr.to_def_id());
+    if has_spec_only_attr(tcx.get_attrs(item)) {
+        tcx.parent(item)
+    } else {
+        Some(item)
+    }
+}
+
+/// Returns the spans of all the specifications of `map`, each together with
+/// the id of the specification that it belongs to. A caller can intersect the
+/// spans with the edited parts of a file to determine which specifications
+/// need to be verified again. The entries are ordered by span, and the spans
+/// reflect the edits passed to `remap_spans`.
+pub fn spans_to_spec_ids<'tcx>(
+    map: &SpecificationMap<'tcx>,
+    mir_body: &mir::Body<'tcx>,
+    tcx: TyCtxt<'tcx>,
+) -> Vec<(Span, SpecificationId)> {
+    let mut cache = map.span_cache();
+    let mut index: Vec<_> = map
+        .iter_sorted(tcx)
+        .flat_map(|(spec_id, assertion)| {
+            assertion
+                .get_spans_cached(mir_body, tcx, &mut *cache)
+                .into_iter()
+                .map(move |span| (span, *spec_id))
+        })
+        .collect();
+    index.sort_by_key(|&(span, spec_id)| (span.lo(), span.hi(), spec_id));
+    index
+}
+
+/// This trait is implemented for specification expressions to compute which
+/// local variables they read, e.g. for frame inference.
+pub trait LocalReferences<'tcx> {
+    /// Returns the locals captured by the closure of the expression. The
+    /// locals belong to the MIR body in which the closure is defined (the
+    /// specification function, or the closure of the enclosing quantifier).
+    /// Locals that are captured by reference are resolved to the borrowed
+    /// local.
+    fn references_local(&self, tcx: TyCtxt<'tcx>) -> HashSet<mir::Local>;
+}
+
+impl<'tcx> LocalReferences<'tcx> for Expression {
+    fn references_local(&self, tcx: TyCtxt<'tcx>) -> HashSet<mir::Local> {
+        let closure_def_id = self.expr.to_def_id();
+        let outer_def_id = tcx.parent(closure_def_id)
+            .expect("a specification closure must have a parent");
+        let (body, _) = tcx.mir_promoted(ty::WithOptConstParam::unknown(outer_def_id.expect_local()));
+        let body = body.borrow();
+
+        let mut borrowed = HashMap::new();
+        let mut captured = Vec::new();
+        for bb_data in body.basic_blocks() {
+            for stmt in &bb_data.statements {
+                if let mir::StatementKind::Assign(box (ref lhs, ref rvalue)) = stmt.kind {
+                    match *rvalue {
+                        mir::Rvalue::Ref(_, _, ref place) => {
+                            if let Some(local) = lhs.as_local() {
+                                borrowed.insert(local, place.local);
+                            }
+                        }
+                        mir::Rvalue::Aggregate(
+                            box mir::AggregateKind::Closure(def_id, _),
+                            ref operands,
+                        ) if def_id == closure_def_id => {
+                            captured.extend(operands.iter().filter_map(|operand| match operand {
+                                mir::Operand::Copy(place) | mir::Operand::Move(place) => {
+                                    Some(place.local)
+                                }
+                                mir::Operand::Constant(_) => None,
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        captured
+            .into_iter()
+            .map(|local| borrowed.get(&local).cloned().unwrap_or(local))
+            .collect()
+    }
+}
+
+/// This trait is implemented for specification expressions to inspect what
+/// their closure captures, to debug specifications that capture unexpected
+/// variables (see `-Zdump-spec-captures`).
+pub trait DumpCaptures<'tcx> {
+    /// Returns the name and type of each local captured by the closure of the
+    /// expression, as declared in the MIR body in which the closure is
+    /// defined, ordered by local. Locals without a name, such as temporaries,
+    /// are named after their index.
+    fn dump_captures(&self, tcx: TyCtxt<'tcx>) -> Vec<(String, ty::Ty<'tcx>)>;
+}
+
+impl<'tcx> DumpCaptures<'tcx> for Expression {
+    fn dump_captures(&self, tcx: TyCtxt<'tcx>) -> Vec<(String, ty::Ty<'tcx>)> {
+        let outer_def_id = tcx.parent(self.expr.to_def_id())
+            .expect("a specification closure must have a parent");
+        let (body, _) = tcx.mir_promoted(ty::WithOptConstParam::unknown(outer_def_id.expect_local()));
+        let body = body.borrow();
+
+        let mut captured: Vec<_> = self.references_local(tcx).into_iter().collect();
+        captured.sort();
+        captured
+            .into_iter()
+            .map(|local| {
+                let name = body.var_debug_info
+                    .iter()
+                    .find(|info| info.place.as_local() == Some(local))
+                    .map(|info| info.name.to_string())
+                    .unwrap_or_else(|| format!("{:?}", local));
+                (name, body.local_decls[local].ty)
+            })
+            .collect()
+    }
+}
+
+/// Returns a line for each expression of `map` that captures some local,
+/// listing the captured locals as `spec_id_expr_id: name: type, ..`. The lines
+/// are ordered by specification.
+pub fn dump_spec_captures<'tcx>(map: &SpecificationMap<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<String> {
+    struct CapturesVisitor<'tcx> {
+        tcx: TyCtxt<'tcx>,
+        lines: Vec<String>,
+    }
+
+    impl<'tcx> AssertionVisitor<'tcx> for CapturesVisitor<'tcx> {
+        fn visit_expr(&mut self, expr: &Expression) {
+            let captures = expr.dump_captures(self.tcx);
+            if !captures.is_empty() {
+                let captures: Vec<_> = captures
+                    .into_iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect();
+                self.lines.push(format!("{}_{}: {}", expr.spec_id, expr.id, captures.join(", ")));
+            }
+        }
+    }
+
+    let mut visitor = CapturesVisitor { tcx, lines: vec![] };
+    for (_, assertion) in map.iter_sorted(tcx) {
+        visitor.visit_assertion(assertion);
+    }
+    visitor.lines
+}
+
+/// Returns a line `item: hash` with the stable hash of each assertion of
+/// `map` that has expressions, where `item` is the path of the item that the
+/// assertion is attached to. The lines are ordered by specification, so the
+/// output of two compilations of the same crate is identical.
+pub fn dump_spec_hashes<'tcx>(map: &SpecificationMap<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<String> {
+    map.iter_sorted(tcx)
+        .filter_map(|(_, assertion)| {
+            let item = annotated_item(assertion.expressions().next()?, tcx)?;
+            Some(format!("{}: {:016x}", tcx.def_path_str(item), assertion.stable_hash(tcx)))
+        })
+        .collect()
+}
+
+/// Returns a line `item: lo..hi, ..` with the byte positions of the spans of
+/// each assertion of `map` that is attached to an item with a MIR body. The
+/// spans are computed through `map.span_cache()`, so they reflect the edits
+/// passed to `remap_spans`. The lines are ordered by specification.
+pub fn dump_spec_spans<'tcx>(map: &SpecificationMap<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<String> {
+    let mut cache = map.span_cache();
+    map.iter_sorted(tcx)
+        .filter_map(|(_, assertion)| {
+            let item = annotated_item(assertion.expressions().next()?, tcx)?;
+            if !tcx.is_mir_available(item) {
+                return None;
+            }
+            let spans: Vec<_> = assertion
+                .get_spans_cached(tcx.optimized_mir(item), tcx, &mut *cache)
+                .into_iter()
+                .map(|span| format!("{}..{}", span.lo().0, span.hi().0))
+                .collect();
+            Some(format!("{}: {}", tcx.def_path_str(item), spans.join(", ")))
+        })
+        .collect()
+}
+
+/// Returns a description of each assertion of `map` that is attached to an
+/// item with a MIR body, showing what the span and variable queries of this
+/// module compute for it (see `-Zdump-spec-details`). Each assertion is
+/// printed as rendered by `DisplayAssertion`, followed by
+/// * one line per expression with its source text, spans and line ranges and
+///   the result of `SubassertionAtSpan` for its span,
+/// * one line per quantifier with the result of `VarNames`.
+///
+/// The last line lists the spans of `spans_to_spec_ids`. Positions are byte
+/// positions.
+pub fn dump_spec_details<'tcx>(map: &SpecificationMap<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<String> {
+    fn collect<'a, 'tcx>(assertion: &'a Assertion<'tcx>, all: &mut Vec<&'a Assertion<'tcx>>) {
+        all.push(assertion);
+        for child in subassertions(assertion) {
+            collect(child, all);
+        }
+    }
+    let show_spans = |spans: Vec<Span>| {
+        spans.iter()
+            .map(|span| format!("{}..{}", span.lo().0, span.hi().0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let source_map = tcx.sess.source_map();
+    let mut lines = vec![];
+    let mut index_body = None;
+    for (_, assertion) in map.iter_sorted(tcx) {
+        let item = match assertion.expressions().next().and_then(|expr| annotated_item(expr, tcx)) {
+            Some(item) if tcx.is_mir_available(item) => item,
+            _ => continue,
+        };
+        let mir_body = tcx.optimized_mir(item);
+        index_body.get_or_insert(mir_body);
+        lines.push(format!("spec {}: {}", tcx.def_path_str(item), assertion.to_display(tcx, source_map)));
+        let mut all = vec![];
+        collect(assertion, &mut all);
+        for part in all {
+            match *part.kind {
+                AssertionKind::Expr(ref expr) => {
+                    let expr_spans = part.get_spans(mir_body, tcx);
+                    let ranges: Vec<_> = part.get_line_ranges(mir_body, tcx, source_map)
+                        .into_iter()
+                        .map(|(file, start_line, start_col, end_line, end_col)| {
+                            format!("{}:{}:{}-{}:{}", file, start_line, start_col, end_line, end_col)
+                        })
+                        .collect();
+                    let target = match expr_spans.first() {
+                        Some(&span) => span,
+                        None => continue,
+                    };
+                    let subassertion = assertion.subassertion_at_span(target, mir_body, tcx)
+                        .map(|subassertion| subassertion.to_display(tcx, source_map))
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "  expr `{}` at {} ({}): subassertion `{}`",
+                        expression_source(expr, tcx, source_map),
+                        show_spans(expr_spans),
+                        ranges.join(", "),
+                        subassertion,
+                    ));
+                }
+                AssertionKind::ForAll(ref vars, _, ref body) |
+                AssertionKind::Exists(ref vars, _, ref body) => {
+                    let closure = match first_expression(body).and_then(|expr| binding_closure(vars, expr, tcx)) {
+                        Some(closure) => closure,
+                        None => continue,
+                    };
+                    let closure_body = tcx.optimized_mir(closure.to_def_id());
+                    let names: Vec<_> = vars.var_names(closure_body)
+                        .into_iter()
+                        .map(|name| name.map(|name| name.to_string()).unwrap_or_default())
+                        .collect();
+                    lines.push(format!(
+                        "  quantifier `{}`: vars {}",
+                        part.to_display(tcx, source_map),
+                        names.join(", "),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some(mir_body) = index_body {
+        let index: Vec<_> = spans_to_spec_ids(map, mir_body, tcx)
+            .into_iter()
+            .map(|(span, _)| span)
+            .collect();
+        lines.push(format!("index {}", show_spans(index)));
+    }
+    lines
+}
+
+/// The `HirId` of the `result` parameter of the specification function in
+/// which the closure of `expr` is (transitively) nested. Returns `None` for the
+/// specifications without a result, such as preconditions and loop invariants.
+pub(crate) fn result_param<'tcx>(expr: &Expression, tcx: TyCtxt<'tcx>) -> Option<hir::HirId> {
+    let mut spec_item = expr.expr.to_def_id();
+    while tcx.is_closure(spec_item) {
+        spec_item = tcx.parent(spec_item).expect("a closure must have a parent");
+    }
+    if !has_spec_only_attr(tcx.get_attrs(spec_item)) {
+        return None;
+    }
+    let hir_map = tcx.hir();
+    let body = hir_map.body(hir_map.body_owned_by(
+        hir_map.local_def_id_to_hir_id(spec_item.expect_local())
+    ));
+    body.params.iter().find_map(|param| match param.pat.kind {
+        hir::PatKind::Binding(_, hir_id, ident, _) if &*ident.name.as_str() == "result" => {
+            Some(hir_id)
+        }
+        _ => None,
+    })
+}
+
+/// A visitor over the structure of typed assertions. Each method is called
+/// on the corresponding assertion kind and by default visits the nested
+/// expressions and assertions; implementors override the methods they are
+/// interested in. The quantified variables and triggers are not visited by
+/// default.
+pub trait AssertionVisitor<'tcx> {
+    fn visit_assertion(&mut self, assertion: &Assertion<'tcx>) {
+        walk_assertion(self, assertion);
+    }
+
+    fn visit_expr(&mut self, _expr: &Expression) {}
+
+    fn visit_matches(&mut self, scrutinee: &Expression, _pattern: &Pattern) {
+        self.visit_expr(scrutinee);
+    }
+
+    fn visit_and(&mut self, conjuncts: &[Assertion<'tcx>]) {
+        for conjunct in conjuncts {
+            self.visit_assertion(conjunct);
+        }
+    }
+
+    fn visit_or(&mut self, disjuncts: &[Assertion<'tcx>]) {
+        for disjunct in disjuncts {
+            self.visit_assertion(disjunct);
+        }
+    }
+
+    fn visit_implies(&mut self, lhs: &Assertion<'tcx>, rhs: &Assertion<'tcx>) {
+        self.visit_assertion(lhs);
+        self.visit_assertion(rhs);
+    }
+
+    fn visit_iff(&mut self, lhs: &Assertion<'tcx>, rhs: &Assertion<'tcx>) {
+        self.visit_assertion(lhs);
+        self.visit_assertion(rhs);
+    }
+
+    fn visit_forall(
+        &mut self,
+        _vars: &ForAllVars<'tcx>,
+        _trigger_set: &TriggerSet,
+        body: &Assertion<'tcx>,
+    ) {
+        self.visit_assertion(body);
+    }
+
+    fn visit_exists(
+        &mut self,
+        _vars: &ForAllVars<'tcx>,
+        _trigger_set: &TriggerSet,
+        body: &Assertion<'tcx>,
+    ) {
+        self.visit_assertion(body);
+    }
+
+    fn visit_bounded_forall(
+        &mut self,
+        _vars: &ForAllVars<'tcx>,
+        lower: &Expression,
+        upper: &Expression,
+        body: &Assertion<'tcx>,
+    ) {
+        walk_bounded_forall(self, lower, upper, body);
+    }
+
+    fn visit_type_cond(&mut self, _vars: &ForAllVars<'tcx>, body: &Assertion<'tcx>) {
+        self.visit_assertion(body);
+    }
+
+    fn visit_let(&mut self, _vars: &ForAllVars<'tcx>, value: &Expression, body: &Assertion<'tcx>) {
+        self.visit_expr(value);
+        self.visit_assertion(body);
+    }
+}
+
+/// Dispatches `assertion` to the visitor method of its kind.
+pub fn walk_assertion<'tcx, V: AssertionVisitor<'tcx> + ?Sized>(
+    visitor: &mut V,
+    assertion: &Assertion<'tcx>,
+) {
+    match *assertion.kind {
+        AssertionKind::Expr(ref expr) => visitor.visit_expr(expr),
+        AssertionKind::Matches(ref scrutinee, ref pattern) => {
+            visitor.visit_matches(scrutinee, pattern)
+        }
+        AssertionKind::And(ref conjuncts) => visitor.visit_and(conjuncts),
+        AssertionKind::Or(ref disjuncts) => visitor.visit_or(disjuncts),
+        AssertionKind::Implies(ref lhs, ref rhs) => visitor.visit_implies(lhs, rhs),
+        AssertionKind::Iff(ref lhs, ref rhs) => visitor.visit_iff(lhs, rhs),
+        AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => {
+            visitor.visit_forall(vars, trigger_set, body)
+        }
+        AssertionKind::Exists(ref vars, ref trigger_set, ref body) => {
+            visitor.visit_exists(vars, trigger_set, body)
+        }
+        AssertionKind::BoundedForAll(ref vars, ref lower, ref upper, ref body) => {
+            visitor.visit_bounded_forall(vars, lower, upper, body)
+        }
+        AssertionKind::TypeCond(ref vars, ref body) => visitor.visit_type_cond(vars, body),
+        AssertionKind::Let(ref vars, ref value, ref body) => visitor.visit_let(vars, value, body),
+    }
+}
+
+/// Visits the bounds and the body of a bounded quantifier.
+pub fn walk_bounded_forall<'tcx, V: AssertionVisitor<'tcx> + ?Sized>(
+    visitor: &mut V,
+    lower: &Expression,
+    upper: &Expression,
+    body: &Assertion<'tcx>,
+) {
+    visitor.visit_expr(lower);
+    visitor.visit_expr(upper);
+    visitor.visit_assertion(body);
+}
 
 /// This trait is implemented for specification-related types that have one or
 /// more associated spans (positions within the source code). The spans are not
@@ -43,100 +631,1232 @@ pub trait Spanned<'tcx> {
     /// Returns the spans for the given value. `mir` is the function body used
     /// to resolve positions of `rustc_middle::mir::Local` indices, `tcx` is
     /// used to resolve positions of global items.
-    fn get_spans(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span>;
+    fn get_spans(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span> {
+        self.get_spans_cached(mir_body, tcx, &mut SpannedCache::new())
+    }
+
+    /// Like `get_spans`, but reuses (and extends) the spans memoized in
+    /// `cache`. The cache must only be used with a single `mir_body`.
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span>;
+
+    /// Returns the spans of `get_spans` as `(file, start_line, start_column,
+    /// end_line, end_column)` tuples, e.g. for IDE integration. Lines are
+    /// 1-based and columns are 0-based. Spans that come from macro expansions
+    /// are reported at the position of the outermost macro call. A span that
+    /// starts and ends in different files is split into one range per file.
+    fn get_line_ranges(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        source_map: &SourceMap,
+    ) -> Vec<(FileName, usize, usize, usize, usize)> {
+        self.get_spans(mir_body, tcx)
+            .into_iter()
+            .flat_map(|span| span_line_ranges(span.source_callsite(), source_map))
+            .collect()
+    }
+}
+
+/// The line ranges of `span`, see `Spanned::get_line_ranges`.
+fn span_line_ranges(span: Span, source_map: &SourceMap) -> Vec<(FileName, usize, usize, usize, usize)> {
+    let start = source_map.lookup_char_pos(span.lo());
+    let end = source_map.lookup_char_pos(span.hi());
+    if start.file.start_pos == end.file.start_pos {
+        return vec![(start.file.name.clone(), start.line, start.col.0, end.line, end.col.0)];
+    }
+    // The source files occupy disjoint ranges of positions, so the span
+    // covers the end of its first file, the start of its last one and all the
+    // files in between.
+    let mut ranges = vec![];
+    for file in source_map.files().iter() {
+        let lo = span.lo().max(file.start_pos);
+        let hi = span.hi().min(file.end_pos);
+        if lo > hi {
+            continue;
+        }
+        let start = source_map.lookup_char_pos(lo);
+        let end = source_map.lookup_char_pos(hi);
+        ranges.push((file.name.clone(), start.line, start.col.0, end.line, end.col.0));
+    }
+    ranges
+}
+
+/// Memoizes the spans of expressions and quantified variables, so that
+/// repeated span lookups on large assertions do not query `tcx` or the MIR
+/// body again.
+///
+/// Expression ids are only unique within a specification, so the entries are
+/// keyed by both the specification id and the expression id.
+#[derive(Debug, Default)]
+pub struct SpannedCache {
+    spans: HashMap<(SpecificationId, ExpressionId), Vec<Span>>,
+    /// The edits of the source code since the compilation whose `tcx` the
+    /// spans are computed with, in the order in which they were made.
+    edits: Vec<SourceEdit>,
+}
+
+impl SpannedCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_or_compute<F: FnOnce() -> Vec<Span>>(
+        &mut self,
+        spec_id: SpecificationId,
+        id: ExpressionId,
+        compute: F,
+    ) -> Vec<Span> {
+        let edits = &self.edits;
+        self.spans.entry((spec_id, id)).or_insert_with(|| {
+            compute()
+                .into_iter()
+                .map(|span| edits.iter().fold(span, |span, edit| edit.remap_span(span)))
+                .collect()
+        }).clone()
+    }
+
+    /// Shifts the memoized spans after an edit of the source code, so that
+    /// they still point at the same code without recomputing them. Spans that
+    /// are computed later are shifted as well.
+    pub fn remap_spans(&mut self, edit: SourceEdit) {
+        for span in self.spans.values_mut().flatten() {
+            *span = edit.remap_span(*span);
+        }
+        self.edits.push(edit);
+    }
+}
+
+/// An edit of the source code that replaces `removed` bytes at `pos` with
+/// `inserted` bytes. `pos` is relative to the source map of the compilation
+/// that computed the spans to be remapped.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceEdit {
+    pub pos: BytePos,
+    pub removed: u32,
+    pub inserted: u32,
+}
+
+impl SourceEdit {
+    /// The span covering the same code as `span` after the edit. Spans
+    /// before the edit are unchanged and spans after it are shifted. A span
+    /// overlapping the removed code is shrunk to the code that remains, so
+    /// that code removed entirely results in an empty span at `pos`.
+    pub fn remap_span(&self, span: Span) -> Span {
+        let edit_end = self.pos.0 + self.removed;
+        let shift = |pos: u32| pos + self.inserted - self.removed;
+        let lo = match span.lo().0 {
+            lo if lo < self.pos.0 => lo,
+            lo if lo >= edit_end => shift(lo),
+            _ => self.pos.0 + self.inserted,
+        };
+        let hi = match span.hi().0 {
+            hi if hi <= self.pos.0 => hi,
+            hi if hi >= edit_end => shift(hi),
+            _ => self.pos.0,
+        };
+        span.with_lo(BytePos(lo)).with_hi(BytePos(hi.max(lo)))
+    }
 }
 
 impl<'tcx> Spanned<'tcx> for Expression {
-    fn get_spans(&self, _mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span> {
-        vec![tcx.def_span(self.expr)]
+    fn get_spans_cached(
+        &self,
+        _mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        let expr = self.expr;
+        let contains_old = self.contains_old;
+        cache.get_or_compute(self.spec_id, self.id, || {
+            let mut spans = vec![tcx.def_span(expr)];
+            if contains_old {
+                spans.extend(old_arg_spans(expr, tcx));
+            }
+            if !config::show_macro_expansion_spans() {
+                spans = spans.into_iter().map(macro_call_site).collect();
+            }
+            spans
+        })
+    }
+}
+
+/// Walks out of the expansions of function-like macros (e.g. `macro_rules!`
+/// ones), so that the errors of a specification generated by a user macro
+/// point at the macro invocation instead of the macro internals. The
+/// expansions of attributes are kept, since the specifications themselves
+/// are expanded from the Prusti attributes.
+fn macro_call_site(mut span: Span) -> Span {
+    loop {
+        let expn_data = span.ctxt().outer_expn_data();
+        match expn_data.kind {
+            ExpnKind::Macro(MacroKind::Bang, _) => span = expn_data.call_site,
+            _ => return span,
+        }
+    }
+}
+
+/// The span of a pattern is the one of the pattern itself, without the
+/// scrutinee.
+impl<'tcx> Spanned<'tcx> for Pattern {
+    fn get_spans_cached(
+        &self,
+        _mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        let check = self.check;
+        cache.get_or_compute(self.spec_id, self.id, || {
+            let span = match matched_pattern(check, tcx) {
+                Some(pat) => pat.span,
+                None => tcx.def_span(check),
+            };
+            if config::show_macro_expansion_spans() {
+                vec![span]
+            } else {
+                vec![macro_call_site(span)]
+            }
+        })
+    }
+}
+
+/// This trait is implemented for the patterns of `matches!` assertions to
+/// resolve the enum variant that they match.
+pub trait PatternVariant<'tcx> {
+    /// The `DefId` of the variant matched by the pattern, if it matches a
+    /// single variant (e.g. `State::Open` or `Some(_)`).
+    fn variant(&self, tcx: TyCtxt<'tcx>) -> Option<DefId>;
+}
+
+impl<'tcx> PatternVariant<'tcx> for Pattern {
+    fn variant(&self, tcx: TyCtxt<'tcx>) -> Option<DefId> {
+        let pat = matched_pattern(self.check, tcx)?;
+        let qpath = match pat.kind {
+            hir::PatKind::Path(ref qpath) |
+            hir::PatKind::TupleStruct(ref qpath, ..) |
+            hir::PatKind::Struct(ref qpath, ..) => qpath,
+            _ => return None,
+        };
+        match tcx.typeck(self.check).qpath_res(qpath, pat.hir_id) {
+            hir::def::Res::Def(hir::def::DefKind::Variant, variant) => Some(variant),
+            hir::def::Res::Def(
+                hir::def::DefKind::Ctor(hir::def::CtorOf::Variant, _),
+                ctor,
+            ) => tcx.parent(ctor),
+            _ => None,
+        }
+    }
+}
+
+/// The pattern of the first arm of the `match` to which `matches!` expands in
+/// the closure `check`.
+fn matched_pattern<'tcx>(check: LocalDefId, tcx: TyCtxt<'tcx>) -> Option<&'tcx hir::Pat<'tcx>> {
+    match closure_def_value(check, tcx).kind {
+        hir::ExprKind::Match(_, arms, _) => arms.first().map(|arm| &*arm.pat),
+        _ => None,
+    }
+}
+
+/// The expression that checks `pattern`, which is encoded instead of the
+/// pattern match. It refers to the state before the call if `scrutinee` does.
+pub fn pattern_check(scrutinee: &Expression, pattern: &Pattern) -> Expression {
+    Expression {
+        spec_id: pattern.spec_id,
+        id: pattern.id,
+        expr: pattern.check,
+        contains_old: scrutinee.contains_old,
+    }
+}
+
+/// Returns the spans of the arguments of all `old(..)` calls in the
+/// specification closure `expr`.
+fn old_arg_spans<'tcx>(expr: LocalDefId, tcx: TyCtxt<'tcx>) -> Vec<Span> {
+    let hir_map = tcx.hir();
+    let body_id = hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(expr));
+    let mut collector = OldArgSpansCollector {
+        tcx,
+        spans: Vec::new(),
+    };
+    collector.visit_body(hir_map.body(body_id));
+    collector.spans
+}
+
+/// A visitor that collects the spans of the arguments of `old(..)` calls.
+struct OldArgSpansCollector<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    spans: Vec<Span>,
+}
+
+impl<'tcx> Visitor<'tcx> for OldArgSpansCollector<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> intravisit::NestedVisitorMap<Self::Map> {
+        intravisit::NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        if let hir::ExprKind::Call(ref callee_expr, ref arguments) = ex.kind {
+            if let hir::ExprKind::Path(ref qself) = callee_expr.kind {
+                let res = self.tcx.typeck(callee_expr.hir_id.owner).qpath_res(qself, callee_expr.hir_id);
+                if let hir::def::Res::Def(_, def_id) = res {
+                    if self.tcx.def_path_str(def_id) == "prusti_contracts::old" {
+                        self.spans.extend(arguments.iter().map(|arg| arg.span));
+                    }
+                }
+            }
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+/// This trait is implemented for specification expressions to evaluate the
+/// ones that are known at compile time.
+pub trait ConstEval<'tcx> {
+    /// Returns the value of the expression if its closure evaluates just a
+    /// boolean or integer literal, or a (non-generic) constant. Expressions
+    /// that mention any variable, as well as composite constant expressions
+    /// such as `1 + 2`, are not evaluated.
+    fn try_eval_const(&self, tcx: TyCtxt<'tcx>) -> Option<ConstValue<'tcx>>;
+}
+
+impl<'tcx> ConstEval<'tcx> for Expression {
+    fn try_eval_const(&self, tcx: TyCtxt<'tcx>) -> Option<ConstValue<'tcx>> {
+        let value = closure_value(self, tcx);
+        match value.kind {
+            hir::ExprKind::Lit(ref lit) => match lit.node {
+                ast::LitKind::Bool(b) => Some(ConstValue::Scalar(Scalar::from_bool(b))),
+                ast::LitKind::Int(i, _) => {
+                    let ty = tcx.typeck(value.hir_id.owner).expr_ty(value);
+                    let layout = tcx.layout_of(ty::ParamEnv::reveal_all().and(ty)).ok()?;
+                    Some(ConstValue::Scalar(Scalar::from_uint(i, layout.size)))
+                }
+                _ => None,
+            },
+            hir::ExprKind::Path(ref qself) => {
+                let res = tcx.typeck(value.hir_id.owner).qpath_res(qself, value.hir_id);
+                match res {
+                    hir::def::Res::Def(hir::def::DefKind::Const, def_id) => {
+                        tcx.const_eval_poly(def_id).ok()
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// This trait is implemented for assertions to fold their boolean literals.
+pub trait Simplify<'tcx> {
+    /// Simplifies the assertion with `common::Assertion::simplify_with`,
+    /// treating the expressions that `ConstEval` evaluates to a boolean as
+    /// literals. Quantifiers and their triggers are preserved.
+    fn simplify(self, tcx: TyCtxt<'tcx>) -> Self;
+}
+
+impl<'tcx> Simplify<'tcx> for Assertion<'tcx> {
+    fn simplify(self, tcx: TyCtxt<'tcx>) -> Self {
+        self.simplify_with(&|expr| match expr.try_eval_const(tcx)? {
+            ConstValue::Scalar(scalar) => scalar.to_bool().ok(),
+            _ => None,
+        })
     }
 }
 
 impl<'tcx> Spanned<'tcx> for ForAllVars<'tcx> {
-    fn get_spans(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span> {
-        self.vars
-            .iter()
-            .filter_map(|v| mir_body.local_decls.get(v.0))
-            .map(|v| v.source_info.span)
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        _tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        let vars = &self.vars;
+        cache.get_or_compute(self.spec_id, self.id, || {
+            vars.iter()
+                .filter_map(|v| mir_body.local_decls.get(v.0))
+                .map(|v| v.source_info.span)
+                .collect()
+        })
+    }
+}
+
+/// This trait is implemented for assertions to compute a hash that is stable
+/// across compilations, e.g. to decide whether the specification of a
+/// procedure changed since its last verification.
+pub trait StableHash<'tcx> {
+    /// Hashes the structure of the assertion, the types of its bound
+    /// variables and, for each expression, the structure of the body of its
+    /// closure. Spans, the (random) specification ids and anything derived
+    /// from them, such as the paths of the closures, are not hashed.
+    fn stable_hash(&self, tcx: TyCtxt<'tcx>) -> u64;
+}
+
+impl<'tcx> StableHash<'tcx> for Assertion<'tcx> {
+    fn stable_hash(&self, tcx: TyCtxt<'tcx>) -> u64 {
+        let mut hcx = tcx.create_no_span_stable_hashing_context();
+        let mut hasher = StableHasher::new();
+        hash_assertion(self, tcx, &mut hcx, &mut hasher);
+        hasher.finish()
+    }
+}
+
+fn hash_assertion<'tcx>(
+    assertion: &Assertion<'tcx>,
+    tcx: TyCtxt<'tcx>,
+    hcx: &mut StableHashingContext<'tcx>,
+    hasher: &mut StableHasher,
+) {
+    std::mem::discriminant(&*assertion.kind).hash(hasher);
+    match *assertion.kind {
+        AssertionKind::Expr(ref expr) => hash_closure(expr.expr, tcx, hcx, hasher),
+        AssertionKind::Matches(ref scrutinee, ref pattern) => {
+            hash_closure(scrutinee.expr, tcx, hcx, hasher);
+            hash_closure(pattern.check, tcx, hcx, hasher);
+        }
+        AssertionKind::And(ref conjuncts) |
+        AssertionKind::Or(ref conjuncts) => {
+            conjuncts.len().hash(hasher);
+            for conjunct in conjuncts {
+                hash_assertion(conjunct, tcx, hcx, hasher);
+            }
+        }
+        AssertionKind::Implies(ref lhs, ref rhs) |
+        AssertionKind::Iff(ref lhs, ref rhs) => {
+            hash_assertion(lhs, tcx, hcx, hasher);
+            hash_assertion(rhs, tcx, hcx, hasher);
+        }
+        AssertionKind::TypeCond(ref vars, ref body) => {
+            hash_vars(vars, hcx, hasher);
+            hash_assertion(body, tcx, hcx, hasher);
+        }
+        AssertionKind::ForAll(ref vars, ref trigger_set, ref body) |
+        AssertionKind::Exists(ref vars, ref trigger_set, ref body) => {
+            hash_vars(vars, hcx, hasher);
+            trigger_set.triggers().len().hash(hasher);
+            for trigger in trigger_set.triggers() {
+                trigger.terms().len().hash(hasher);
+                for term in trigger.terms() {
+                    hash_closure(term.expr, tcx, hcx, hasher);
+                }
+            }
+            hash_assertion(body, tcx, hcx, hasher);
+        }
+        AssertionKind::BoundedForAll(ref vars, ref lower, ref upper, ref body) => {
+            hash_vars(vars, hcx, hasher);
+            hash_closure(lower.expr, tcx, hcx, hasher);
+            hash_closure(upper.expr, tcx, hcx, hasher);
+            hash_assertion(body, tcx, hcx, hasher);
+        }
+        AssertionKind::Let(ref vars, ref value, ref body) => {
+            hash_vars(vars, hcx, hasher);
+            hash_closure(value.expr, tcx, hcx, hasher);
+            hash_assertion(body, tcx, hcx, hasher);
+        }
+    }
+}
+
+fn hash_vars<'tcx>(
+    vars: &ForAllVars<'tcx>,
+    hcx: &mut StableHashingContext<'tcx>,
+    hasher: &mut StableHasher,
+) {
+    vars.vars.len().hash(hasher);
+    for &(local, ty) in &vars.vars {
+        local.as_u32().hash(hasher);
+        ty.hash_stable(hcx, hasher);
+    }
+    vars.weight.hash(hasher);
+}
+
+/// Hashes the body of the closure structurally. The closure and everything
+/// defined in it live in a specification item whose name contains a random
+/// UUID, so neither the path of the closure nor its HIR (whose ids are
+/// relative to that item) are stable. Instead, the local variables that the
+/// body binds are numbered in order of appearance, captured variables are
+/// hashed by name, and items defined inside the specification item (e.g. the
+/// closures of nested quantifiers) by their path relative to it.
+fn hash_closure<'tcx>(
+    closure: LocalDefId,
+    tcx: TyCtxt<'tcx>,
+    hcx: &mut StableHashingContext<'tcx>,
+    hasher: &mut StableHasher,
+) {
+    let mut spec_item = closure.to_def_id();
+    while tcx.is_closure(spec_item) {
+        spec_item = tcx.parent(spec_item).expect("a closure must have a parent");
+    }
+    let hir_map = tcx.hir();
+    let body = hir_map.body(hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(closure)));
+    let mut visitor = StructuralHasher {
+        tcx,
+        hcx,
+        hasher,
+        spec_item,
+        bound_locals: HashMap::new(),
+    };
+    visitor.visit_body(body);
+}
+
+struct StructuralHasher<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    hcx: &'a mut StableHashingContext<'tcx>,
+    hasher: &'a mut StableHasher,
+    /// The specification item in which the hashed closure is nested.
+    spec_item: DefId,
+    /// The position in which each local bound by the body was bound.
+    bound_locals: HashMap<hir::HirId, usize>,
+}
+
+impl<'a, 'tcx> StructuralHasher<'a, 'tcx> {
+    fn hash_str(&mut self, value: &str) {
+        value.hash(self.hasher);
+    }
+
+    fn hash_res(&mut self, res: hir::def::Res) {
+        std::mem::discriminant(&res).hash(self.hasher);
+        match res {
+            hir::def::Res::Local(hir_id) => match self.bound_locals.get(&hir_id) {
+                Some(&index) => index.hash(self.hasher),
+                None => {
+                    // Captured from an enclosing closure or the specification
+                    // item, e.g. a quantified variable or an argument.
+                    let name = self.tcx.hir().name(hir_id);
+                    self.hash_str(&name.as_str());
+                }
+            },
+            hir::def::Res::Def(_, def_id) => self.hash_def(def_id),
+            _ => {}
+        }
+    }
+
+    fn hash_def(&mut self, def_id: DefId) {
+        let mut relative_path = vec![];
+        let mut current = Some(def_id);
+        while let Some(id) = current {
+            if id == self.spec_item {
+                relative_path.len().hash(self.hasher);
+                for data in relative_path.into_iter().rev() {
+                    self.hash_str(&format!("{:?}", data));
+                }
+                return;
+            }
+            relative_path.push(self.tcx.def_key(id).disambiguated_data);
+            current = self.tcx.parent(id);
+        }
+        self.tcx.def_path_hash(def_id).hash_stable(self.hcx, self.hasher);
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for StructuralHasher<'a, 'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> intravisit::NestedVisitorMap<Self::Map> {
+        intravisit::NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_pat(&mut self, pat: &'tcx hir::Pat<'tcx>) {
+        std::mem::discriminant(&pat.kind).hash(self.hasher);
+        if let hir::PatKind::Binding(_, hir_id, _, _) = pat.kind {
+            let index = self.bound_locals.len();
+            self.bound_locals.insert(hir_id, index);
+        }
+        intravisit::walk_pat(self, pat);
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        std::mem::discriminant(&ex.kind).hash(self.hasher);
+        match ex.kind {
+            hir::ExprKind::Lit(ref lit) => self.hash_str(&format!("{:?}", lit.node)),
+            hir::ExprKind::Binary(op, _, _) | hir::ExprKind::AssignOp(op, _, _) => {
+                std::mem::discriminant(&op.node).hash(self.hasher);
+            }
+            hir::ExprKind::Unary(op, _) => std::mem::discriminant(&op).hash(self.hasher),
+            hir::ExprKind::Field(_, ident) => self.hash_str(&ident.name.as_str()),
+            hir::ExprKind::Path(ref qpath) => {
+                let res = self.tcx.typeck(ex.hir_id.owner).qpath_res(qpath, ex.hir_id);
+                self.hash_res(res);
+            }
+            hir::ExprKind::Struct(qpath, fields, _) => {
+                let res = self.tcx.typeck(ex.hir_id.owner).qpath_res(qpath, ex.hir_id);
+                self.hash_res(res);
+                for field in fields {
+                    self.hash_str(&field.ident.name.as_str());
+                }
+            }
+            hir::ExprKind::MethodCall(segment, _, args, _) => {
+                self.hash_str(&segment.ident.name.as_str());
+                args.len().hash(self.hasher);
+                let callee = self.tcx.typeck(ex.hir_id.owner).type_dependent_def_id(ex.hir_id);
+                if let Some(def_id) = callee {
+                    self.hash_def(def_id);
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+/// This trait is implemented for quantified variables to recover their source
+/// names, e.g. to mention "quantifier variable `i`" in diagnostics instead of
+/// a MIR local.
+pub trait VarNames<'tcx> {
+    /// Returns the name of each variable, in order, as recorded in the debug
+    /// info of `mir_body`, the body of the quantifier closure. Variables
+    /// without a source name (e.g. introduced by a macro) are `None`.
+    fn var_names(&self, mir_body: &mir::Body<'tcx>) -> Vec<Option<Symbol>>;
+}
+
+impl<'tcx> VarNames<'tcx> for ForAllVars<'tcx> {
+    fn var_names(&self, mir_body: &mir::Body<'tcx>) -> Vec<Option<Symbol>> {
+        self.vars.iter()
+            .map(|&(local, _)| {
+                mir_body.var_debug_info.iter()
+                    .find(|info| info.place.as_local() == Some(local))
+                    .map(|info| info.name)
+            })
             .collect()
     }
 }
 
+/// This trait is implemented for quantified variables to show the values that
+/// a counterexample assigns to them.
+pub trait RenderWitnesses<'tcx> {
+    /// Formats each witness as `name: type = value`, in the order of the
+    /// variables. `mir_body` is the body of the quantifier closure, which
+    /// declares the variables. Variables without a source name are shown with
+    /// their MIR local (e.g. `_2`).
+    fn render_witnesses(&self, witnesses: &[String], mir_body: &mir::Body<'tcx>) -> Vec<String>;
+}
+
+impl<'tcx> RenderWitnesses<'tcx> for ForAllVars<'tcx> {
+    fn render_witnesses(&self, witnesses: &[String], mir_body: &mir::Body<'tcx>) -> Vec<String> {
+        self.format_witnesses(&self.var_names(mir_body), witnesses)
+    }
+}
+
 impl<'tcx> Spanned<'tcx> for Assertion<'tcx> {
-    fn get_spans(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span> {
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        self.get_spans_tagged_cached(mir_body, tcx, cache)
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect()
+    }
+}
+
+/// The part of an assertion that a span belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// An expression of the assertion that is not tagged as `Consequent`,
+    /// including the bounds of a bounded quantifier.
+    Body,
+    /// An expression of the consequent of an implication, but not of the
+    /// antecedent of an implication nested in it.
+    Consequent,
+    /// A term of a quantifier trigger.
+    Trigger,
+    /// A variable bound by a quantifier or a type condition.
+    BoundVar,
+}
+
+/// This trait is implemented for assertions to report their spans together
+/// with the part of the assertion they belong to, e.g. to point only at the
+/// triggers in a trigger-related error message.
+pub trait TaggedSpans<'tcx> {
+    /// Returns the same spans as `Spanned::get_spans`, in the same order.
+    fn get_spans_tagged(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<(SpanKind, Span)> {
+        self.get_spans_tagged_cached(mir_body, tcx, &mut SpannedCache::new())
+    }
+
+    /// Like `get_spans_tagged`, but reuses (and extends) the spans memoized in
+    /// `cache`.
+    fn get_spans_tagged_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<(SpanKind, Span)>;
+
+    /// Returns the spans tagged with `SpanKind::Consequent`, e.g. to underline
+    /// what did not follow from the antecedent of a failing implication. The
+    /// result is empty if there is no implication.
+    fn get_implies_consequent_spans(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<Span> {
+        self.get_spans_tagged(mir_body, tcx)
+            .into_iter()
+            .filter(|&(kind, _)| kind == SpanKind::Consequent)
+            .map(|(_, span)| span)
+            .collect()
+    }
+}
+
+impl<'tcx> TaggedSpans<'tcx> for Assertion<'tcx> {
+    fn get_spans_tagged_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<(SpanKind, Span)> {
+        let mut collector = AssertionSpansCollector {
+            mir_body,
+            tcx,
+            cache,
+            spans: Vec::new(),
+            in_consequent: false,
+        };
+        collector.visit_assertion(self);
+        collector.spans
+    }
+}
+
+/// A visitor that collects the spans of all parts of an assertion.
+struct AssertionSpansCollector<'a, 'tcx> {
+    mir_body: &'a mir::Body<'tcx>,
+    tcx: TyCtxt<'tcx>,
+    cache: &'a mut SpannedCache,
+    spans: Vec<(SpanKind, Span)>,
+    /// Whether the visited expressions are in the consequent of an
+    /// implication.
+    in_consequent: bool,
+}
+
+impl<'a, 'tcx> AssertionSpansCollector<'a, 'tcx> {
+    fn add_spans<T: Spanned<'tcx>>(&mut self, kind: SpanKind, value: &T) {
+        let kind = if kind == SpanKind::Body && self.in_consequent {
+            SpanKind::Consequent
+        } else {
+            kind
+        };
+        let spans = value.get_spans_cached(self.mir_body, self.tcx, self.cache);
+        self.spans.extend(spans.into_iter().map(|span| (kind, span)));
+    }
+
+    fn visit_quantifier(
+        &mut self,
+        vars: &ForAllVars<'tcx>,
+        trigger_set: &TriggerSet,
+        body: &Assertion<'tcx>,
+    ) {
+        self.add_spans(SpanKind::BoundVar, vars);
+        for term in trigger_set.triggers().iter().flat_map(|t| t.terms()) {
+            self.add_spans(SpanKind::Trigger, term);
+        }
+        self.visit_assertion(body);
+    }
+}
+
+impl<'a, 'tcx> AssertionVisitor<'tcx> for AssertionSpansCollector<'a, 'tcx> {
+    fn visit_expr(&mut self, expr: &Expression) {
+        self.add_spans(SpanKind::Body, expr);
+    }
+
+    fn visit_matches(&mut self, scrutinee: &Expression, pattern: &Pattern) {
+        self.add_spans(SpanKind::Body, scrutinee);
+        self.add_spans(SpanKind::Body, pattern);
+    }
+
+    fn visit_implies(&mut self, lhs: &Assertion<'tcx>, rhs: &Assertion<'tcx>) {
+        let in_consequent = self.in_consequent;
+        self.in_consequent = false;
+        self.visit_assertion(lhs);
+        self.in_consequent = true;
+        self.visit_assertion(rhs);
+        self.in_consequent = in_consequent;
+    }
+
+    fn visit_forall(
+        &mut self,
+        vars: &ForAllVars<'tcx>,
+        trigger_set: &TriggerSet,
+        body: &Assertion<'tcx>,
+    ) {
+        self.visit_quantifier(vars, trigger_set, body);
+    }
+
+    fn visit_exists(
+        &mut self,
+        vars: &ForAllVars<'tcx>,
+        trigger_set: &TriggerSet,
+        body: &Assertion<'tcx>,
+    ) {
+        self.visit_quantifier(vars, trigger_set, body);
+    }
+
+    fn visit_bounded_forall(
+        &mut self,
+        vars: &ForAllVars<'tcx>,
+        lower: &Expression,
+        upper: &Expression,
+        body: &Assertion<'tcx>,
+    ) {
+        self.add_spans(SpanKind::BoundVar, vars);
+        walk_bounded_forall(self, lower, upper, body);
+    }
+
+    fn visit_type_cond(&mut self, vars: &ForAllVars<'tcx>, body: &Assertion<'tcx>) {
+        self.add_spans(SpanKind::BoundVar, vars);
+        self.visit_assertion(body);
+    }
+
+    fn visit_let(&mut self, vars: &ForAllVars<'tcx>, value: &Expression, body: &Assertion<'tcx>) {
+        self.add_spans(SpanKind::BoundVar, vars);
+        self.visit_expr(value);
+        self.visit_assertion(body);
+    }
+}
+
+/// This trait is implemented for assertions to find the part of an assertion
+/// written at some position, e.g. to re-verify only what changed in a large
+/// conjunction.
+pub trait SubassertionAtSpan<'tcx> {
+    /// Returns the smallest subassertion of `self` whose spans contain
+    /// `target`, or `None` if no span of `self` contains `target`.
+    fn subassertion_at_span(
+        &self,
+        target: Span,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Option<&Self>;
+}
+
+impl<'tcx> SubassertionAtSpan<'tcx> for Assertion<'tcx> {
+    fn subassertion_at_span(
+        &self,
+        target: Span,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Option<&Self> {
+        let mut cache = SpannedCache::new();
+        let mut current = self;
+        if !current.get_spans_cached(mir_body, tcx, &mut cache).iter().any(|span| span.contains(target)) {
+            return None;
+        }
+        'search: loop {
+            for child in subassertions(current) {
+                let spans = child.get_spans_cached(mir_body, tcx, &mut cache);
+                if spans.iter().any(|span| span.contains(target)) {
+                    current = child;
+                    continue 'search;
+                }
+            }
+            return Some(current);
+        }
+    }
+}
+
+/// The assertions directly nested in `assertion`.
+fn subassertions<'a, 'tcx>(assertion: &'a Assertion<'tcx>) -> Vec<&'a Assertion<'tcx>> {
+    match *assertion.kind {
+        AssertionKind::Expr(_) | AssertionKind::Matches(..) => vec![],
+        AssertionKind::And(ref assertions) |
+        AssertionKind::Or(ref assertions) => assertions.iter().collect(),
+        AssertionKind::Implies(ref lhs, ref rhs) |
+        AssertionKind::Iff(ref lhs, ref rhs) => vec![lhs, rhs],
+        AssertionKind::TypeCond(_, ref body) |
+        AssertionKind::ForAll(_, _, ref body) |
+        AssertionKind::Exists(_, _, ref body) |
+        AssertionKind::BoundedForAll(_, _, _, ref body) |
+        AssertionKind::Let(_, _, ref body) => vec![body],
+    }
+}
+
+/// This trait is implemented for assertions to render them back in the
+/// specification syntax, e.g. to let users check what was parsed from their
+/// attributes.
+pub trait DisplayAssertion<'tcx> {
+    /// Reconstructs the `&&`, `||`, `==>`, `<==>`, `forall`, `exists`, `type_cond`
+    /// and `let` syntax of the assertion, inlining the source text of each
+    /// expression. Expressions and variables whose source text is not
+    /// available are shown by their `DefId` and MIR local, respectively.
+    fn to_display(&self, tcx: TyCtxt<'tcx>, source_map: &SourceMap) -> String;
+}
+
+impl<'tcx> DisplayAssertion<'tcx> for Assertion<'tcx> {
+    fn to_display(&self, tcx: TyCtxt<'tcx>, source_map: &SourceMap) -> String {
+        let operand = |assertion: &Assertion<'tcx>| {
+            let text = assertion.to_display(tcx, source_map);
+            match *assertion.kind {
+                AssertionKind::And(_) |
+                AssertionKind::Or(_) |
+                AssertionKind::Implies(..) |
+                AssertionKind::Iff(..) => format!("({})", text),
+                _ => text,
+            }
+        };
         match *self.kind {
-            AssertionKind::Expr(ref assertion_expr) => assertion_expr.get_spans(mir_body, tcx),
-            AssertionKind::And(ref assertions) => {
-                assertions
-                    .iter()
-                    .flat_map(|a| a.get_spans(mir_body, tcx))
-                    .collect()
+            AssertionKind::Expr(ref expr) => expression_source(expr, tcx, source_map),
+            AssertionKind::Matches(ref scrutinee, ref pattern) => {
+                expression_source(&pattern_check(scrutinee, pattern), tcx, source_map)
+            }
+            AssertionKind::And(ref conjuncts) if conjuncts.is_empty() => "true".to_string(),
+            AssertionKind::And(ref conjuncts) => {
+                conjuncts.iter().map(operand).collect::<Vec<_>>().join(" && ")
+            }
+            AssertionKind::Or(ref disjuncts) if disjuncts.is_empty() => "false".to_string(),
+            AssertionKind::Or(ref disjuncts) => {
+                disjuncts.iter().map(operand).collect::<Vec<_>>().join(" || ")
             }
             AssertionKind::Implies(ref lhs, ref rhs) => {
-                let mut spans = lhs.get_spans(mir_body, tcx);
-                spans.extend(rhs.get_spans(mir_body, tcx));
-                spans
-            }
-            AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => {
-                let mut spans = vars.get_spans(mir_body, tcx);
-                spans.extend(trigger_set
-                    .triggers()
-                    .iter()
-                    .flat_map(|t| t.terms())
-                    .flat_map(|e| e.get_spans(mir_body, tcx))
-                    .collect::<Vec<Span>>());
-                spans.extend(body.get_spans(mir_body, tcx));
-                spans
+                format!("{} ==> {}", operand(lhs), operand(rhs))
+            }
+            AssertionKind::Iff(ref lhs, ref rhs) => {
+                format!("{} <==> {}", operand(lhs), operand(rhs))
             }
             AssertionKind::TypeCond(ref vars, ref body) => {
-                let mut spans = vars.get_spans(mir_body, tcx);
-                spans.extend(body.get_spans(mir_body, tcx));
-                spans
+                let body = body.to_display(tcx, source_map);
+                match vars.vars[..] {
+                    [(_, lhs), (_, rhs)] => format!("type_cond({} == {}, {})", lhs, rhs, body),
+                    // Only type conditions built by hand can have another
+                    // number of types, which are then left out.
+                    _ => format!("type_cond(.., {})", body),
+                }
+            }
+            AssertionKind::ForAll(ref vars, ref triggers, ref body) |
+            AssertionKind::Exists(ref vars, ref triggers, ref body) => {
+                let quantifier = match *self.kind {
+                    AssertionKind::ForAll(..) => "forall",
+                    _ => "exists",
+                };
+                let mut text = format!(
+                    "{}(|{}| {}",
+                    quantifier,
+                    binder_source(vars, body, tcx, source_map),
+                    body.to_display(tcx, source_map),
+                );
+                if !triggers.triggers().is_empty() {
+                    let triggers = triggers.triggers().iter().map(|trigger| {
+                        let terms = trigger.terms().iter()
+                            .map(|term| expression_source(term, tcx, source_map))
+                            .collect::<Vec<_>>();
+                        if terms.len() == 1 {
+                            format!("({},)", terms[0])
+                        } else {
+                            format!("({})", terms.join(", "))
+                        }
+                    }).collect::<Vec<_>>();
+                    text.push_str(&format!(", triggers=[{}]", triggers.join(", ")));
+                }
+                text.push(')');
+                text
             }
+            AssertionKind::BoundedForAll(ref vars, ref lower, ref upper, ref body) => {
+                format!(
+                    "forall(|{}| {} && {} ==> {})",
+                    binder_source(vars, body, tcx, source_map),
+                    expression_source(lower, tcx, source_map),
+                    expression_source(upper, tcx, source_map),
+                    operand(body),
+                )
+            }
+            AssertionKind::Let(ref vars, ref value, ref body) => {
+                format!(
+                    "let {} = {} in {}",
+                    binder_source(vars, body, tcx, source_map),
+                    expression_source(value, tcx, source_map),
+                    body.to_display(tcx, source_map),
+                )
+            }
+        }
+    }
+}
+
+/// The source text of the specification expression `expr`, e.g. for an IDE
+/// to show the specification inline. Returns `None` if the source is not
+/// available, for example because the span of the expression belongs to
+/// another crate. The text is the one of the expression evaluated by the
+/// closure of `expr`, without the closure header.
+pub fn spec_source_text<'tcx>(
+    expr: &Expression,
+    tcx: TyCtxt<'tcx>,
+    source_map: &SourceMap,
+) -> Option<String> {
+    let span = macro_call_site(closure_value(expr, tcx).span);
+    if source_map.lookup_source_file(span.lo()).cnum != LOCAL_CRATE {
+        return None;
+    }
+    source_map.span_to_snippet(span).ok()
+}
+
+/// The source text of `expr` as given by `spec_source_text`, or its `DefId`
+/// if the source is not available.
+fn expression_source<'tcx>(expr: &Expression, tcx: TyCtxt<'tcx>, source_map: &SourceMap) -> String {
+    spec_source_text(expr, tcx, source_map).unwrap_or_else(|| format!("{:?}", expr.expr))
+}
+
+/// The variables of `vars` as `name: type`, separated by commas. The names
+/// are taken from the parameters of the closure that binds the variables,
+/// which is found by walking up from an expression of `body`.
+fn binder_source<'tcx>(
+    vars: &ForAllVars<'tcx>,
+    body: &Assertion<'tcx>,
+    tcx: TyCtxt<'tcx>,
+    source_map: &SourceMap,
+) -> String {
+    let params = first_expression(body)
+        .and_then(|expr| binding_closure(vars, expr, tcx))
+        .map(|closure| quantifier_params(closure, tcx))
+        .unwrap_or_default();
+    vars.vars.iter()
+        .enumerate()
+        .map(|(index, &(local, ty))| {
+            let name = params.get(index)
+                .and_then(|&param| source_map.span_to_snippet(tcx.hir().span(param)).ok())
+                .unwrap_or_else(|| format!("{:?}", local));
+            format!("{}: {}", name, ty)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The span of the closure of the quantifier that binds `vars` in `body`, if
+/// `body` mentions an expression.
+pub fn quantifier_span<'tcx>(
+    vars: &ForAllVars<'tcx>,
+    body: &Assertion<'tcx>,
+    tcx: TyCtxt<'tcx>,
+) -> Option<Span> {
+    first_expression(body)
+        .and_then(|expr| binding_closure(vars, expr, tcx))
+        .map(|closure| tcx.def_span(closure))
+}
+
+/// The closure that binds `vars`, among the closures in which the closure of
+/// `nested` is (transitively) nested.
+fn binding_closure<'tcx>(
+    vars: &ForAllVars<'tcx>,
+    nested: &Expression,
+    tcx: TyCtxt<'tcx>,
+) -> Option<LocalDefId> {
+    let identifier = format!("{}_{}", vars.spec_id, vars.id);
+    let mut closure = nested.expr.to_def_id();
+    while tcx.is_closure(closure) {
+        if read_prusti_attr("expr_id", tcx.get_attrs(closure)).as_ref() == Some(&identifier) {
+            return closure.as_local();
+        }
+        closure = tcx.parent(closure)?;
+    }
+    None
+}
+
+/// The first expression of `assertion`, in source order.
+fn first_expression<'a, 'tcx>(assertion: &'a Assertion<'tcx>) -> Option<&'a Expression> {
+    match *assertion.kind {
+        AssertionKind::Expr(ref expr) |
+        AssertionKind::Matches(ref expr, _) => Some(expr),
+        AssertionKind::And(ref conjuncts) |
+        AssertionKind::Or(ref conjuncts) => conjuncts.iter().find_map(first_expression),
+        AssertionKind::Implies(ref lhs, ref rhs) |
+        AssertionKind::Iff(ref lhs, ref rhs) => {
+            first_expression(lhs).or_else(|| first_expression(rhs))
+        }
+        AssertionKind::TypeCond(_, ref body) |
+        AssertionKind::ForAll(_, _, ref body) |
+        AssertionKind::Exists(_, _, ref body) => first_expression(body),
+        AssertionKind::BoundedForAll(_, ref lower, _, _) => Some(lower),
+        AssertionKind::Let(_, ref value, _) => Some(value),
+    }
+}
+
+impl<'tcx> Spanned<'tcx> for Pledge<'tcx> {
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        let mut spans = vec![];
+        if let Some(ref reference) = self.reference {
+            spans.extend(reference.get_spans_cached(mir_body, tcx, cache));
+        }
+        if let PledgeKind::Conditional(lhs) = self.kind() {
+            spans.extend(lhs.get_spans_cached(mir_body, tcx, cache));
+        }
+        spans.extend(self.rhs.get_spans_cached(mir_body, tcx, cache));
+        spans
+    }
+}
+
+impl<'tcx> Spanned<'tcx> for Specification<'tcx> {
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        self.assertion.get_spans_cached(mir_body, tcx, cache)
+    }
+}
+
+/// The spans of all the assertions of the set, e.g. to underline a whole group
+/// of `#[requires]` and `#[ensures]` attributes. For procedures, the spans of
+/// the pledges are included.
+impl<'tcx> Spanned<'tcx> for SpecificationSet<'tcx> {
+    fn get_spans_cached(
+        &self,
+        mir_body: &mir::Body<'tcx>,
+        tcx: TyCtxt<'tcx>,
+        cache: &mut SpannedCache,
+    ) -> Vec<Span> {
+        let mut spans = vec![];
+        let partitioned = self.partition();
+        let assertions = partitioned.pres.iter()
+            .chain(&partitioned.posts)
+            .chain(&partitioned.invariants)
+            .chain(&partitioned.predicates);
+        for assertion in assertions {
+            spans.extend(assertion.get_spans_cached(mir_body, tcx, cache));
+        }
+        for pledge in partitioned.pledges {
+            spans.extend(pledge.get_spans_cached(mir_body, tcx, cache));
         }
+        spans
     }
 }
 
+/// Orders the invariants of a loop specification by their position in the
+/// source code, so that they are encoded in the order in which they were
+/// written.
+pub trait OrderedInvariants<'tcx> {
+    /// Returns the invariants sorted by the byte position of their first span.
+    /// Invariants without spans are kept at the end, in their original order.
+    fn ordered_invariants(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<&Assertion<'tcx>>;
+}
+
+impl<'tcx> OrderedInvariants<'tcx> for LoopSpecification<'tcx> {
+    fn ordered_invariants(&self, mir_body: &mir::Body<'tcx>, tcx: TyCtxt<'tcx>) -> Vec<&Assertion<'tcx>> {
+        let mut cache = SpannedCache::new();
+        let mut invariants: Vec<_> = self.invariant
+            .iter()
+            .map(|invariant| {
+                let position = invariant.get_spans_cached(mir_body, tcx, &mut cache)
+                    .first()
+                    .map(|span| span.lo());
+                (position.is_none(), position, invariant)
+            })
+            .collect();
+        invariants.sort_by_key(|&(no_position, position, _)| (no_position, position));
+        invariants.into_iter().map(|(_, _, invariant)| invariant).collect()
+    }
+}
+
+/// Reconstructs a typed specification from its structural (JSON) form, looking
+/// up the type-checked closures generated for the specification.
+///
+/// The conversion fails with a `PrustiError` if the structural form does not
+/// match the compiled closures.
 pub trait StructuralToTyped<'tcx, Target> {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> Target;
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Target, PrustiError>;
+}
+
+/// Looks up the type-checked closure that was generated for the expression
+/// `expr_id` of the specification `spec_id`. The error has no span, it is
+/// reported at the specification item by `reconstruct_typed_assertion`.
+fn lookup_typed_expression(
+    typed_expressions: &HashMap<String, LocalDefId>,
+    spec_id: SpecificationId,
+    expr_id: ExpressionId,
+) -> Result<LocalDefId, PrustiError> {
+    typed_expressions
+        .get(&format!("{}_{}", spec_id, expr_id))
+        .cloned()
+        .ok_or_else(|| PrustiError::internal(
+            format!(
+                "cannot find the type-checked closure of expression {} of specification {}; \
+                the specification probably failed to compile",
+                expr_id,
+                spec_id,
+            ),
+            MultiSpan::new(),
+        ))
 }
 
 impl<'tcx> StructuralToTyped<'tcx, Expression> for json::Expression {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> Expression {
-        let local_id = typed_expressions[&format!("{}_{}", self.spec_id, self.expr_id)];
-        Expression {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Expression, PrustiError> {
+        let local_id = lookup_typed_expression(typed_expressions, self.spec_id, self.expr_id)?;
+        Ok(Expression {
             spec_id: self.spec_id,
             id: self.expr_id,
             expr: local_id,
-        }
+            contains_old: self.contains_old,
+        })
+    }
+}
+
+impl<'tcx> StructuralToTyped<'tcx, Pattern> for json::Pattern {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        _tcx: TyCtxt<'tcx>,
+    ) -> Result<Pattern, PrustiError> {
+        Ok(common::Pattern {
+            spec_id: self.spec_id,
+            id: self.expr_id,
+            check: lookup_typed_expression(typed_expressions, self.spec_id, self.expr_id)?,
+        })
     }
 }
 
 impl<'tcx> StructuralToTyped<'tcx, TriggerSet> for json::TriggerSet {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> TriggerSet {
-        common::TriggerSet(
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<TriggerSet, PrustiError> {
+        let trigger_set = common::TriggerSet(
             self.0
                 .into_iter()
                 .map(|x| x.to_typed(typed_expressions, tcx))
-                .collect()
-        )
+                .collect::<Result<_, _>>()?
+        );
+        // Repeated terms would be encoded repeatedly in the Viper program.
+        Ok(trigger_set.dedup())
     }
 }
 
 impl<'tcx> StructuralToTyped<'tcx, Trigger> for json::Trigger {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> Trigger {
-        common::Trigger(
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Trigger, PrustiError> {
+        Ok(common::Trigger(
             self.0
                 .into_iter()
                 .map(|x| x.to_typed(typed_expressions, tcx))
-                .collect()
-        )
+                .collect::<Result<_, _>>()?
+        ))
     }
 }
 
 impl<'tcx> StructuralToTyped<'tcx, ForAllVars<'tcx>> for json::ForAllVars {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> ForAllVars<'tcx> {
-        let local_id = typed_expressions[&format!("{}_{}", self.spec_id, self.expr_id)];
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<ForAllVars<'tcx>, PrustiError> {
+        let local_id = lookup_typed_expression(typed_expressions, self.spec_id, self.expr_id)?;
+        if self.count == 0 {
+            return Err(PrustiError::incorrect(
+                "the quantifier does not bind any variable".to_string(),
+                MultiSpan::from_span(tcx.def_span(local_id)),
+            ).set_help("a quantifier needs at least one variable, e.g. `forall(|i: usize| ..)`"));
+        }
         let (body, _) = tcx.mir_promoted(ty::WithOptConstParam::unknown(local_id));
         let body = body.borrow();
 
@@ -153,43 +1873,462 @@ impl<'tcx> StructuralToTyped<'tcx, ForAllVars<'tcx>> for json::ForAllVars {
                            .clone()))
             .collect();
 
-        assert!(body.arg_count-1 == self.count);
-        assert_eq!(vars.len(), self.count);
-        return ForAllVars {
+        if body.arg_count == 0 || vars.len() != self.count {
+            return Err(PrustiError::internal(
+                format!(
+                    "internal spec encoding mismatch: the quantifier binds {} variables, \
+                    but its closure has {} arguments",
+                    self.count,
+                    vars.len(),
+                ),
+                MultiSpan::from_span(tcx.def_span(local_id)),
+            ));
+        }
+        // Variables of fieldless enums range over finitely many values, so the
+        // encoder can expand the quantifier into a conjunction over them.
+        let enumerated = vars
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ty))| match ty.kind() {
+                ty::TyKind::Adt(adt_def, _) => adt_def.is_enum() && adt_def.is_payloadfree(),
+                _ => false,
+            })
+            .map(|(index, _)| index)
+            .collect();
+        Ok(ForAllVars {
             spec_id: self.spec_id,
             id: self.expr_id,
-            vars
-        }
+            vars,
+            enumerated,
+            weight: self.weight,
+            trigger_strategies: self.trigger_strategies,
+        })
+    }
+}
+
+/// Keeps the triggers of the quantifier over `vars` that are used with the
+/// configured trigger strategy (see `config::trigger_strategy`), i.e. the
+/// written triggers and the ones inferred by the strategy. The inferred
+/// triggers of all the strategies are in the specification, so that its
+/// expansion does not depend on the configuration.
+fn select_triggers<'tcx>(
+    vars: &mut ForAllVars<'tcx>,
+    triggers: json::TriggerSet,
+    quantifier: LocalDefId,
+    tcx: TyCtxt<'tcx>,
+) -> Result<json::TriggerSet, PrustiError> {
+    if !vars.has_inferred_triggers() {
+        return Ok(triggers);
     }
+    let name = config::trigger_strategy();
+    let strategy = TriggerStrategy::from_name(&name).ok_or_else(|| PrustiError::incorrect(
+        format!("unknown trigger strategy `{}`", name),
+        MultiSpan::from_span(tcx.def_span(quantifier)),
+    ).set_help("the trigger strategy must be `none`, `shallow` or `aggressive`"))?;
+    let (selected, strategies): (Vec<_>, Vec<_>) = triggers.0
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| vars.uses_trigger(*index, strategy))
+        .map(|(index, trigger)| {
+            (trigger, vars.trigger_strategies.get(index).cloned().unwrap_or_default())
+        })
+        .unzip();
+    vars.trigger_strategies = strategies;
+    Ok(json::TriggerSet(selected))
 }
 
 impl<'tcx> StructuralToTyped<'tcx, AssertionKind<'tcx>> for json::AssertionKind {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> AssertionKind<'tcx> {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<AssertionKind<'tcx>, PrustiError> {
         use json::AssertionKind::*;
-        match self {
-            Expr(expr) => AssertionKind::Expr(expr.to_typed(typed_expressions, tcx)),
+        Ok(match self {
+            Expr(expr) => AssertionKind::Expr(expr.to_typed(typed_expressions, tcx)?),
+            Matches(scrutinee, pattern) => {
+                let scrutinee = scrutinee.to_typed(typed_expressions, tcx)?;
+                let pattern = pattern.to_typed(typed_expressions, tcx)?;
+                // Other patterns (e.g. ranges or alternatives) are checked as
+                // plain expressions.
+                if pattern.variant(tcx).is_some() {
+                    AssertionKind::Matches(scrutinee, pattern)
+                } else {
+                    AssertionKind::Expr(pattern_check(&scrutinee, &pattern))
+                }
+            }
             And(assertions) => AssertionKind::And(
                 assertions.into_iter()
                           .map(|assertion| assertion.to_typed(typed_expressions, tcx))
-                          .collect()
+                          .collect::<Result<_, _>>()?
+            ),
+            Or(assertions) => AssertionKind::Or(
+                assertions.into_iter()
+                          .map(|assertion| assertion.to_typed(typed_expressions, tcx))
+                          .collect::<Result<_, _>>()?
             ),
             Implies(lhs, rhs) => AssertionKind::Implies(
-                lhs.to_typed(typed_expressions, tcx),
-                rhs.to_typed(typed_expressions, tcx)
+                lhs.to_typed(typed_expressions, tcx)?,
+                rhs.to_typed(typed_expressions, tcx)?
+            ),
+            Iff(lhs, rhs) => AssertionKind::Iff(
+                lhs.to_typed(typed_expressions, tcx)?,
+                rhs.to_typed(typed_expressions, tcx)?
             ),
-            ForAll(vars, body, triggers) => AssertionKind::ForAll(
-                vars.to_typed(typed_expressions, tcx),
-                triggers.to_typed(typed_expressions, tcx),
-                body.to_typed(typed_expressions, tcx),
-            )
+            ForAll(vars, body, triggers) => {
+                let quantifier = lookup_typed_expression(typed_expressions, vars.spec_id, vars.expr_id)?;
+                let mut vars = vars.to_typed(typed_expressions, tcx)?;
+                let triggers = select_triggers(&mut vars, triggers, quantifier, tcx)?
+                    .to_typed(typed_expressions, tcx)?;
+                let body = body.to_typed(typed_expressions, tcx)?;
+                match range_bounds(quantifier, &vars, &triggers, &body, tcx) {
+                    Some((lower, upper, body)) => AssertionKind::BoundedForAll(vars, lower, upper, body),
+                    None => AssertionKind::ForAll(vars, triggers, body),
+                }
+            }
+            Exists(vars, body, triggers) => {
+                let quantifier = lookup_typed_expression(typed_expressions, vars.spec_id, vars.expr_id)?;
+                let mut vars = vars.to_typed(typed_expressions, tcx)?;
+                let triggers = select_triggers(&mut vars, triggers, quantifier, tcx)?
+                    .to_typed(typed_expressions, tcx)?;
+                AssertionKind::Exists(vars, triggers, body.to_typed(typed_expressions, tcx)?)
+            }
+            TypeCond(vars, body) => {
+                let closure = lookup_typed_expression(typed_expressions, vars.spec_id, vars.expr_id)?;
+                let vars = vars.to_typed(typed_expressions, tcx)?;
+                // A condition on concrete types is always true or always
+                // false, which is most likely not what the user intended.
+                let mentions_param = vars.vars
+                    .iter()
+                    .any(|&(_, ty)| ty.has_type_flags(ty::TypeFlags::HAS_TY_PARAM));
+                if !mentions_param {
+                    return Err(PrustiError::incorrect(
+                        format!(
+                            "the type condition `{} == {}` does not mention a type parameter \
+                            of the function",
+                            vars.vars[0].1,
+                            vars.vars[1].1,
+                        ),
+                        MultiSpan::from_span(tcx.def_span(closure)),
+                    ).set_help("type conditions can only refer to the generic parameters in scope"));
+                }
+                AssertionKind::TypeCond(vars, body.to_typed(typed_expressions, tcx)?)
+            }
+            Let(vars, value, body) => AssertionKind::Let(
+                vars.to_typed(typed_expressions, tcx)?,
+                value.to_typed(typed_expressions, tcx)?,
+                body.to_typed(typed_expressions, tcx)?,
+            ),
+        })
+    }
+}
+
+/// Reconstructs the structural (JSON) form of a typed specification, e.g. to
+/// cache typed specifications across compiler invocations. This is the
+/// inverse of `StructuralToTyped`: the spans are dropped, but the ids are
+/// preserved so that the result can be typed again with the same closures.
+pub trait TypedToStructural<'tcx, Target> {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> Target;
+}
+
+/// Whether the structural form of `assertion` is typed again to a
+/// structurally equal assertion, i.e. whether `TypedToStructural` inverts
+/// `StructuralToTyped` on it. The closures of the expressions are resolved
+/// among `typed_expressions` by the keys recovered from their attributes.
+pub fn round_trips<'tcx>(
+    assertion: &Assertion<'tcx>,
+    typed_expressions: &HashMap<String, LocalDefId>,
+    tcx: TyCtxt<'tcx>,
+) -> bool {
+    let json: json::Assertion = assertion.to_json(tcx);
+    match json.to_typed(typed_expressions, tcx) {
+        Ok(reloaded) => assertion.structurally_eq(&reloaded),
+        Err(_) => false,
+    }
+}
+
+/// The key of the closure `closure` in the map of the type-checked closures
+/// passed to `StructuralToTyped`, i.e. its `expr_id` attribute.
+fn typed_expression_key<'tcx>(closure: LocalDefId, tcx: TyCtxt<'tcx>) -> Option<String> {
+    read_prusti_attr("expr_id", tcx.get_attrs(closure.to_def_id()))
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::Expression> for Expression {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::Expression {
+        debug_assert_eq!(
+            typed_expression_key(self.expr, tcx),
+            Some(format!("{}_{}", self.spec_id, self.id)),
+            "the closure of an expression does not match its identifier",
+        );
+        json::Expression {
+            spec_id: self.spec_id,
+            expr_id: self.id,
+            contains_old: self.contains_old,
         }
     }
 }
 
-impl<'tcx> StructuralToTyped<'tcx, Assertion<'tcx>> for json::Assertion {
-    fn to_typed(self, typed_expressions: &HashMap<String, LocalDefId>, tcx: TyCtxt<'tcx>) -> Assertion<'tcx> {
-        Assertion {
-            kind: box self.kind.to_typed(typed_expressions, tcx),
+impl<'tcx> TypedToStructural<'tcx, json::Pattern> for Pattern {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::Pattern {
+        debug_assert_eq!(
+            typed_expression_key(self.check, tcx),
+            Some(format!("{}_{}", self.spec_id, self.id)),
+            "the closure of a pattern does not match its identifier",
+        );
+        json::Pattern {
+            spec_id: self.spec_id,
+            expr_id: self.id,
+        }
+    }
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::ForAllVars> for ForAllVars<'tcx> {
+    fn to_json(&self, _tcx: TyCtxt<'tcx>) -> json::ForAllVars {
+        json::ForAllVars {
+            spec_id: self.spec_id,
+            expr_id: self.id,
+            count: self.vars.len(),
+            weight: self.weight,
+            trigger_strategies: self.trigger_strategies.clone(),
+        }
+    }
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::TriggerSet> for TriggerSet {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::TriggerSet {
+        json::TriggerSet(self.triggers().iter().map(|x| x.to_json(tcx)).collect())
+    }
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::Trigger> for Trigger {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::Trigger {
+        json::Trigger(self.terms().iter().map(|x| x.to_json(tcx)).collect())
+    }
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::Assertion> for Assertion<'tcx> {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::Assertion {
+        json::Assertion {
+            kind: box self.kind.to_json(tcx),
+        }
+    }
+}
+
+impl<'tcx> TypedToStructural<'tcx, json::AssertionKind> for AssertionKind<'tcx> {
+    fn to_json(&self, tcx: TyCtxt<'tcx>) -> json::AssertionKind {
+        use common::AssertionKind::*;
+        match self {
+            Expr(expr) => json::AssertionKind::Expr(expr.to_json(tcx)),
+            Matches(scrutinee, pattern) => json::AssertionKind::Matches(
+                scrutinee.to_json(tcx),
+                pattern.to_json(tcx),
+            ),
+            And(assertions) => json::AssertionKind::And(
+                assertions.iter().map(|assertion| assertion.to_json(tcx)).collect()
+            ),
+            Or(assertions) => json::AssertionKind::Or(
+                assertions.iter().map(|assertion| assertion.to_json(tcx)).collect()
+            ),
+            Implies(lhs, rhs) => json::AssertionKind::Implies(lhs.to_json(tcx), rhs.to_json(tcx)),
+            Iff(lhs, rhs) => json::AssertionKind::Iff(lhs.to_json(tcx), rhs.to_json(tcx)),
+            TypeCond(vars, body) => json::AssertionKind::TypeCond(
+                vars.to_json(tcx),
+                body.to_json(tcx),
+            ),
+            ForAll(vars, triggers, body) => json::AssertionKind::ForAll(
+                vars.to_json(tcx),
+                body.to_json(tcx),
+                triggers.to_json(tcx),
+            ),
+            Exists(vars, triggers, body) => json::AssertionKind::Exists(
+                vars.to_json(tcx),
+                body.to_json(tcx),
+                triggers.to_json(tcx),
+            ),
+            // As in the JSON emitted by the rewriter, the bounds are part of
+            // the body; they are recognized again by `StructuralToTyped`.
+            BoundedForAll(vars, lower, upper, body) => json::AssertionKind::ForAll(
+                vars.to_json(tcx),
+                json::Assertion {
+                    kind: box json::AssertionKind::Implies(
+                        json::Assertion {
+                            kind: box json::AssertionKind::And(vec![
+                                json::Assertion { kind: box json::AssertionKind::Expr(lower.to_json(tcx)) },
+                                json::Assertion { kind: box json::AssertionKind::Expr(upper.to_json(tcx)) },
+                            ]),
+                        },
+                        body.to_json(tcx),
+                    ),
+                },
+                json::TriggerSet(vec![]),
+            ),
+            Let(vars, value, body) => json::AssertionKind::Let(
+                vars.to_json(tcx),
+                value.to_json(tcx),
+                body.to_json(tcx),
+            ),
+        }
+    }
+}
+
+/// Recognizes a quantifier body of the shape `lo <= i && i < hi ==> body`
+/// (`<` and `<=` are accepted for both bounds), where `i` is the only variable
+/// bound by the quantifier closure `quantifier`. Quantifiers with explicit
+/// triggers are left as they are.
+fn range_bounds<'tcx>(
+    quantifier: LocalDefId,
+    vars: &ForAllVars<'tcx>,
+    triggers: &TriggerSet,
+    body: &Assertion<'tcx>,
+    tcx: TyCtxt<'tcx>,
+) -> Option<(Expression, Expression, Assertion<'tcx>)> {
+    if vars.arity() != 1 || !triggers.triggers().is_empty() {
+        return None;
+    }
+    let (conjuncts, body) = match *body.kind {
+        AssertionKind::Implies(ref lhs, ref rhs) => match *lhs.kind {
+            AssertionKind::And(ref conjuncts) => (conjuncts, rhs),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match (&conjuncts[..], quantifier_params(quantifier, tcx).as_slice()) {
+        ([lower, upper], [var]) => match (&*lower.kind, &*upper.kind) {
+            (AssertionKind::Expr(lower), AssertionKind::Expr(upper))
+                if bounds_var(lower, *var, true, tcx) && bounds_var(upper, *var, false, tcx) =>
+            {
+                Some((lower.clone(), upper.clone(), body.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The `HirId`s of the variables bound by the closure `quantifier`.
+fn quantifier_params<'tcx>(quantifier: LocalDefId, tcx: TyCtxt<'tcx>) -> Vec<hir::HirId> {
+    let hir_map = tcx.hir();
+    let body = hir_map.body(hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(quantifier)));
+    body.params.iter().map(|param| param.pat.hir_id).collect()
+}
+
+/// Whether the closure of `expr` is a comparison `_ <= var` or `_ < var` (if
+/// `lower`), respectively `var <= _` or `var < _` (otherwise).
+fn bounds_var<'tcx>(expr: &Expression, var: hir::HirId, lower: bool, tcx: TyCtxt<'tcx>) -> bool {
+    match closure_value(expr, tcx).kind {
+        hir::ExprKind::Binary(op, lhs, rhs) => {
+            let bounded = if lower { rhs } else { lhs };
+            let is_var = match bounded.kind {
+                hir::ExprKind::Path(hir::QPath::Resolved(None, path)) => {
+                    path.res == hir::def::Res::Local(var)
+                }
+                _ => false,
+            };
+            is_var && matches!(op.node, hir::BinOpKind::Le | hir::BinOpKind::Lt)
         }
+        _ => false,
+    }
+}
+
+/// The expression evaluated by the closure of `expr`, without the blocks and
+/// temporaries introduced by the desugaring of the specification.
+pub(crate) fn closure_value<'tcx>(expr: &Expression, tcx: TyCtxt<'tcx>) -> &'tcx hir::Expr<'tcx> {
+    closure_def_value(expr.expr, tcx)
+}
+
+/// Like `closure_value`, for the closure `closure`.
+fn closure_def_value<'tcx>(closure: LocalDefId, tcx: TyCtxt<'tcx>) -> &'tcx hir::Expr<'tcx> {
+    let hir_map = tcx.hir();
+    let body = hir_map.body(hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(closure)));
+    let mut value = &body.value;
+    loop {
+        match value.kind {
+            hir::ExprKind::Block(block, _) if block.stmts.is_empty() && block.expr.is_some() => {
+                value = block.expr.unwrap();
+            }
+            hir::ExprKind::DropTemps(inner) => value = inner,
+            _ => return value,
+        }
+    }
+}
+
+impl<'tcx> StructuralToTyped<'tcx, Assertion<'tcx>> for json::Assertion {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Assertion<'tcx>, PrustiError> {
+        Ok(Assertion {
+            kind: box self.kind.to_typed(typed_expressions, tcx)?,
+        })
+    }
+}
+
+/// Checks that `specification` was emitted with the current version of the
+/// specification format. `span` is the span of the item that carries the
+/// specification, at which the mismatch is reported.
+pub fn check_specification_version(
+    specification: &json::Specification,
+    span: Span,
+) -> Result<(), PrustiError> {
+    if specification.version != json::SPECIFICATION_VERSION {
+        return Err(PrustiError::incorrect(
+            format!(
+                "the specification was encoded with version {} of the specification format, \
+                but this version of Prusti expects version {}",
+                specification.version,
+                json::SPECIFICATION_VERSION,
+            ),
+            MultiSpan::from_span(span),
+        ).set_help("recompile the crate that declares the specification with this version of Prusti"));
+    }
+    Ok(())
+}
+
+/// The version of the specification is not checked here, because it is
+/// reported at the item that carries the specification, see
+/// `check_specification_version`.
+impl<'tcx> StructuralToTyped<'tcx, Assertion<'tcx>> for json::Specification {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Assertion<'tcx>, PrustiError> {
+        self.assertion.to_typed(typed_expressions, tcx)
+    }
+}
+
+/// The JSON form of a specification does not record its type, which is
+/// instead known from the item that carries it (see `SpecItem`), so the type
+/// is passed along with the specification.
+impl<'tcx> StructuralToTyped<'tcx, Specification<'tcx>> for (SpecType, json::Specification) {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Specification<'tcx>, PrustiError> {
+        let (typ, specification) = self;
+        Ok(Specification {
+            typ,
+            assertion: specification.to_typed(typed_expressions, tcx)?,
+        })
+    }
+}
+
+impl<'tcx> StructuralToTyped<'tcx, Pledge<'tcx>> for json::Pledge {
+    fn to_typed(
+        self,
+        typed_expressions: &HashMap<String, LocalDefId>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Result<Pledge<'tcx>, PrustiError> {
+        Ok(Pledge {
+            reference: self.reference
+                .map(|reference| reference.to_typed(typed_expressions, tcx))
+                .transpose()?,
+            lhs: self.lhs
+                .map(|lhs| lhs.to_typed(typed_expressions, tcx))
+                .transpose()?,
+            rhs: self.rhs.to_typed(typed_expressions, tcx)?,
+        })
     }
 }
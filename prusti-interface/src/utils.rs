@@ -11,6 +11,7 @@ use rustc_middle::ty::{self, TyCtxt};
 use rustc_index::vec::Idx;
 use std::collections::HashSet;
 use rustc_ast::ast;
+use rustc_span::Span;
 use log::trace;
 
 /// Check if the place `potential_prefix` is a prefix of `place`. For example:
@@ -293,6 +294,16 @@ pub fn has_extern_spec_attr(attrs: &[ast::Attribute]) -> bool {
 
 /// Read the value stored in a Prusti attribute (e.g. `prusti::<attr_name>="...")`.
 pub fn read_prusti_attrs(attr_name: &str, attrs: &[ast::Attribute]) -> Vec<String> {
+    read_prusti_attrs_with_spans(attr_name, attrs)
+        .into_iter()
+        .map(|(value, _)| value)
+        .collect()
+}
+
+/// Like `read_prusti_attrs`, but also return the span of each attribute. For
+/// the attributes generated by the specification macros, this is the span of
+/// the macro (e.g. the `#[requires(..)]` attribute).
+pub fn read_prusti_attrs_with_spans(attr_name: &str, attrs: &[ast::Attribute]) -> Vec<(String, Span)> {
     let mut strings = vec![];
     for attr in attrs {
         if let ast::AttrKind::Normal(ast::AttrItem {
@@ -317,7 +328,7 @@ pub fn read_prusti_attrs(attr_name: &str, attrs: &[ast::Attribute]) -> Vec<Strin
                                      kind: TokenKind::Literal(Lit { symbol, .. }),
                                      ..
                                  }) => {
-                    strings.push(symbol.as_str().replace("\\\"", "\""))
+                    strings.push((symbol.as_str().replace("\\\"", "\""), attr.span))
                 }
                 x => unreachable!("{:?}", x),
             }
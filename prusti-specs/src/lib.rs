@@ -8,7 +8,7 @@ mod parse_closure_macro;
 mod spec_attribute_kind;
 pub mod specifications;
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
 use quote::{quote, ToTokens};
 use syn::spanned::Spanned;
 use syn::parse_quote;
@@ -87,6 +87,7 @@ fn generate_spec_and_assertions(
             SpecAttributeKind::AfterExpiryIf => generate_for_after_expiry_if(attr_tokens, item),
             SpecAttributeKind::Pure => generate_for_pure(attr_tokens, item),
             SpecAttributeKind::Trusted => generate_for_trusted(attr_tokens, item),
+            SpecAttributeKind::Ghost => generate_for_ghost(attr_tokens, item),
         };
         let (new_items, new_attributes) = rewriting_result?;
         generated_items.extend(new_items);
@@ -101,13 +102,17 @@ fn generate_for_requires(attr: TokenStream, item: &untyped::AnyFnItem) -> Genera
     let mut rewriter = rewriter::AstRewriter::new();
     let spec_id = rewriter.generate_spec_id();
     let spec_id_str = spec_id.to_string();
+    let (unbounded_arithmetic, attr) = split_unbounded_annotation(attr);
     let assertion = rewriter.parse_assertion(spec_id, attr)?;
-    let spec_item = rewriter.generate_spec_item_fn(
+    let mut spec_item = rewriter.generate_spec_item_fn(
         rewriter::SpecItemType::Precondition,
         spec_id,
         assertion,
         &item
     )?;
+    if unbounded_arithmetic {
+        mark_unbounded_arithmetic(&mut spec_item);
+    }
     Ok((
         vec![spec_item],
         vec![parse_quote!(#[prusti::pre_spec_id_ref = #spec_id_str])],
@@ -119,19 +124,45 @@ fn generate_for_ensures(attr: TokenStream, item: &untyped::AnyFnItem) -> Generat
     let mut rewriter = rewriter::AstRewriter::new();
     let spec_id = rewriter.generate_spec_id();
     let spec_id_str = spec_id.to_string();
+    let (unbounded_arithmetic, attr) = split_unbounded_annotation(attr);
     let assertion = rewriter.parse_assertion(spec_id, attr)?;
-    let spec_item = rewriter.generate_spec_item_fn(
+    let mut spec_item = rewriter.generate_spec_item_fn(
         rewriter::SpecItemType::Postcondition,
         spec_id,
         assertion,
         &item
     )?;
+    if unbounded_arithmetic {
+        mark_unbounded_arithmetic(&mut spec_item);
+    }
     Ok((
         vec![spec_item],
         vec![parse_quote!(#[prusti::post_spec_id_ref = #spec_id_str])],
     ))
 }
 
+/// Split the `#[unbounded]` annotation off the start of the tokens of a
+/// precondition or postcondition. The arithmetic of an annotated assertion is
+/// encoded on mathematical integers, even if overflow checks are enabled.
+fn split_unbounded_annotation(tokens: TokenStream) -> (bool, TokenStream) {
+    let mut iter = tokens.clone().into_iter();
+    if let (Some(TokenTree::Punct(pound)), Some(TokenTree::Group(group))) = (iter.next(), iter.next()) {
+        if pound.as_char() == '#' && group.delimiter() == Delimiter::Bracket
+            && group.stream().to_string() == "unbounded" {
+            return (true, iter.collect());
+        }
+    }
+    (false, tokens)
+}
+
+/// Mark a spec item so that the encoder does not check the overflows of its
+/// arithmetic operations.
+fn mark_unbounded_arithmetic(spec_item: &mut syn::Item) {
+    if let syn::Item::Fn(spec_item) = spec_item {
+        spec_item.attrs.push(parse_quote!(#[prusti::unbounded_arithmetic]));
+    }
+}
+
 /// Check if the given expression is identifier `result`.
 fn check_is_result(reference: &Option<untyped::Expression>) -> syn::Result<()> {
     if let Some(untyped::Expression { expr, ..}) = reference {
@@ -216,6 +247,55 @@ fn generate_for_trusted(_attr: TokenStream, _item: &untyped::AnyFnItem) -> Gener
     ))
 }
 
+/// Generate spec items and attributes to typecheck and later retrieve "ghost" annotations.
+/// Ghost functions are encoded like pure functions.
+fn generate_for_ghost(_attr: TokenStream, _item: &untyped::AnyFnItem) -> GeneratedResult {
+    Ok((
+        vec![],
+        vec![parse_quote!(#[prusti::pure]), parse_quote!(#[prusti::ghost])],
+    ))
+}
+
+/// Generate the ghost function of `predicate! { fn name(..) -> bool { .. } }`,
+/// whose body is an assertion. The function is trusted, and its result is
+/// defined by the postcondition `result <==> body` (see
+/// `AstRewriter::parse_predicate`).
+pub fn predicate(tokens: TokenStream) -> TokenStream {
+    let mut tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        _ => return syn::Error::new(
+            Span::call_site(),
+            "expected a predicate of the form `fn name(..) -> bool { .. }`",
+        ).to_compile_error(),
+    };
+    let signature: TokenStream = tokens.into_iter().collect();
+    // The body is an assertion, which is not valid Rust, so the function is
+    // parsed without it.
+    let mut item: syn::ItemFn = handle_result!(syn::parse2(quote! { #signature {} }));
+    let mut rewriter = rewriter::AstRewriter::new();
+    let spec_id = rewriter.generate_spec_id();
+    let spec_id_str = spec_id.to_string();
+    let assertion = handle_result!(rewriter.parse_predicate(spec_id, body));
+    let spec_item = handle_result!(rewriter.generate_spec_item_fn(
+        rewriter::SpecItemType::Predicate,
+        spec_id,
+        assertion,
+        &untyped::AnyFnItem::Fn(item.clone()),
+    ));
+    item.block = parse_quote! {{
+        unimplemented!("predicates can only be used in specifications")
+    }};
+    quote! {
+        #spec_item
+        #[prusti::pure]
+        #[prusti::trusted]
+        #[prusti::ghost]
+        #[prusti::pred_spec_id_ref = #spec_id_str]
+        #item
+    }
+}
+
 pub fn body_invariant(tokens: TokenStream) -> TokenStream {
     let mut rewriter = rewriter::AstRewriter::new();
     let spec_id = rewriter.generate_spec_id();
@@ -228,6 +308,18 @@ pub fn body_invariant(tokens: TokenStream) -> TokenStream {
     }
 }
 
+pub fn prusti_assertion(tokens: TokenStream) -> TokenStream {
+    let mut rewriter = rewriter::AstRewriter::new();
+    let spec_id = rewriter.generate_spec_id();
+    let assertion = handle_result!(rewriter.parse_assertion(spec_id, tokens));
+    let check = rewriter.generate_spec_assert(spec_id, assertion);
+    quote! {
+        if false {
+            #check
+        }
+    }
+}
+
 /// Unlike the functions above, which are only called from
 /// prusti-contracts-internal, this function also needs to be called
 /// from prusti-contracts-impl, because we still need to parse the
@@ -1,5 +1,6 @@
-use crate::specifications::common::{ExpressionIdGenerator, SpecificationIdGenerator};
-use crate::specifications::untyped::{self, EncodeTypeCheck};
+use crate::specifications::common::{self, ExpressionIdGenerator, SpecificationIdGenerator};
+use crate::specifications::preparser::ExpressionWithoutId;
+use crate::specifications::untyped::{self, AssignExpressionId, EncodeTypeCheck};
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, format_ident};
 use syn::spanned::Spanned;
@@ -13,6 +14,7 @@ pub(crate) struct AstRewriter {
 pub enum SpecItemType {
     Precondition,
     Postcondition,
+    Predicate,
 }
 
 impl std::fmt::Display for SpecItemType {
@@ -20,6 +22,7 @@ impl std::fmt::Display for SpecItemType {
         match self {
             SpecItemType::Precondition => write!(f, "pre"),
             SpecItemType::Postcondition => write!(f, "post"),
+            SpecItemType::Predicate => write!(f, "pred"),
         }
     }
 }
@@ -42,7 +45,26 @@ impl AstRewriter {
         spec_id: untyped::SpecificationId,
         tokens: TokenStream,
     ) -> syn::Result<untyped::Assertion> {
-        untyped::Assertion::parse(tokens, spec_id, &mut self.expr_id_generator)
+        let assertion = untyped::Assertion::parse(tokens, spec_id, &mut self.expr_id_generator)?;
+        Ok(assertion.infer_missing_triggers(&mut self.expr_id_generator))
+    }
+
+    /// Parse the body of a predicate as the assertion `result <==> body`, which
+    /// defines the result of the predicate.
+    pub fn parse_predicate(
+        &mut self,
+        spec_id: untyped::SpecificationId,
+        tokens: TokenStream,
+    ) -> syn::Result<untyped::Assertion> {
+        let body = self.parse_assertion(spec_id, tokens)?;
+        let result: untyped::Expression = ExpressionWithoutId::new(syn::parse_quote! { result })
+            .assign_id(spec_id, &mut self.expr_id_generator);
+        Ok(untyped::Assertion {
+            kind: box common::AssertionKind::Iff(
+                untyped::Assertion { kind: box common::AssertionKind::Expr(result) },
+                body,
+            ),
+        })
     }
 
     /// Parse a pledge.
@@ -91,7 +113,8 @@ impl AstRewriter {
 
     /// Generate a dummy function for checking the given precondition or postcondition.
     ///
-    /// `spec_type` should be either `"pre"` or `"post"`.
+    /// The function of a postcondition or a predicate also takes the `result`
+    /// of `item`.
     pub fn generate_spec_item_fn(
         &mut self,
         spec_type: SpecItemType,
@@ -124,7 +147,7 @@ impl AstRewriter {
         };
         spec_item.sig.generics = item.sig().generics.clone();
         spec_item.sig.inputs = item.sig().inputs.clone();
-        if spec_type == SpecItemType::Postcondition {
+        if spec_type != SpecItemType::Precondition {
             let fn_arg = self.generate_result_arg(item);
             spec_item.sig.inputs.push(fn_arg);
         }
@@ -153,6 +176,28 @@ impl AstRewriter {
         }
     }
 
+    /// Generate statements for checking the given inline assertion.
+    pub fn generate_spec_assert(
+        &mut self,
+        spec_id: untyped::SpecificationId,
+        assertion: untyped::Assertion,
+    ) -> TokenStream {
+        let mut statements = TokenStream::new();
+        assertion.encode_type_check(&mut statements);
+        let spec_id_str = spec_id.to_string();
+        let assertion_json = crate::specifications::json::to_json_string(&assertion);
+        quote! {
+            #[allow(unused_must_use, unused_variables)]
+            #[prusti::spec_only]
+            #[prusti::assert_spec]
+            #[prusti::spec_id = #spec_id_str]
+            #[prusti::assertion = #assertion_json]
+            || {
+                #statements
+            };
+        }
+    }
+
     /// Generate statements for checking a closure specification.
     /// TODO: arguments, result (types are typically not known yet after parsing...)
     pub fn generate_cl_spec(
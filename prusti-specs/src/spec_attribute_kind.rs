@@ -9,6 +9,7 @@ pub enum SpecAttributeKind {
     AfterExpiryIf,
     Pure,
     Trusted,
+    Ghost,
 }
 
 impl TryFrom<String> for SpecAttributeKind {
@@ -22,6 +23,7 @@ impl TryFrom<String> for SpecAttributeKind {
             "after_expiry_if" => Ok(SpecAttributeKind::AfterExpiryIf),
             "pure" => Ok(SpecAttributeKind::Pure),
             "trusted" => Ok(SpecAttributeKind::Trusted),
+            "ghost" => Ok(SpecAttributeKind::Ghost),
             _ => Err(name),
         }
     }
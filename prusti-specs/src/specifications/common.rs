@@ -5,8 +5,11 @@
 
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Debug};
+use std::hash::{Hash, Hasher};
 use uuid::Uuid;
+use super::triggers::TriggerStrategy;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// A specification type.
@@ -17,13 +20,19 @@ pub enum SpecType {
     Postcondition,
     /// Loop invariant or struct invariant
     Invariant,
+    /// Assertion at a program point, written with `prusti_assert!`.
+    Assert,
+    /// Ghost function, which can only be used in specifications.
+    Ghost,
+    /// Definition of a predicate declared with `predicate!`.
+    Predicate,
 }
 
 #[derive(Debug)]
 /// A conversion from string into specification type error.
 pub enum TryFromStringError {
     /// Reported when the string being converted is not one of the
-    /// following: `requires`, `ensures`, `invariant`.
+    /// following: `requires`, `ensures`, `invariant`, `ghost`, `predicate`.
     UnknownSpecificationType,
 }
 
@@ -35,6 +44,8 @@ impl<'a> TryFrom<&'a str> for SpecType {
             "requires" => Ok(SpecType::Precondition),
             "ensures" => Ok(SpecType::Postcondition),
             "invariant" => Ok(SpecType::Invariant),
+            "ghost" => Ok(SpecType::Ghost),
+            "predicate" => Ok(SpecType::Predicate),
             _ => Err(TryFromStringError::UnknownSpecificationType),
         }
     }
@@ -70,14 +81,32 @@ impl SpecificationId {
     }
 }
 
-pub(crate) struct SpecificationIdGenerator {}
+/// A generator of fresh specification ids.
+pub struct SpecificationIdGenerator {
+    /// The largest id generated or seen so far. Generators without it
+    /// produce random ids.
+    last_id: Option<u128>,
+}
 
 impl SpecificationIdGenerator {
     pub(crate) fn new() -> Self {
-        Self {}
+        Self { last_id: None }
+    }
+    /// A generator of ids that are larger than all `existing_ids`, e.g. the
+    /// keys of a loaded `SpecificationMap`, so that the ids of synthesized
+    /// specifications never collide with the ones of parsed specifications.
+    pub fn seeded_from<'a, I: IntoIterator<Item = &'a SpecificationId>>(existing_ids: I) -> Self {
+        let last_id = existing_ids.into_iter().map(|id| id.0.as_u128()).max().unwrap_or(0);
+        Self { last_id: Some(last_id) }
     }
-    pub(crate) fn generate(&mut self) -> SpecificationId {
-        SpecificationId(Uuid::new_v4())
+    pub fn generate(&mut self) -> SpecificationId {
+        match self.last_id {
+            Some(ref mut last_id) => {
+                *last_id = last_id.checked_add(1).expect("no specification id is left");
+                SpecificationId(Uuid::from_u128(*last_id))
+            }
+            None => SpecificationId(Uuid::new_v4()),
+        }
     }
 }
 
@@ -148,6 +177,39 @@ pub struct Expression<EID, ET> {
     pub id: EID,
     /// Actual expression.
     pub expr: ET,
+    /// Whether the expression contains an `old(..)` call, whose argument
+    /// refers to the state at the beginning of the call.
+    pub contains_old: bool,
+}
+
+// `contains_old` is determined by `expr`, so it is not part of the identity of
+// an expression.
+impl<EID: PartialEq, ET: PartialEq> PartialEq for Expression<EID, ET> {
+    fn eq(&self, other: &Self) -> bool {
+        self.spec_id == other.spec_id && self.id == other.id && self.expr == other.expr
+    }
+}
+
+impl<EID: Eq, ET: Eq> Eq for Expression<EID, ET> {}
+
+impl<EID: Hash, ET: Hash> Hash for Expression<EID, ET> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.spec_id.hash(state);
+        self.id.hash(state);
+        self.expr.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The pattern of a `matches!(scrutinee, pattern)` assertion.
+pub struct Pattern<EID, ET> {
+    /// Identifier of the specification to which this pattern belongs.
+    pub spec_id: SpecificationId,
+    /// Unique identifier.
+    pub id: EID,
+    /// The whole `matches!(scrutinee, pattern)` check, from which the
+    /// pattern is resolved once it is type-checked.
+    pub check: ET,
 }
 
 #[derive(Debug, Clone)]
@@ -157,6 +219,481 @@ pub struct Assertion<EID, ET, AT> {
     pub kind: Box<AssertionKind<EID, ET, AT>>,
 }
 
+impl<EID: PartialEq, ET, AT: PartialEq> Assertion<EID, ET, AT> {
+    /// Merge directly-nested universal quantifiers such as
+    /// `forall i :: forall j :: p(i, j)` into a single quantifier
+    /// `forall i, j :: p(i, j)`, so that triggers can be inferred for all
+    /// variables at once. The bound variables are concatenated in order, the
+    /// trigger sets are merged without duplicates and the enumerated indices
+    /// of the inner quantifier are shifted past the outer variables.
+    ///
+    /// Quantifiers are kept nested if the inner one shadows a variable of the
+    /// outer one, or if both have different explicit weights.
+    pub fn flatten_nested_foralls(self) -> Assertion<EID, ET, AT> {
+        let kind = match *self.kind {
+            AssertionKind::Expr(expr) => AssertionKind::Expr(expr),
+            AssertionKind::Matches(scrutinee, pattern) => AssertionKind::Matches(scrutinee, pattern),
+            AssertionKind::And(conjuncts) => AssertionKind::And(
+                conjuncts.into_iter().map(|conjunct| conjunct.flatten_nested_foralls()).collect()
+            ),
+            AssertionKind::Or(disjuncts) => AssertionKind::Or(
+                disjuncts.into_iter().map(|disjunct| disjunct.flatten_nested_foralls()).collect()
+            ),
+            AssertionKind::Implies(lhs, rhs) => AssertionKind::Implies(
+                lhs.flatten_nested_foralls(),
+                rhs.flatten_nested_foralls(),
+            ),
+            AssertionKind::Iff(lhs, rhs) => AssertionKind::Iff(
+                lhs.flatten_nested_foralls(),
+                rhs.flatten_nested_foralls(),
+            ),
+            AssertionKind::TypeCond(vars, body) => AssertionKind::TypeCond(
+                vars,
+                body.flatten_nested_foralls(),
+            ),
+            AssertionKind::ForAll(mut vars, mut triggers, body) => {
+                // The body has already been flattened, so there is at most
+                // one directly-nested quantifier left.
+                let body = match *body.flatten_nested_foralls().kind {
+                    AssertionKind::ForAll(inner_vars, inner_triggers, inner_body)
+                        if vars.can_merge(&inner_vars) =>
+                    {
+                        let offset = vars.arity();
+                        vars.weight = vars.weight.or(inner_vars.weight);
+                        vars.enumerated.extend(inner_vars.enumerated.into_iter().map(|index| index + offset));
+                        vars.vars.extend(inner_vars.vars);
+                        // The strategies stay aligned with the triggers, and
+                        // the written triggers get no strategies.
+                        let mut inner_strategies = inner_vars.trigger_strategies.into_iter();
+                        for trigger in inner_triggers {
+                            let strategies = inner_strategies.next().unwrap_or_default();
+                            if !triggers.triggers().iter().any(|existing| existing.same_terms(&trigger)) {
+                                if !strategies.is_empty() {
+                                    vars.trigger_strategies.resize(triggers.0.len(), vec![]);
+                                }
+                                if !vars.trigger_strategies.is_empty() {
+                                    vars.trigger_strategies.push(strategies);
+                                }
+                                triggers.0.push(trigger);
+                            }
+                        }
+                        inner_body
+                    }
+                    kind => Assertion { kind: Box::new(kind) },
+                };
+                AssertionKind::ForAll(vars, triggers, body)
+            }
+            AssertionKind::Exists(vars, triggers, body) => AssertionKind::Exists(
+                vars,
+                triggers,
+                body.flatten_nested_foralls(),
+            ),
+            AssertionKind::BoundedForAll(vars, lower, upper, body) => AssertionKind::BoundedForAll(
+                vars,
+                lower,
+                upper,
+                body.flatten_nested_foralls(),
+            ),
+            AssertionKind::Let(vars, value, body) => AssertionKind::Let(
+                vars,
+                value,
+                body.flatten_nested_foralls(),
+            ),
+        };
+        Assertion { kind: Box::new(kind) }
+    }
+
+    /// The nesting depth of the assertion: 1 for an expression or a pattern
+    /// match, plus 1 for every enclosing conjunction, disjunction, implication,
+    /// quantifier, type condition or let binding.
+    pub fn depth(&self) -> usize {
+        let nested_depth = match *self.kind {
+            AssertionKind::Expr(_) |
+            AssertionKind::Matches(..) => 0,
+            AssertionKind::And(ref conjuncts) |
+            AssertionKind::Or(ref conjuncts) => {
+                conjuncts.iter().map(|conjunct| conjunct.depth()).max().unwrap_or(0)
+            }
+            AssertionKind::Implies(ref lhs, ref rhs) |
+            AssertionKind::Iff(ref lhs, ref rhs) => lhs.depth().max(rhs.depth()),
+            AssertionKind::TypeCond(_, ref body) |
+            AssertionKind::ForAll(_, _, ref body) |
+            AssertionKind::Exists(_, _, ref body) |
+            AssertionKind::BoundedForAll(_, _, _, ref body) |
+            AssertionKind::Let(_, _, ref body) => body.depth(),
+        };
+        nested_depth + 1
+    }
+
+    /// Collapse nested implications such as `a ==> (b ==> c)` into
+    /// `(a && b) ==> c`, and rewrite an implication `!a ==> b` whose lhs is a
+    /// negated expression into the disjunction `a || b`. The conjuncts of the
+    /// new lhs are flattened, the quantifiers and type conditions are kept as
+    /// they are (including their triggers) and only their bodies are
+    /// normalized.
+    ///
+    /// The negation is part of the Rust expression, so `negated_operand`
+    /// returns the operand `a` of an expression `!a`, or `None` if the
+    /// expression is not a negation.
+    pub fn normalize_implications_with(
+        self,
+        negated_operand: &dyn Fn(&ET) -> Option<ET>,
+    ) -> Assertion<EID, ET, AT> {
+        let kind = match *self.kind {
+            AssertionKind::Expr(expr) => AssertionKind::Expr(expr),
+            AssertionKind::Matches(scrutinee, pattern) => AssertionKind::Matches(scrutinee, pattern),
+            AssertionKind::And(conjuncts) => AssertionKind::And(
+                conjuncts.into_iter()
+                    .map(|conjunct| conjunct.normalize_implications_with(negated_operand))
+                    .collect()
+            ),
+            AssertionKind::Or(disjuncts) => AssertionKind::Or(
+                disjuncts.into_iter()
+                    .map(|disjunct| disjunct.normalize_implications_with(negated_operand))
+                    .collect()
+            ),
+            AssertionKind::Implies(lhs, rhs) => {
+                let mut conjuncts = vec![];
+                let mut lhs = lhs.normalize_implications_with(negated_operand);
+                // The rhs has already been normalized, so there is at most one
+                // directly-nested implication left.
+                let rhs = match *rhs.normalize_implications_with(negated_operand).kind {
+                    AssertionKind::Implies(inner_lhs, inner_rhs) => {
+                        push_conjuncts(&mut conjuncts, lhs);
+                        push_conjuncts(&mut conjuncts, inner_lhs);
+                        lhs = Assertion { kind: Box::new(AssertionKind::And(conjuncts)) };
+                        inner_rhs
+                    }
+                    kind => Assertion { kind: Box::new(kind) },
+                };
+                match *lhs.kind {
+                    AssertionKind::Expr(expr) => match negated_operand(&expr.expr) {
+                        Some(operand) => {
+                            let operand = Expression {
+                                spec_id: expr.spec_id,
+                                id: expr.id,
+                                expr: operand,
+                                contains_old: expr.contains_old,
+                            };
+                            let mut disjuncts = vec![
+                                Assertion { kind: Box::new(AssertionKind::Expr(operand)) },
+                            ];
+                            push_disjuncts(&mut disjuncts, rhs);
+                            AssertionKind::Or(disjuncts)
+                        }
+                        None => AssertionKind::Implies(
+                            Assertion { kind: Box::new(AssertionKind::Expr(expr)) },
+                            rhs,
+                        ),
+                    },
+                    kind => AssertionKind::Implies(Assertion { kind: Box::new(kind) }, rhs),
+                }
+            }
+            AssertionKind::Iff(lhs, rhs) => AssertionKind::Iff(
+                lhs.normalize_implications_with(negated_operand),
+                rhs.normalize_implications_with(negated_operand),
+            ),
+            AssertionKind::TypeCond(vars, body) => AssertionKind::TypeCond(
+                vars,
+                body.normalize_implications_with(negated_operand),
+            ),
+            AssertionKind::ForAll(vars, triggers, body) => AssertionKind::ForAll(
+                vars,
+                triggers,
+                body.normalize_implications_with(negated_operand),
+            ),
+            AssertionKind::Exists(vars, triggers, body) => AssertionKind::Exists(
+                vars,
+                triggers,
+                body.normalize_implications_with(negated_operand),
+            ),
+            AssertionKind::BoundedForAll(vars, lower, upper, body) => AssertionKind::BoundedForAll(
+                vars,
+                lower,
+                upper,
+                body.normalize_implications_with(negated_operand),
+            ),
+            AssertionKind::Let(vars, value, body) => AssertionKind::Let(
+                vars,
+                value,
+                body.normalize_implications_with(negated_operand),
+            ),
+        };
+        Assertion { kind: Box::new(kind) }
+    }
+}
+
+impl<EID, ET, AT> Assertion<EID, ET, AT> {
+    /// Iterates over all the expressions of the assertion, including the
+    /// terms of the triggers, in source order.
+    pub fn expressions(&self) -> impl Iterator<Item = &Expression<EID, ET>> {
+        let mut exprs = vec![];
+        self.collect_expressions(&mut exprs);
+        exprs.into_iter()
+    }
+
+    /// The variables of all the quantifiers (`forall`, `exists` and bounded
+    /// `forall`) of the assertion, outer quantifiers first.
+    pub fn quantifiers(&self) -> Vec<&ForAllVars<EID, AT>> {
+        let mut quantifiers = vec![];
+        self.collect_quantifiers(&mut quantifiers);
+        quantifiers
+    }
+
+    fn collect_quantifiers<'a>(&'a self, quantifiers: &mut Vec<&'a ForAllVars<EID, AT>>) {
+        match *self.kind {
+            AssertionKind::Expr(_) |
+            AssertionKind::Matches(..) => {}
+            AssertionKind::And(ref conjuncts) |
+            AssertionKind::Or(ref conjuncts) => {
+                for conjunct in conjuncts {
+                    conjunct.collect_quantifiers(quantifiers);
+                }
+            }
+            AssertionKind::Implies(ref lhs, ref rhs) |
+            AssertionKind::Iff(ref lhs, ref rhs) => {
+                lhs.collect_quantifiers(quantifiers);
+                rhs.collect_quantifiers(quantifiers);
+            }
+            AssertionKind::TypeCond(_, ref body) |
+            AssertionKind::Let(_, _, ref body) => body.collect_quantifiers(quantifiers),
+            AssertionKind::ForAll(ref vars, _, ref body) |
+            AssertionKind::Exists(ref vars, _, ref body) |
+            AssertionKind::BoundedForAll(ref vars, _, _, ref body) => {
+                quantifiers.push(vars);
+                body.collect_quantifiers(quantifiers);
+            }
+        }
+    }
+
+    /// Whether an expression of the assertion refers to the pre-state with
+    /// `old(..)`. The calls are detected when the specification is parsed, so
+    /// the closures of the expressions are not inspected.
+    pub fn contains_old(&self) -> bool {
+        self.expressions().any(|expr| expr.contains_old)
+    }
+
+    fn collect_expressions<'a>(&'a self, exprs: &mut Vec<&'a Expression<EID, ET>>) {
+        match *self.kind {
+            AssertionKind::Expr(ref expr) |
+            AssertionKind::Matches(ref expr, _) => exprs.push(expr),
+            AssertionKind::And(ref conjuncts) |
+            AssertionKind::Or(ref conjuncts) => {
+                for conjunct in conjuncts {
+                    conjunct.collect_expressions(exprs);
+                }
+            }
+            AssertionKind::Implies(ref lhs, ref rhs) |
+            AssertionKind::Iff(ref lhs, ref rhs) => {
+                lhs.collect_expressions(exprs);
+                rhs.collect_expressions(exprs);
+            }
+            AssertionKind::TypeCond(_, ref body) => body.collect_expressions(exprs),
+            AssertionKind::ForAll(_, ref triggers, ref body) |
+            AssertionKind::Exists(_, ref triggers, ref body) => {
+                body.collect_expressions(exprs);
+                for trigger in triggers.triggers() {
+                    exprs.extend(trigger.terms());
+                }
+            }
+            AssertionKind::BoundedForAll(_, ref lower, ref upper, ref body) => {
+                exprs.push(lower);
+                exprs.push(upper);
+                body.collect_expressions(exprs);
+            }
+            AssertionKind::Let(_, ref value, ref body) => {
+                exprs.push(value);
+                body.collect_expressions(exprs);
+            }
+        }
+    }
+
+    /// A rough estimate of the cost of verifying the assertion, e.g. to start
+    /// with the most expensive procedures when verifying in parallel. The
+    /// costs are only meaningful relative to each other: an expression costs
+    /// 1, an implication adds a case split to its operands, and a quantifier
+    /// multiplies the cost of its body, so that nested quantifiers dominate.
+    pub fn estimated_cost(&self) -> u64 {
+        const EXPRESSION_COST: u64 = 1;
+        const IMPLICATION_COST: u64 = 2;
+        const QUANTIFIER_FACTOR: u64 = 10;
+        match *self.kind {
+            AssertionKind::Expr(_) |
+            AssertionKind::Matches(..) => EXPRESSION_COST,
+            AssertionKind::And(ref conjuncts) => {
+                conjuncts.iter().fold(0, |cost, conjunct| cost.saturating_add(conjunct.estimated_cost()))
+            }
+            // Every disjunct is a case split
+            AssertionKind::Or(ref disjuncts) => {
+                disjuncts.iter().fold(0, |cost, disjunct| {
+                    cost.saturating_add(IMPLICATION_COST).saturating_add(disjunct.estimated_cost())
+                })
+            }
+            AssertionKind::Implies(ref lhs, ref rhs) => {
+                IMPLICATION_COST
+                    .saturating_add(lhs.estimated_cost())
+                    .saturating_add(rhs.estimated_cost())
+            }
+            // Both directions are implications
+            AssertionKind::Iff(ref lhs, ref rhs) => {
+                IMPLICATION_COST
+                    .saturating_add(lhs.estimated_cost())
+                    .saturating_add(rhs.estimated_cost())
+                    .saturating_mul(2)
+            }
+            // Type conditions are resolved statically
+            AssertionKind::TypeCond(_, ref body) => body.estimated_cost(),
+            AssertionKind::ForAll(ref vars, _, ref body) |
+            AssertionKind::Exists(ref vars, _, ref body) => {
+                QUANTIFIER_FACTOR
+                    .saturating_mul(vars.arity().max(1) as u64)
+                    .saturating_mul(body.estimated_cost())
+            }
+            AssertionKind::BoundedForAll(ref vars, _, _, ref body) => {
+                QUANTIFIER_FACTOR
+                    .saturating_mul(vars.arity().max(1) as u64)
+                    .saturating_mul(
+                        IMPLICATION_COST
+                            .saturating_add(2 * EXPRESSION_COST)
+                            .saturating_add(body.estimated_cost())
+                    )
+            }
+            AssertionKind::Let(_, _, ref body) => EXPRESSION_COST.saturating_add(body.estimated_cost()),
+        }
+    }
+
+    /// If the assertion is a quantifier whose body is an implication, e.g.
+    /// `forall(|i: usize| i < n ==> p(i))`, returns its bound variables, the
+    /// guard (the left-hand side of the implication) and the conclusion.
+    pub fn as_guarded_forall(
+        &self,
+    ) -> Option<(&ForAllVars<EID, AT>, &Assertion<EID, ET, AT>, &Assertion<EID, ET, AT>)> {
+        match *self.kind {
+            AssertionKind::ForAll(ref vars, _, ref body) => match *body.kind {
+                AssertionKind::Implies(ref guard, ref conclusion) => Some((vars, guard, conclusion)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Constant-fold the boolean literals of the assertion, e.g.
+    /// `true && a` into `a`, `true ==> a` into `a` and `false ==> a` into
+    /// `true`. Expressions are opaque at this level, so `literal` tells the
+    /// value of the expressions that are literals. The literal `true` is
+    /// represented by the empty conjunction, and `false` by the empty
+    /// disjunction.
+    ///
+    /// Quantifiers and let bindings are kept, including their triggers, and
+    /// only their bodies are simplified.
+    pub fn simplify_with(self, literal: &dyn Fn(&Expression<EID, ET>) -> Option<bool>) -> Self {
+        let value = |assertion: &Self| match *assertion.kind {
+            AssertionKind::Expr(ref expr) => literal(expr),
+            AssertionKind::And(ref conjuncts) if conjuncts.is_empty() => Some(true),
+            AssertionKind::Or(ref disjuncts) if disjuncts.is_empty() => Some(false),
+            _ => None,
+        };
+        let truth = || Assertion { kind: Box::new(AssertionKind::And(vec![])) };
+        let kind = match *self.kind {
+            AssertionKind::And(conjuncts) => {
+                let mut simplified = vec![];
+                for conjunct in conjuncts {
+                    let conjunct = conjunct.simplify_with(literal);
+                    match value(&conjunct) {
+                        Some(true) => {}
+                        Some(false) => return conjunct,
+                        None => push_conjuncts(&mut simplified, conjunct),
+                    }
+                }
+                if simplified.len() == 1 {
+                    return simplified.pop().unwrap();
+                }
+                AssertionKind::And(simplified)
+            }
+            AssertionKind::Or(disjuncts) => {
+                let mut simplified = vec![];
+                for disjunct in disjuncts {
+                    let disjunct = disjunct.simplify_with(literal);
+                    match value(&disjunct) {
+                        Some(true) => return disjunct,
+                        Some(false) => {}
+                        None => match *disjunct.kind {
+                            AssertionKind::Or(inner) => simplified.extend(inner),
+                            kind => simplified.push(Assertion { kind: Box::new(kind) }),
+                        },
+                    }
+                }
+                if simplified.len() == 1 {
+                    return simplified.pop().unwrap();
+                }
+                AssertionKind::Or(simplified)
+            }
+            AssertionKind::Implies(lhs, rhs) => {
+                let lhs = lhs.simplify_with(literal);
+                let rhs = rhs.simplify_with(literal);
+                match (value(&lhs), value(&rhs)) {
+                    (Some(true), _) => return rhs,
+                    (Some(false), _) | (_, Some(true)) => return truth(),
+                    _ => AssertionKind::Implies(lhs, rhs),
+                }
+            }
+            AssertionKind::Iff(lhs, rhs) => {
+                let lhs = lhs.simplify_with(literal);
+                let rhs = rhs.simplify_with(literal);
+                match (value(&lhs), value(&rhs)) {
+                    (Some(true), _) => return rhs,
+                    (_, Some(true)) => return lhs,
+                    _ => AssertionKind::Iff(lhs, rhs),
+                }
+            }
+            AssertionKind::TypeCond(vars, body) => {
+                let body = body.simplify_with(literal);
+                if value(&body) == Some(true) {
+                    return truth();
+                }
+                AssertionKind::TypeCond(vars, body)
+            }
+            AssertionKind::ForAll(vars, triggers, body) => {
+                AssertionKind::ForAll(vars, triggers, body.simplify_with(literal))
+            }
+            AssertionKind::Exists(vars, triggers, body) => {
+                AssertionKind::Exists(vars, triggers, body.simplify_with(literal))
+            }
+            AssertionKind::BoundedForAll(vars, lower, upper, body) => {
+                AssertionKind::BoundedForAll(vars, lower, upper, body.simplify_with(literal))
+            }
+            AssertionKind::Let(vars, bound, body) => {
+                AssertionKind::Let(vars, bound, body.simplify_with(literal))
+            }
+            kind @ AssertionKind::Expr(_) |
+            kind @ AssertionKind::Matches(..) => kind,
+        };
+        Assertion { kind: Box::new(kind) }
+    }
+}
+
+/// Push `assertion` to `conjuncts`, or its conjuncts if it is a conjunction.
+fn push_conjuncts<EID, ET, AT>(
+    conjuncts: &mut Vec<Assertion<EID, ET, AT>>,
+    assertion: Assertion<EID, ET, AT>,
+) {
+    match *assertion.kind {
+        AssertionKind::And(inner) => conjuncts.extend(inner),
+        kind => conjuncts.push(Assertion { kind: Box::new(kind) }),
+    }
+}
+
+/// Push `assertion` to `disjuncts`, or its disjuncts if it is a disjunction.
+fn push_disjuncts<EID, ET, AT>(
+    disjuncts: &mut Vec<Assertion<EID, ET, AT>>,
+    assertion: Assertion<EID, ET, AT>,
+) {
+    match *assertion.kind {
+        AssertionKind::Or(inner) => disjuncts.extend(inner),
+        kind => disjuncts.push(Assertion { kind: Box::new(kind) }),
+    }
+}
+
 #[derive(Debug, Clone)]
 /// A single trigger for a quantifier.
 pub struct Trigger<EID, ET>(pub Vec<Expression<EID, ET>>);
@@ -166,10 +703,26 @@ impl<EID, ET> Trigger<EID, ET> {
     pub fn new(terms: Vec<Expression<EID, ET>>) -> Trigger<EID, ET> {
         Trigger(terms)
     }
+    /// Construct a trigger that consists of a single term.
+    pub fn single(term: Expression<EID, ET>) -> Trigger<EID, ET> {
+        Trigger(vec![term])
+    }
     /// Getter for terms.
     pub fn terms(&self) -> &Vec<Expression<EID, ET>> {
         &self.0
     }
+    /// Mutable getter for terms, e.g. for rewriting them in place.
+    pub fn terms_mut(&mut self) -> &mut Vec<Expression<EID, ET>> {
+        &mut self.0
+    }
+}
+
+impl<EID: PartialEq, ET> Trigger<EID, ET> {
+    /// Whether both triggers consist of the same expressions.
+    pub fn same_terms(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() &&
+            self.0.iter().zip(other.0.iter()).all(|(a, b)| a.spec_id == b.spec_id && a.id == b.id)
+    }
 }
 
 impl<EID, ET> IntoIterator for Trigger<EID, ET> {
@@ -189,12 +742,36 @@ impl<EID, ET> TriggerSet<EID, ET> {
     pub fn new(triggers: Vec<Trigger<EID, ET>>) -> TriggerSet<EID, ET> {
         TriggerSet(triggers)
     }
+    /// Construct a trigger set with a trigger for each group of terms.
+    pub fn from_terms(groups: Vec<Vec<Expression<EID, ET>>>) -> TriggerSet<EID, ET> {
+        TriggerSet(groups.into_iter().map(Trigger::new).collect())
+    }
     /// Getter for triggers.
     pub fn triggers(&self) -> &Vec<Trigger<EID, ET>> {
         &self.0
     }
 }
 
+impl<EID: Clone + Eq + Hash, ET: Clone + Eq + Hash> TriggerSet<EID, ET> {
+    /// Remove the repeated terms of each trigger and then the repeated
+    /// triggers, keeping the first occurrence of each.
+    pub fn dedup(self) -> TriggerSet<EID, ET> {
+        let mut seen_triggers = HashSet::new();
+        let mut triggers = vec![];
+        for trigger in self.0 {
+            let mut seen_terms = HashSet::new();
+            let terms: Vec<_> = trigger.0
+                .into_iter()
+                .filter(|term| seen_terms.insert(term.clone()))
+                .collect();
+            if seen_triggers.insert(terms.clone()) {
+                triggers.push(Trigger(terms));
+            }
+        }
+        TriggerSet(triggers)
+    }
+}
+
 impl<EID, ET> IntoIterator for TriggerSet<EID, ET> {
     type Item = Trigger<EID, ET>;
     type IntoIter = ::std::vec::IntoIter<Self::Item>;
@@ -213,6 +790,98 @@ pub struct ForAllVars<EID, AT> {
     pub id: EID,
     /// Variables.
     pub vars: Vec<AT>,
+    /// Indices of the variables whose type is a fieldless enum, so that the
+    /// quantifier can be expanded over its variants. It is only computed for
+    /// typed specifications.
+    pub enumerated: Vec<usize>,
+    /// The instantiation weight of the quantifier, given by `weight = N`. The
+    /// default weight is used if there is none.
+    pub weight: Option<u32>,
+    /// For a quantifier whose triggers were inferred by Prusti instead of
+    /// being written with `triggers = [..]`, the strategies that infer each
+    /// trigger, in the order of the trigger set of the quantifier. It is empty
+    /// if all the triggers were written; a written trigger among inferred ones
+    /// has no strategies. Typed specifications only keep the triggers of the
+    /// configured strategy.
+    pub trigger_strategies: Vec<Vec<TriggerStrategy>>,
+}
+
+impl<EID, AT> ForAllVars<EID, AT> {
+    /// Whether some triggers of the quantifier were inferred.
+    pub fn has_inferred_triggers(&self) -> bool {
+        self.trigger_strategies.iter().any(|strategies| !strategies.is_empty())
+    }
+
+    /// Whether the trigger at `index` in the trigger set of the quantifier is
+    /// used with `strategy`, i.e. it was written or inferred by `strategy`.
+    pub fn uses_trigger(&self, index: usize, strategy: TriggerStrategy) -> bool {
+        match self.trigger_strategies.get(index) {
+            Some(strategies) => strategies.is_empty() || strategies.contains(&strategy),
+            None => true,
+        }
+    }
+}
+
+impl<EID, AT> ForAllVars<EID, AT> {
+    /// The number of bound variables.
+    pub fn arity(&self) -> usize {
+        self.vars.len()
+    }
+}
+
+impl<EID, AT: PartialEq> ForAllVars<EID, AT> {
+    /// Whether the variables of a directly-nested quantifier can be merged
+    /// into these ones without changing the meaning of the quantifier: no
+    /// variable is shadowed and the weights do not conflict.
+    fn can_merge(&self, inner: &Self) -> bool {
+        let shadows = inner.vars.iter().any(|var| self.vars.contains(var));
+        let conflicting_weights = match (self.weight, inner.weight) {
+            (Some(outer), Some(inner)) => outer != inner,
+            _ => false,
+        };
+        !shadows && !conflicting_weights
+    }
+}
+
+impl<EID, AT: Clone> ForAllVars<EID, AT> {
+    /// Iterate over the bound variables. For typed specifications, these are
+    /// the MIR locals paired with their resolved types.
+    pub fn typed_vars<'a>(&'a self) -> impl Iterator<Item = AT> + 'a {
+        self.vars.iter().cloned()
+    }
+}
+
+impl<EID, L: Debug, T: Display> ForAllVars<EID, (L, T)> {
+    /// Formats each witness as `name: type = value`, in the order of the
+    /// variables. Variables whose name is `None` are shown with their local.
+    pub fn format_witnesses<N: Display>(&self, names: &[Option<N>], witnesses: &[String]) -> Vec<String> {
+        self.vars.iter()
+            .zip(names)
+            .zip(witnesses)
+            .map(|(((local, ty), name), value)| {
+                let name = name.as_ref()
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("{:?}", local));
+                format!("{}: {} = {}", name, ty, value)
+            })
+            .collect()
+    }
+}
+
+impl<EID, L: Eq + Hash + Clone, T> ForAllVars<EID, (L, T)> {
+    /// Replaces the local of each variable by its image in `mapping`, keeping
+    /// its type, e.g. to relocate a specification copied from a callee into
+    /// the context of a caller. Variables whose local is not in `mapping` are
+    /// left unchanged.
+    pub fn remap_locals(self, mapping: &HashMap<L, L>) -> Self {
+        ForAllVars {
+            vars: self.vars
+                .into_iter()
+                .map(|(local, ty)| (mapping.get(&local).cloned().unwrap_or(local), ty))
+                .collect(),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -220,10 +889,17 @@ pub struct ForAllVars<EID, AT> {
 pub enum AssertionKind<EID, ET, AT> {
     /// A single Rust expression.
     Expr(Expression<EID, ET>),
+    /// A check `matches!(scrutinee, pattern)` that the scrutinee is of a given
+    /// enum variant.
+    Matches(Expression<EID, ET>, Pattern<EID, ET>),
     /// Conjunction &&.
     And(Vec<Assertion<EID, ET, AT>>),
+    /// Disjunction ||.
+    Or(Vec<Assertion<EID, ET, AT>>),
     /// Implication ==>
     Implies(Assertion<EID, ET, AT>, Assertion<EID, ET, AT>),
+    /// Bi-implication <==>
+    Iff(Assertion<EID, ET, AT>, Assertion<EID, ET, AT>),
     /// TODO < Even > ==> x % 2 == 0
     TypeCond(ForAllVars<EID, AT>, Assertion<EID, ET, AT>),
     /// Quantifier
@@ -232,6 +908,27 @@ pub enum AssertionKind<EID, ET, AT> {
         TriggerSet<EID, ET>,
         Assertion<EID, ET, AT>,
     ),
+    /// Existential quantifier
+    Exists(
+        ForAllVars<EID, AT>,
+        TriggerSet<EID, ET>,
+        Assertion<EID, ET, AT>,
+    ),
+    /// Quantifier over a range `forall i :: lo <= i && i < hi ==> body`,
+    /// storing the lower and the upper bound conditions of the variable.
+    BoundedForAll(
+        ForAllVars<EID, AT>,
+        Expression<EID, ET>,
+        Expression<EID, ET>,
+        Assertion<EID, ET, AT>,
+    ),
+    /// Binding `let x = value in body` of a single variable to the value of
+    /// a Rust expression.
+    Let(
+        ForAllVars<EID, AT>,
+        Expression<EID, ET>,
+        Assertion<EID, ET, AT>,
+    ),
 }
 
 #[derive(Debug, Clone)]
@@ -246,6 +943,26 @@ pub struct Pledge<EID, ET, AT> {
     pub rhs: Assertion<EID, ET, AT>,
 }
 
+/// Whether a pledge holds unconditionally (`after_expiry`) or only when its
+/// lhs holds (`after_expiry_if`).
+#[derive(Debug, Clone, Copy)]
+pub enum PledgeKind<'a, EID, ET, AT> {
+    /// `after_expiry(ref => rhs)`
+    Unconditional,
+    /// `after_expiry_if(ref => lhs, rhs)`, carrying the lhs.
+    Conditional(&'a Assertion<EID, ET, AT>),
+}
+
+impl<EID, ET, AT> Pledge<EID, ET, AT> {
+    /// Whether the pledge is conditional, based on the presence of the lhs.
+    pub fn kind(&self) -> PledgeKind<EID, ET, AT> {
+        match self.lhs {
+            Some(ref lhs) => PledgeKind::Conditional(lhs),
+            None => PledgeKind::Unconditional,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 /// Specification such as precondition, postcondition, or invariant.
 pub struct Specification<EID, ET, AT> {
@@ -272,6 +989,10 @@ impl<EID, ET, AT> LoopSpecification<EID, ET, AT> {
     pub fn is_empty(&self) -> bool {
         self.invariant.is_empty()
     }
+    /// The variables of all the quantifiers of the invariant.
+    pub fn quantifiers(&self) -> Vec<&ForAllVars<EID, AT>> {
+        self.invariant.iter().flat_map(|assertion| assertion.quantifiers()).collect()
+    }
 }
 
 /// Specification of a procedure.
@@ -299,6 +1020,292 @@ impl<EID, ET, AT> ProcedureSpecification<EID, ET, AT> {
     pub fn is_empty(&self) -> bool {
         self.pres.is_empty() && self.posts.is_empty()
     }
+    /// Whether the procedure has no specification at all, i.e. also no
+    /// pledges. Unlike `is_empty`, this implies that the contract of the
+    /// procedure does not depend on the specification.
+    pub fn is_trivial(&self) -> bool {
+        self.is_empty() && !self.has_pledges()
+    }
+    /// Whether the postcondition contains pledges, which affects how the
+    /// borrows of the procedure are handled.
+    pub fn has_pledges(&self) -> bool {
+        !self.pledges.is_empty()
+    }
+    pub fn pledges(&self) -> &[Pledge<EID, ET, AT>] {
+        &self.pledges
+    }
+    /// The variables of all the quantifiers of the preconditions, the
+    /// postconditions and then the pledges. Loop invariants are part of
+    /// `LoopSpecification` instead.
+    pub fn quantifiers(&self) -> Vec<&ForAllVars<EID, AT>> {
+        let mut quantifiers: Vec<_> = self.pres.iter()
+            .chain(&self.posts)
+            .flat_map(|assertion| assertion.quantifiers())
+            .collect();
+        for pledge in &self.pledges {
+            if let Some(ref lhs) = pledge.lhs {
+                quantifiers.extend(lhs.quantifiers());
+            }
+            quantifiers.extend(pledge.rhs.quantifiers());
+        }
+        quantifiers
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A refinement of a trait method specification that violates behavioral
+/// subtyping.
+pub enum RefinementError {
+    /// The trait method has no precondition, but the implementation declares
+    /// one, which is a strengthening of the precondition.
+    PreconditionStrengthened,
+}
+
+impl<EID: Clone, ET: Clone, AT: Clone> ProcedureSpecification<EID, ET, AT> {
+    /// Combine the specification `self` declared by a trait method with the
+    /// specification `impl_spec` of its implementation.
+    ///
+    /// The implementation inherits the postconditions of the trait method and
+    /// may add its own, which can only strengthen them. Preconditions declared
+    /// by the implementation replace the ones of the trait method; the proof
+    /// that they are weaker is left to the verification, but adding a
+    /// precondition to a method without one is rejected directly. The pledges
+    /// of the implementation, if any, replace the ones of the trait method.
+    pub fn refine_with(&self, impl_spec: &Self) -> Result<Self, RefinementError> {
+        let pres = if impl_spec.pres.is_empty() {
+            self.pres.clone()
+        } else if self.pres.is_empty() {
+            return Err(RefinementError::PreconditionStrengthened);
+        } else {
+            impl_spec.pres.clone()
+        };
+        let mut posts = self.posts.clone();
+        posts.extend(impl_spec.posts.iter().cloned());
+        let pledges = if impl_spec.pledges.is_empty() {
+            self.pledges.clone()
+        } else {
+            impl_spec.pledges.clone()
+        };
+        Ok(Self::new(pres, posts, pledges))
+    }
+}
+
+impl<EID: PartialEq, ET: PartialEq, AT: PartialEq> Assertion<EID, ET, AT> {
+    /// Whether both assertions have the same structure, with equal
+    /// expressions, quantified variables and triggers.
+    pub fn structurally_eq(&self, other: &Self) -> bool {
+        use AssertionKind::*;
+        let triggers_eq = |lhs: &TriggerSet<EID, ET>, rhs: &TriggerSet<EID, ET>| {
+            lhs.triggers().len() == rhs.triggers().len() &&
+                lhs.triggers().iter().zip(rhs.triggers()).all(|(l, r)| l.terms() == r.terms())
+        };
+        match (&*self.kind, &*other.kind) {
+            (Expr(lhs), Expr(rhs)) => lhs == rhs,
+            (Matches(lhs_scrutinee, lhs_pattern), Matches(rhs_scrutinee, rhs_pattern)) => {
+                lhs_scrutinee == rhs_scrutinee && lhs_pattern == rhs_pattern
+            }
+            (And(lhs), And(rhs)) |
+            (Or(lhs), Or(rhs)) => {
+                lhs.len() == rhs.len() &&
+                    lhs.iter().zip(rhs).all(|(l, r)| l.structurally_eq(r))
+            }
+            (Implies(lhs_a, lhs_b), Implies(rhs_a, rhs_b)) |
+            (Iff(lhs_a, lhs_b), Iff(rhs_a, rhs_b)) => {
+                lhs_a.structurally_eq(rhs_a) && lhs_b.structurally_eq(rhs_b)
+            }
+            (TypeCond(lhs_vars, lhs), TypeCond(rhs_vars, rhs)) => {
+                lhs_vars.vars == rhs_vars.vars && lhs.structurally_eq(rhs)
+            }
+            (ForAll(lhs_vars, lhs_triggers, lhs), ForAll(rhs_vars, rhs_triggers, rhs)) |
+            (Exists(lhs_vars, lhs_triggers, lhs), Exists(rhs_vars, rhs_triggers, rhs)) => {
+                lhs_vars.vars == rhs_vars.vars &&
+                    triggers_eq(lhs_triggers, rhs_triggers) &&
+                    lhs.structurally_eq(rhs)
+            }
+            (
+                BoundedForAll(lhs_vars, lhs_lower, lhs_upper, lhs),
+                BoundedForAll(rhs_vars, rhs_lower, rhs_upper, rhs),
+            ) => {
+                lhs_vars.vars == rhs_vars.vars &&
+                    lhs_lower == rhs_lower &&
+                    lhs_upper == rhs_upper &&
+                    lhs.structurally_eq(rhs)
+            }
+            (Let(lhs_vars, lhs_value, lhs), Let(rhs_vars, rhs_value, rhs)) => {
+                lhs_vars.vars == rhs_vars.vars &&
+                    lhs_value == rhs_value &&
+                    lhs.structurally_eq(rhs)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<EID, ET, AT> Assertion<EID, ET, AT> {
+    /// Whether both assertions are equal up to a consistent renaming of the
+    /// variables bound by quantifiers, type conditions and `let`s. The
+    /// variables bound at the same position are compared with `vars_eq`,
+    /// while `exprs_eq` compares expressions knowing the correspondence of the
+    /// bound variables in scope, innermost last. Identifiers of expressions
+    /// and specifications are not compared.
+    pub fn alpha_eq_with(
+        &self,
+        other: &Self,
+        vars_eq: &dyn Fn(&AT, &AT) -> bool,
+        exprs_eq: &dyn Fn(&ET, &ET, &[(&AT, &AT)]) -> bool,
+    ) -> bool {
+        self.alpha_eq_in(other, vars_eq, exprs_eq, &mut vec![])
+    }
+
+    fn alpha_eq_in<'a>(
+        &'a self,
+        other: &'a Self,
+        vars_eq: &dyn Fn(&AT, &AT) -> bool,
+        exprs_eq: &dyn Fn(&ET, &ET, &[(&AT, &AT)]) -> bool,
+        bindings: &mut Vec<(&'a AT, &'a AT)>,
+    ) -> bool {
+        use AssertionKind::*;
+        let triggers_eq = |lhs: &TriggerSet<EID, ET>, rhs: &TriggerSet<EID, ET>, bindings: &[(&AT, &AT)]| {
+            lhs.triggers().len() == rhs.triggers().len() &&
+                lhs.triggers().iter().zip(rhs.triggers()).all(|(l, r)| {
+                    l.terms().len() == r.terms().len() &&
+                        l.terms().iter().zip(r.terms()).all(|(l, r)| exprs_eq(&l.expr, &r.expr, bindings))
+                })
+        };
+        // Checks `check` with the variables of both sides bound, if they correspond.
+        let bound = |lhs: &'a ForAllVars<EID, AT>, rhs: &'a ForAllVars<EID, AT>,
+                         bindings: &mut Vec<(&'a AT, &'a AT)>,
+                         check: &mut dyn FnMut(&mut Vec<(&'a AT, &'a AT)>) -> bool| {
+            if lhs.vars.len() != rhs.vars.len() ||
+                lhs.weight != rhs.weight ||
+                !lhs.vars.iter().zip(&rhs.vars).all(|(l, r)| vars_eq(l, r)) {
+                return false;
+            }
+            let scope = bindings.len();
+            bindings.extend(lhs.vars.iter().zip(&rhs.vars));
+            let result = check(bindings);
+            bindings.truncate(scope);
+            result
+        };
+        match (&*self.kind, &*other.kind) {
+            (Expr(lhs), Expr(rhs)) => exprs_eq(&lhs.expr, &rhs.expr, bindings),
+            (Matches(lhs_scrutinee, lhs_pattern), Matches(rhs_scrutinee, rhs_pattern)) => {
+                exprs_eq(&lhs_scrutinee.expr, &rhs_scrutinee.expr, bindings) &&
+                    exprs_eq(&lhs_pattern.check, &rhs_pattern.check, bindings)
+            }
+            (And(lhs), And(rhs)) |
+            (Or(lhs), Or(rhs)) => {
+                lhs.len() == rhs.len() &&
+                    lhs.iter().zip(rhs).all(|(l, r)| l.alpha_eq_in(r, vars_eq, exprs_eq, bindings))
+            }
+            (Implies(lhs_a, lhs_b), Implies(rhs_a, rhs_b)) |
+            (Iff(lhs_a, lhs_b), Iff(rhs_a, rhs_b)) => {
+                lhs_a.alpha_eq_in(rhs_a, vars_eq, exprs_eq, bindings) &&
+                    lhs_b.alpha_eq_in(rhs_b, vars_eq, exprs_eq, bindings)
+            }
+            (TypeCond(lhs_vars, lhs), TypeCond(rhs_vars, rhs)) => {
+                bound(lhs_vars, rhs_vars, bindings, &mut |bindings| {
+                    lhs.alpha_eq_in(rhs, vars_eq, exprs_eq, bindings)
+                })
+            }
+            (ForAll(lhs_vars, lhs_triggers, lhs), ForAll(rhs_vars, rhs_triggers, rhs)) |
+            (Exists(lhs_vars, lhs_triggers, lhs), Exists(rhs_vars, rhs_triggers, rhs)) => {
+                bound(lhs_vars, rhs_vars, bindings, &mut |bindings| {
+                    triggers_eq(lhs_triggers, rhs_triggers, bindings) &&
+                        lhs.alpha_eq_in(rhs, vars_eq, exprs_eq, bindings)
+                })
+            }
+            (
+                BoundedForAll(lhs_vars, lhs_lower, lhs_upper, lhs),
+                BoundedForAll(rhs_vars, rhs_lower, rhs_upper, rhs),
+            ) => {
+                bound(lhs_vars, rhs_vars, bindings, &mut |bindings| {
+                    exprs_eq(&lhs_lower.expr, &rhs_lower.expr, bindings) &&
+                        exprs_eq(&lhs_upper.expr, &rhs_upper.expr, bindings) &&
+                        lhs.alpha_eq_in(rhs, vars_eq, exprs_eq, bindings)
+                })
+            }
+            (Let(lhs_vars, lhs_value, lhs), Let(rhs_vars, rhs_value, rhs)) => {
+                // The value is outside of the scope of the bound variable
+                exprs_eq(&lhs_value.expr, &rhs_value.expr, bindings) &&
+                    bound(lhs_vars, rhs_vars, bindings, &mut |bindings| {
+                        lhs.alpha_eq_in(rhs, vars_eq, exprs_eq, bindings)
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A change of a single precondition or postcondition, see
+/// `ProcedureSpecification::diff`.
+pub enum AssertionChange<'a, EID, ET, AT> {
+    /// The assertion only exists in the new specification.
+    Added(&'a Assertion<EID, ET, AT>),
+    /// The assertion only exists in the old specification.
+    Removed(&'a Assertion<EID, ET, AT>),
+    /// The old assertion was replaced by the new one.
+    Changed(&'a Assertion<EID, ET, AT>, &'a Assertion<EID, ET, AT>),
+}
+
+#[derive(Debug)]
+/// The differences between two specifications of a procedure.
+pub struct SpecDiff<'a, EID, ET, AT> {
+    pub pres: Vec<AssertionChange<'a, EID, ET, AT>>,
+    pub posts: Vec<AssertionChange<'a, EID, ET, AT>>,
+}
+
+impl<'a, EID, ET, AT> SpecDiff<'a, EID, ET, AT> {
+    /// Whether the preconditions and postconditions are unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.pres.is_empty() && self.posts.is_empty()
+    }
+}
+
+impl<EID: PartialEq, ET: PartialEq, AT: PartialEq> ProcedureSpecification<EID, ET, AT> {
+    /// Compare the preconditions and postconditions of `self` (the old
+    /// specification) with the ones of `other` (the new specification).
+    ///
+    /// The assertions that are structurally equal in both specifications are
+    /// matched first, regardless of their order. The remaining ones are paired
+    /// in order as changed assertions, and the rest is reported as added or
+    /// removed. Pledges are not compared.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> SpecDiff<'a, EID, ET, AT> {
+        SpecDiff {
+            pres: diff_assertions(&self.pres, &other.pres),
+            posts: diff_assertions(&self.posts, &other.posts),
+        }
+    }
+}
+
+fn diff_assertions<'a, EID: PartialEq, ET: PartialEq, AT: PartialEq>(
+    old: &'a [Assertion<EID, ET, AT>],
+    new: &'a [Assertion<EID, ET, AT>],
+) -> Vec<AssertionChange<'a, EID, ET, AT>> {
+    let mut unmatched_new: Vec<_> = new.iter().collect();
+    let mut unmatched_old = vec![];
+    for old_assertion in old {
+        let matching = unmatched_new
+            .iter()
+            .position(|new_assertion| old_assertion.structurally_eq(new_assertion));
+        match matching {
+            Some(index) => {
+                unmatched_new.remove(index);
+            }
+            None => unmatched_old.push(old_assertion),
+        }
+    }
+    let mut changes = vec![];
+    let mut unmatched_new = unmatched_new.into_iter();
+    for old_assertion in unmatched_old {
+        match unmatched_new.next() {
+            Some(new_assertion) => changes.push(AssertionChange::Changed(old_assertion, new_assertion)),
+            None => changes.push(AssertionChange::Removed(old_assertion)),
+        }
+    }
+    changes.extend(unmatched_new.map(AssertionChange::Added));
+    changes
 }
 
 #[derive(Debug, Clone)]
@@ -320,6 +1327,53 @@ impl<EID, ET, AT> SpecificationSet<EID, ET, AT> {
             SpecificationSet::Struct(ref invs) => invs.is_empty(),
         }
     }
+
+    /// Group the assertions of the set by their role. The specifications of
+    /// the closure arguments of a procedure are not included.
+    pub fn partition(&self) -> PartitionedSpecs<EID, ET, AT> {
+        let mut partitioned = PartitionedSpecs {
+            pres: vec![],
+            posts: vec![],
+            invariants: vec![],
+            predicates: vec![],
+            pledges: vec![],
+        };
+        match self {
+            SpecificationSet::Procedure(spec) => {
+                partitioned.pres.extend(&spec.pres);
+                partitioned.posts.extend(&spec.posts);
+                partitioned.pledges.extend(&spec.pledges);
+            }
+            SpecificationSet::Loop(spec) => partitioned.invariants.extend(&spec.invariant),
+            SpecificationSet::Struct(specs) => {
+                for spec in specs {
+                    let group = match spec.typ {
+                        SpecType::Precondition => &mut partitioned.pres,
+                        SpecType::Postcondition => &mut partitioned.posts,
+                        SpecType::Predicate => &mut partitioned.predicates,
+                        SpecType::Invariant | SpecType::Assert => &mut partitioned.invariants,
+                        // Ghost functions have no assertion of their own.
+                        SpecType::Ghost => continue,
+                    };
+                    group.push(&spec.assertion);
+                }
+            }
+        }
+        partitioned
+    }
+}
+
+#[derive(Debug)]
+/// The assertions of a `SpecificationSet` grouped by their role, see
+/// `SpecificationSet::partition`.
+pub struct PartitionedSpecs<'a, EID, ET, AT> {
+    pub pres: Vec<&'a Assertion<EID, ET, AT>>,
+    pub posts: Vec<&'a Assertion<EID, ET, AT>>,
+    /// Loop, struct and program point invariants.
+    pub invariants: Vec<&'a Assertion<EID, ET, AT>>,
+    /// The bodies of predicates, which are neither required nor ensured.
+    pub predicates: Vec<&'a Assertion<EID, ET, AT>>,
+    pub pledges: Vec<&'a Pledge<EID, ET, AT>>,
 }
 
 impl<EID: Clone + Debug, ET: Clone + Debug, AT: Clone + Debug> SpecificationSet<EID, ET, AT> {
@@ -366,4 +1420,770 @@ impl<EID: Clone + Debug, ET: Clone + Debug, AT: Clone + Debug> SpecificationSet<
         SpecificationSet::Procedure(ProcedureSpecification { pres, posts, pledges })
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestAssertion = Assertion<ExpressionId, (), ()>;
+
+    fn expr(id_generator: &mut ExpressionIdGenerator) -> Expression<ExpressionId, ()> {
+        Expression {
+            spec_id: SpecificationId::dummy(),
+            id: id_generator.generate(),
+            expr: (),
+            contains_old: false,
+        }
+    }
+
+    fn forall(
+        id_generator: &mut ExpressionIdGenerator,
+        arity: usize,
+        triggers: Vec<Trigger<ExpressionId, ()>>,
+        body: TestAssertion,
+    ) -> TestAssertion {
+        Assertion {
+            kind: box AssertionKind::ForAll(
+                ForAllVars {
+                    spec_id: SpecificationId::dummy(),
+                    id: id_generator.generate(),
+                    vars: vec![(); arity],
+                    enumerated: vec![],
+                    weight: None,
+                    trigger_strategies: vec![],
+                },
+                TriggerSet::new(triggers),
+                body,
+            ),
+        }
+    }
+
+    fn spec(
+        id_generator: &mut ExpressionIdGenerator,
+        pres: usize,
+        posts: usize,
+    ) -> ProcedureSpecification<ExpressionId, (), ()> {
+        let mut assertion = || Assertion { kind: box AssertionKind::Expr(expr(id_generator)) };
+        ProcedureSpecification::new(
+            (0..pres).map(|_| assertion()).collect(),
+            (0..posts).map(|_| assertion()).collect(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_seeded_spec_id_generator() {
+        let mut random_generator = SpecificationIdGenerator::new();
+        let existing_ids: Vec<_> = (0..10).map(|_| random_generator.generate()).collect();
+        let max_id = *existing_ids.iter().max().unwrap();
+
+        let mut generator = SpecificationIdGenerator::seeded_from(&existing_ids);
+        let first = generator.generate();
+        let second = generator.generate();
+        assert!(first > max_id);
+        assert!(second > first);
+
+        let mut generator = SpecificationIdGenerator::seeded_from(&[]);
+        assert_ne!(generator.generate(), SpecificationId::dummy());
+    }
+
+    #[test]
+    fn test_expressions() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let guard = expr(&mut id_generator);
+        let conclusion = expr(&mut id_generator);
+        let term = expr(&mut id_generator);
+        let body = Assertion {
+            kind: box AssertionKind::Implies(
+                Assertion { kind: box AssertionKind::Expr(guard.clone()) },
+                Assertion { kind: box AssertionKind::Expr(conclusion.clone()) },
+            ),
+        };
+        let triggers = vec![Trigger::new(vec![term.clone()])];
+        let assertion = forall(&mut id_generator, 1, triggers, body);
+        let ids: Vec<_> = assertion.expressions().map(|e| e.id).collect();
+        assert_eq!(ids, vec![guard.id, conclusion.id, term.id]);
+    }
+
+    #[test]
+    fn test_contains_old() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let lhs: TestAssertion = Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let rhs: TestAssertion = Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let implication = Assertion { kind: box AssertionKind::Implies(lhs.clone(), rhs) };
+        assert!(!implication.contains_old());
+
+        let old_rhs = Assertion {
+            kind: box AssertionKind::Expr(Expression {
+                contains_old: true,
+                ..expr(&mut id_generator)
+            }),
+        };
+        let implication = Assertion { kind: box AssertionKind::Implies(lhs, old_rhs) };
+        assert!(implication.contains_old());
+        assert!(forall(&mut id_generator, 1, vec![], implication).contains_old());
+    }
+
+    #[test]
+    fn test_as_guarded_forall() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let guard = expr(&mut id_generator);
+        let conclusion = expr(&mut id_generator);
+        let body = Assertion {
+            kind: box AssertionKind::Implies(
+                Assertion { kind: box AssertionKind::Expr(guard.clone()) },
+                Assertion { kind: box AssertionKind::Expr(conclusion.clone()) },
+            ),
+        };
+        let guarded = forall(&mut id_generator, 2, vec![], body);
+        match guarded.as_guarded_forall() {
+            Some((vars, lhs, rhs)) => {
+                assert_eq!(vars.arity(), 2);
+                assert!(matches!(*lhs.kind, AssertionKind::Expr(ref e) if e.id == guard.id));
+                assert!(matches!(*rhs.kind, AssertionKind::Expr(ref e) if e.id == conclusion.id));
+            }
+            None => panic!("expected a guarded quantifier"),
+        }
+
+        let unguarded = forall(
+            &mut id_generator,
+            1,
+            vec![],
+            Assertion { kind: box AssertionKind::Expr(conclusion) },
+        );
+        assert!(unguarded.as_guarded_forall().is_none());
+    }
+
+    #[test]
+    fn test_typed_vars() {
+        let vars: ForAllVars<ExpressionId, (u32, &str)> = ForAllVars {
+            spec_id: SpecificationId::dummy(),
+            id: ExpressionIdGenerator::new().generate(),
+            vars: vec![(1, "usize"), (2, "bool")],
+            enumerated: vec![],
+            weight: None,
+            trigger_strategies: vec![],
+        };
+        assert_eq!(vars.arity(), 2);
+        assert_eq!(vars.typed_vars().collect::<Vec<_>>(), vec![(1, "usize"), (2, "bool")]);
+    }
+
+    #[test]
+    fn test_format_witnesses() {
+        let vars: ForAllVars<ExpressionId, (u32, &str)> = ForAllVars {
+            spec_id: SpecificationId::dummy(),
+            id: ExpressionIdGenerator::new().generate(),
+            vars: vec![(1, "usize"), (2, "bool")],
+            enumerated: vec![],
+            weight: None,
+            trigger_strategies: vec![],
+        };
+        let witnesses = vec!["42".to_string(), "true".to_string()];
+        assert_eq!(
+            vars.format_witnesses(&[Some("i"), None], &witnesses),
+            vec!["i: usize = 42", "2: bool = true"],
+        );
+    }
+
+    #[test]
+    fn test_remap_locals() {
+        let vars: ForAllVars<ExpressionId, (u32, &str)> = ForAllVars {
+            spec_id: SpecificationId::dummy(),
+            id: ExpressionIdGenerator::new().generate(),
+            vars: vec![(1, "usize"), (2, "bool")],
+            enumerated: vec![],
+            weight: None,
+            trigger_strategies: vec![],
+        };
+        let mapping: HashMap<_, _> = vec![(1, 11), (3, 13)].into_iter().collect();
+        assert_eq!(vars.remap_locals(&mapping).vars, vec![(11, "usize"), (2, "bool")]);
+    }
+
+    #[test]
+    fn test_simplify() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let true_expr = expr(&mut id_generator);
+        let false_expr = expr(&mut id_generator);
+        let literal = |e: &Expression<ExpressionId, ()>| {
+            if e.id == true_expr.id {
+                Some(true)
+            } else if e.id == false_expr.id {
+                Some(false)
+            } else {
+                None
+            }
+        };
+        let assertion = |e: &Expression<ExpressionId, ()>| -> TestAssertion {
+            Assertion { kind: box AssertionKind::Expr(e.clone()) }
+        };
+        let a = expr(&mut id_generator);
+
+        let conjunction = Assertion {
+            kind: box AssertionKind::And(vec![assertion(&true_expr), assertion(&a)]),
+        };
+        match *conjunction.simplify_with(&literal).kind {
+            AssertionKind::Expr(ref e) => assert_eq!(e.id, a.id),
+            ref kind => panic!("unexpected {:?}", kind),
+        }
+
+        let disjunction = Assertion {
+            kind: box AssertionKind::Or(vec![assertion(&false_expr), assertion(&a)]),
+        };
+        match *disjunction.simplify_with(&literal).kind {
+            AssertionKind::Expr(ref e) => assert_eq!(e.id, a.id),
+            ref kind => panic!("unexpected {:?}", kind),
+        }
+
+        let vacuous = Assertion {
+            kind: box AssertionKind::Implies(assertion(&false_expr), assertion(&a)),
+        };
+        match *vacuous.simplify_with(&literal).kind {
+            AssertionKind::And(ref conjuncts) => assert!(conjuncts.is_empty()),
+            ref kind => panic!("unexpected {:?}", kind),
+        }
+
+        let triggers = vec![Trigger::new(vec![a.clone()])];
+        let body = Assertion {
+            kind: box AssertionKind::Implies(assertion(&true_expr), assertion(&a)),
+        };
+        match *forall(&mut id_generator, 1, triggers, body).simplify_with(&literal).kind {
+            AssertionKind::ForAll(_, ref triggers, ref body) => {
+                assert_eq!(triggers.triggers().len(), 1);
+                assert!(matches!(*body.kind, AssertionKind::Expr(_)));
+            }
+            ref kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_refine_with() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let trait_spec = spec(&mut id_generator, 1, 1);
+        let impl_spec = spec(&mut id_generator, 1, 2);
+
+        let refined = trait_spec.refine_with(&impl_spec).unwrap();
+        assert_eq!(refined.pres.len(), 1);
+        assert_eq!(refined.posts.len(), 3);
+
+        let refined = trait_spec.refine_with(&spec(&mut id_generator, 0, 0)).unwrap();
+        assert_eq!(refined.pres.len(), 1);
+        assert_eq!(refined.posts.len(), 1);
+
+        let unconstrained_trait_spec = spec(&mut id_generator, 0, 1);
+        assert_eq!(
+            unconstrained_trait_spec.refine_with(&impl_spec).unwrap_err(),
+            RefinementError::PreconditionStrengthened,
+        );
+    }
+
+    /// A quantifier over the variables `vars`, which are numbered so that
+    /// shadowing can be detected.
+    fn forall_over(
+        id_generator: &mut ExpressionIdGenerator,
+        vars: Vec<usize>,
+        enumerated: Vec<usize>,
+        weight: Option<u32>,
+        triggers: Vec<Trigger<ExpressionId, ()>>,
+        body: Assertion<ExpressionId, (), usize>,
+    ) -> Assertion<ExpressionId, (), usize> {
+        Assertion {
+            kind: box AssertionKind::ForAll(
+                ForAllVars {
+                    spec_id: SpecificationId::dummy(),
+                    id: id_generator.generate(),
+                    vars,
+                    enumerated,
+                    weight,
+                    trigger_strategies: vec![],
+                },
+                TriggerSet::new(triggers),
+                body,
+            ),
+        }
+    }
+
+    #[test]
+    fn test_flatten_nested_foralls() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let term = expr(&mut id_generator);
+        let body = Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let inner_triggers = vec![Trigger::new(vec![term.clone()])];
+        let inner = forall_over(&mut id_generator, vec![1, 2], vec![1], Some(3), inner_triggers, body);
+        let outer_triggers = vec![Trigger::new(vec![term])];
+        let outer = forall_over(&mut id_generator, vec![0], vec![0], None, outer_triggers, inner);
+
+        match *outer.flatten_nested_foralls().kind {
+            AssertionKind::ForAll(vars, triggers, body) => {
+                assert_eq!(vars.vars, vec![0, 1, 2]);
+                assert_eq!(vars.enumerated, vec![0, 2]);
+                assert_eq!(vars.weight, Some(3));
+                assert_eq!(triggers.triggers().len(), 1);
+                match *body.kind {
+                    AssertionKind::Expr(_) => {}
+                    _ => panic!("expected the quantifiers to be merged"),
+                }
+            }
+            _ => panic!("expected a quantifier"),
+        }
+    }
+
+    #[test]
+    fn test_flatten_nested_foralls_keeps_conflicts() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut body = || Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let (shadowing_body, weighted_body) = (body(), body());
+
+        // forall x :: forall x, y :: p(x, y)
+        let inner = forall_over(&mut id_generator, vec![0, 1], vec![], None, vec![], shadowing_body);
+        let shadowing = forall_over(&mut id_generator, vec![0], vec![], None, vec![], inner);
+        match *shadowing.flatten_nested_foralls().kind {
+            AssertionKind::ForAll(vars, _, body) => {
+                assert_eq!(vars.arity(), 1);
+                assert!(matches!(*body.kind, AssertionKind::ForAll(..)));
+            }
+            _ => panic!("expected a quantifier"),
+        }
+
+        let inner = forall_over(&mut id_generator, vec![1], vec![], Some(2), vec![], weighted_body);
+        let weighted = forall_over(&mut id_generator, vec![0], vec![], Some(5), vec![], inner);
+        match *weighted.flatten_nested_foralls().kind {
+            AssertionKind::ForAll(vars, _, body) => {
+                assert_eq!(vars.weight, Some(5));
+                assert!(matches!(*body.kind, AssertionKind::ForAll(..)));
+            }
+            _ => panic!("expected a quantifier"),
+        }
+    }
+
+    #[test]
+    fn test_normalize_implications() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut assertion = || Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let (a, b, c, d) = (assertion(), assertion(), assertion(), assertion());
+        // (a && b) ==> (c ==> d)
+        let lhs = Assertion { kind: box AssertionKind::And(vec![a, b]) };
+        let rhs = Assertion { kind: box AssertionKind::Implies(c, d) };
+        let nested: TestAssertion = Assertion { kind: box AssertionKind::Implies(lhs, rhs) };
+
+        match *nested.normalize_implications_with(&|_| None).kind {
+            AssertionKind::Implies(lhs, rhs) => {
+                match *lhs.kind {
+                    AssertionKind::And(conjuncts) => assert_eq!(conjuncts.len(), 3),
+                    _ => panic!("expected the lhs to be a conjunction"),
+                }
+                match *rhs.kind {
+                    AssertionKind::Expr(_) => {}
+                    _ => panic!("expected the implications to be collapsed"),
+                }
+            }
+            _ => panic!("expected an implication"),
+        }
+    }
+
+    #[test]
+    fn test_normalize_implications_in_forall() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let term = expr(&mut id_generator);
+        let mut assertion = || Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let (a, b, c) = (assertion(), assertion(), assertion());
+        // forall x :: a ==> (b ==> c), with a trigger
+        let body = Assertion {
+            kind: box AssertionKind::Implies(a, Assertion { kind: box AssertionKind::Implies(b, c) }),
+        };
+        let quantifier = forall(&mut id_generator, 1, vec![Trigger::new(vec![term])], body);
+
+        match *quantifier.normalize_implications_with(&|_| None).kind {
+            AssertionKind::ForAll(_, triggers, body) => {
+                assert_eq!(triggers.triggers().len(), 1);
+                match *body.kind {
+                    AssertionKind::Implies(lhs, _) => match *lhs.kind {
+                        AssertionKind::And(conjuncts) => assert_eq!(conjuncts.len(), 2),
+                        _ => panic!("expected the lhs to be a conjunction"),
+                    },
+                    _ => panic!("expected the body to be an implication"),
+                }
+            }
+            _ => panic!("expected a quantifier"),
+        }
+    }
+
+    #[test]
+    fn test_normalize_negated_implications() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut assertion = |expr: syn::Expr| -> Assertion<ExpressionId, syn::Expr, ()> {
+            Assertion {
+                kind: box AssertionKind::Expr(Expression {
+                    spec_id: SpecificationId::dummy(),
+                    id: id_generator.generate(),
+                    expr,
+                    contains_old: false,
+                }),
+            }
+        };
+        let negated_operand = |expr: &syn::Expr| match expr {
+            syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Not(_), expr, .. }) => Some((**expr).clone()),
+            _ => None,
+        };
+        let a: syn::Expr = syn::parse_quote! { a };
+
+        // !a ==> (b || c) becomes a || b || c
+        let disjunction = Assertion {
+            kind: box AssertionKind::Or(vec![
+                assertion(syn::parse_quote! { b }),
+                assertion(syn::parse_quote! { c }),
+            ]),
+        };
+        let implication = Assertion {
+            kind: box AssertionKind::Implies(assertion(syn::parse_quote! { !a }), disjunction),
+        };
+        match *implication.normalize_implications_with(&negated_operand).kind {
+            AssertionKind::Or(disjuncts) => {
+                assert_eq!(disjuncts.len(), 3);
+                match *disjuncts[0].kind {
+                    AssertionKind::Expr(ref expr) => assert_eq!(expr.expr, a),
+                    _ => panic!("expected the operand of the negation"),
+                }
+            }
+            _ => panic!("expected a disjunction"),
+        }
+
+        // a ==> b is kept
+        let implication = Assertion {
+            kind: box AssertionKind::Implies(assertion(a.clone()), assertion(syn::parse_quote! { !b })),
+        };
+        match *implication.normalize_implications_with(&negated_operand).kind {
+            AssertionKind::Implies(..) => {}
+            _ => panic!("expected an implication"),
+        }
+
+        // !a ==> (b ==> c) becomes (!a && b) ==> c, whose lhs is not negated
+        let nested = Assertion {
+            kind: box AssertionKind::Implies(
+                assertion(syn::parse_quote! { !a }),
+                Assertion {
+                    kind: box AssertionKind::Implies(
+                        assertion(syn::parse_quote! { b }),
+                        assertion(syn::parse_quote! { c }),
+                    ),
+                },
+            ),
+        };
+        match *nested.normalize_implications_with(&negated_operand).kind {
+            AssertionKind::Implies(lhs, _) => match *lhs.kind {
+                AssertionKind::And(conjuncts) => assert_eq!(conjuncts.len(), 2),
+                _ => panic!("expected the lhs to be a conjunction"),
+            },
+            _ => panic!("expected an implication"),
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut assertion = || Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let (a, b, c) = (assertion(), assertion(), assertion());
+        // a && (b ==> c)
+        let implication = Assertion { kind: box AssertionKind::Implies(b, c) };
+        let conjunction: TestAssertion = Assertion { kind: box AssertionKind::And(vec![a, implication]) };
+        assert_eq!(conjunction.depth(), 3);
+
+        let quantifier = forall(&mut id_generator, 1, vec![], conjunction);
+        assert_eq!(quantifier.depth(), 4);
+    }
+
+    #[test]
+    fn test_quantifiers() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let body = Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let inner = forall(&mut id_generator, 1, vec![], body);
+        let outer = forall(&mut id_generator, 2, vec![], inner);
+        let outer_ids: Vec<_> = outer.quantifiers().iter().map(|vars| vars.id).collect();
+        assert_eq!(outer_ids.len(), 2);
+
+        let mut procedure = spec(&mut id_generator, 1, 0);
+        procedure.posts.push(outer);
+        let pledge_body = Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        procedure.pledges.push(Pledge {
+            reference: None,
+            lhs: None,
+            rhs: forall(&mut id_generator, 1, vec![], pledge_body),
+        });
+        let quantifiers = procedure.quantifiers();
+        assert_eq!(quantifiers.len(), 3);
+        assert_eq!(quantifiers[0].id, outer_ids[0]);
+        assert_eq!(quantifiers[1].id, outer_ids[1]);
+        assert_eq!(quantifiers[2].vars.len(), 1);
+    }
+
+    #[test]
+    fn test_is_trivial() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        assert!(ProcedureSpecification::<ExpressionId, (), ()>::empty().is_trivial());
+        assert!(!spec(&mut id_generator, 1, 0).is_trivial());
+        assert!(!spec(&mut id_generator, 0, 1).is_trivial());
+
+        let mut with_pledge = spec(&mut id_generator, 0, 0);
+        with_pledge.pledges.push(Pledge {
+            reference: None,
+            lhs: None,
+            rhs: Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) },
+        });
+        assert!(with_pledge.is_empty());
+        assert!(!with_pledge.is_trivial());
+        assert!(with_pledge.has_pledges());
+        assert_eq!(with_pledge.pledges().len(), 1);
+        assert!(!spec(&mut id_generator, 1, 1).has_pledges());
+    }
+
+    #[test]
+    fn test_partition() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut procedure = spec(&mut id_generator, 2, 1);
+        procedure.pledges.push(Pledge {
+            reference: None,
+            lhs: None,
+            rhs: Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) },
+        });
+        let procedure = SpecificationSet::Procedure(procedure);
+        let partitioned = procedure.partition();
+        assert_eq!(partitioned.pres.len(), 2);
+        assert_eq!(partitioned.posts.len(), 1);
+        assert_eq!(partitioned.pledges.len(), 1);
+        assert!(partitioned.invariants.is_empty());
+
+        let mut assertion = || -> TestAssertion {
+            Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) }
+        };
+        let invariants = SpecificationSet::Loop(LoopSpecification::new(vec![assertion(), assertion()]));
+        let partitioned = invariants.partition();
+        assert_eq!(partitioned.invariants.len(), 2);
+        assert!(partitioned.pres.is_empty() && partitioned.posts.is_empty());
+
+        let structure = SpecificationSet::Struct(vec![
+            Specification { typ: SpecType::Invariant, assertion: assertion() },
+            Specification { typ: SpecType::Precondition, assertion: assertion() },
+            Specification { typ: SpecType::Predicate, assertion: assertion() },
+        ]);
+        let partitioned = structure.partition();
+        assert_eq!(partitioned.invariants.len(), 1);
+        assert_eq!(partitioned.pres.len(), 1);
+        assert_eq!(partitioned.predicates.len(), 1);
+        assert!(partitioned.posts.is_empty());
+        if let SpecificationSet::Struct(ref specs) = structure {
+            assert!(std::ptr::eq(partitioned.invariants[0], &specs[0].assertion));
+        }
+    }
+
+    #[test]
+    fn test_pledge_kind() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut pledge: Pledge<ExpressionId, (), ()> = Pledge {
+            reference: None,
+            lhs: None,
+            rhs: Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) },
+        };
+        assert!(matches!(pledge.kind(), PledgeKind::Unconditional));
+        pledge.lhs = Some(Assertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) });
+        match pledge.kind() {
+            PledgeKind::Conditional(lhs) => assert!(std::ptr::eq(lhs, pledge.lhs.as_ref().unwrap())),
+            PledgeKind::Unconditional => panic!("the pledge has a lhs"),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let old_spec = spec(&mut id_generator, 2, 1);
+        let mut new_spec = old_spec.clone();
+        assert!(old_spec.diff(&new_spec).is_empty());
+
+        // Reorder the preconditions, replace the postcondition and add one.
+        new_spec.pres.reverse();
+        let added = spec(&mut id_generator, 0, 2).posts;
+        new_spec.posts = added.clone();
+        let diff = old_spec.diff(&new_spec);
+        assert!(diff.pres.is_empty());
+        assert_eq!(diff.posts.len(), 2);
+        match diff.posts[0] {
+            AssertionChange::Changed(old, new) => {
+                assert!(old.structurally_eq(&old_spec.posts[0]));
+                assert!(new.structurally_eq(&added[0]));
+            }
+            _ => panic!("expected a changed postcondition"),
+        }
+        match diff.posts[1] {
+            AssertionChange::Added(new) => assert!(new.structurally_eq(&added[1])),
+            _ => panic!("expected an added postcondition"),
+        }
+
+        let diff = new_spec.diff(&spec(&mut id_generator, 0, 0));
+        assert_eq!(diff.pres.len(), 2);
+        assert!(diff.pres.iter().all(|change| matches!(change, AssertionChange::Removed(_))));
+    }
+
+    #[test]
+    fn test_trigger_set_dedup() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let a = expr(&mut id_generator);
+        let b = expr(&mut id_generator);
+        let trigger_set = TriggerSet::from_terms(vec![
+            vec![a.clone(), b.clone(), a.clone()],
+            vec![a.clone(), b.clone()],
+            vec![b.clone()],
+        ]);
+
+        let triggers = trigger_set.dedup().0;
+        assert_eq!(triggers.len(), 2);
+        assert_eq!(triggers[0].terms(), &vec![a, b.clone()]);
+        assert!(triggers[1].same_terms(&Trigger::single(b)));
+    }
+
+    #[test]
+    fn test_trigger_terms_mut() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let a = expr(&mut id_generator);
+        let b = expr(&mut id_generator);
+        let old_a = Expression { contains_old: true, ..a.clone() };
+        let mut trigger = Trigger::new(vec![old_a, b.clone()]);
+
+        for term in trigger.terms_mut() {
+            term.contains_old = false;
+        }
+        trigger.terms_mut().push(a.clone());
+        assert_eq!(trigger.terms(), &vec![a.clone(), b, a]);
+    }
+
+    #[test]
+    fn test_alpha_eq() {
+        use crate::specifications::untyped;
+        use quote::quote;
+
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut parse = |tokens| untyped::Assertion::parse(tokens, spec_id, &mut id_generator).unwrap();
+
+        let assertion = parse(quote! { forall(|i: usize, j: usize| i < j ==> a[i] <= a[j]) });
+        let renamed = parse(quote! { forall(|k: usize, l: usize| k < l ==> a[k] <= a[l]) });
+        let swapped = parse(quote! { forall(|k: usize, l: usize| l < k ==> a[l] <= a[k]) });
+        let inconsistent = parse(quote! { forall(|k: usize, l: usize| k < l ==> a[k] <= a[k]) });
+        let retyped = parse(quote! { forall(|k: usize, l: u32| k < l ==> a[k] <= a[l]) });
+        assert!(assertion.alpha_eq(&renamed));
+        assert!(!assertion.alpha_eq(&swapped));
+        assert!(!assertion.alpha_eq(&inconsistent));
+        assert!(!assertion.alpha_eq(&retyped));
+
+        // Free variables are not renamed, nor captured by a bound variable
+        let free = parse(quote! { forall(|i: usize| i < n) });
+        let captured = parse(quote! { forall(|n: usize| n < n) });
+        assert!(!free.alpha_eq(&parse(quote! { forall(|i: usize| i < m) })));
+        assert!(!free.alpha_eq(&captured));
+
+        let nested = parse(quote! { forall(|i: usize| forall(|i: usize| i < n)) });
+        let shadowing = parse(quote! { forall(|i: usize| forall(|j: usize| j < n)) });
+        assert!(nested.alpha_eq(&shadowing));
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut leaf = || TestAssertion { kind: box AssertionKind::Expr(expr(&mut id_generator)) };
+        let conjunction = TestAssertion { kind: box AssertionKind::And(vec![leaf(), leaf()]) };
+        let implication = TestAssertion { kind: box AssertionKind::Implies(leaf(), leaf()) };
+        assert_eq!(leaf().estimated_cost(), 1);
+        assert_eq!(conjunction.estimated_cost(), 2);
+        assert!(implication.estimated_cost() > conjunction.estimated_cost());
+
+        let nested_body = TestAssertion { kind: box AssertionKind::Implies(leaf(), leaf()) };
+        let flat = forall(&mut id_generator, 1, vec![], implication);
+        let nested = forall(
+            &mut id_generator,
+            1,
+            vec![],
+            forall(&mut id_generator, 1, vec![], nested_body),
+        );
+        assert!(flat.estimated_cost() > conjunction.estimated_cost());
+        assert!(nested.estimated_cost() >= 10 * flat.estimated_cost());
+    }
+
+    #[test]
+    fn test_infer_triggers() {
+        use crate::specifications::triggers::TriggerStrategy;
+        use crate::specifications::untyped;
+        use quote::{quote, ToTokens};
+
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut infer = |tokens, strategy| {
+            let assertion = untyped::Assertion::parse(tokens, spec_id, &mut id_generator).unwrap();
+            match *assertion.infer_missing_triggers(&mut id_generator).kind {
+                AssertionKind::ForAll(vars, triggers, _) |
+                AssertionKind::Exists(vars, triggers, _) => triggers.triggers().iter()
+                    .enumerate()
+                    .filter(|(index, _)| vars.uses_trigger(*index, strategy))
+                    .map(|(_, trigger)| trigger.terms().iter()
+                        .map(|term| term.expr.to_token_stream().to_string())
+                        .collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+                _ => unreachable!(),
+            }
+        };
+
+        let body = quote! { forall(|i: usize| i < n ==> f(g(i)) && h(i + 1)) };
+        assert!(infer(body.clone(), TriggerStrategy::None).is_empty());
+        assert_eq!(infer(body.clone(), TriggerStrategy::Shallow), vec![vec!["f (g (i))"]]);
+        assert_eq!(
+            infer(body, TriggerStrategy::Aggressive),
+            vec![vec!["f (g (i))"], vec!["g (i)"]],
+        );
+
+        // The variables are covered by several terms together
+        let body = quote! { forall(|i: usize, j: usize| a.lookup(i) <= a.lookup(j)) };
+        assert_eq!(
+            infer(body, TriggerStrategy::Shallow),
+            vec![vec!["a . lookup (i)", "a . lookup (j)"]],
+        );
+
+        // Written triggers are kept
+        let body = quote! { forall(|i: usize| f(i) && g(i), triggers = [(g(i),)]) };
+        assert_eq!(infer(body.clone(), TriggerStrategy::Shallow), vec![vec!["g (i)"]]);
+        assert_eq!(infer(body, TriggerStrategy::None), vec![vec!["g (i)"]]);
+
+        // Existential quantifiers get triggers too
+        let body = quote! { exists(|i: usize| f(g(i))) };
+        assert!(infer(body.clone(), TriggerStrategy::None).is_empty());
+        assert_eq!(infer(body, TriggerStrategy::Shallow), vec![vec!["f (g (i))"]]);
+    }
+
+    #[test]
+    fn test_inferred_triggers_provenance() {
+        use crate::specifications::triggers::TriggerStrategy;
+        use crate::specifications::untyped;
+        use quote::quote;
+
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let mut strategies = |tokens| {
+            let assertion = untyped::Assertion::parse(tokens, spec_id, &mut id_generator).unwrap();
+            match *assertion.infer_missing_triggers(&mut id_generator).kind {
+                AssertionKind::ForAll(vars, _, _) => vars.trigger_strategies,
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(
+            strategies(quote! { forall(|i: usize| f(i)) }),
+            vec![vec![TriggerStrategy::Shallow, TriggerStrategy::Aggressive]],
+        );
+        // Only the aggressive strategy uses nested terms
+        assert_eq!(
+            strategies(quote! { forall(|i: usize| f(g(i))) }),
+            vec![
+                vec![TriggerStrategy::Shallow, TriggerStrategy::Aggressive],
+                vec![TriggerStrategy::Aggressive],
+            ],
+        );
+        // Nothing can be inferred without a function call
+        assert!(strategies(quote! { forall(|i: usize| i + 1 > i) }).is_empty());
+        assert!(strategies(quote! { forall(|i: usize| f(i), triggers = [(f(i),)]) }).is_empty());
+    }
+}
@@ -1,6 +1,30 @@
 use super::untyped;
 use serde::{Deserialize, Serialize};
 use super::common;
+use super::triggers::TriggerStrategy;
+use std::io::Read;
+
+/// The version of the JSON format of the specifications. It must be increased
+/// whenever the shape of one of the types of this module changes, so that
+/// specifications emitted by another version of Prusti are rejected instead of
+/// being misinterpreted.
+///
+/// * 0: specifications without a version.
+/// * 1: the `version` field.
+/// * 2: `let` bindings, `matches!` assertions, quantifier weights, top-level
+///   disjunctions and the trigger strategies of quantifiers.
+pub const SPECIFICATION_VERSION: u32 = 2;
+
+/// The top-level JSON form of a specification.
+#[derive(Serialize, Deserialize)]
+pub struct Specification {
+    /// The `SPECIFICATION_VERSION` of the Prusti that emitted the
+    /// specification. Specifications without a version are emitted by the
+    /// versions of Prusti that precede the field and are read as version 0.
+    #[serde(default)]
+    pub version: u32,
+    pub assertion: Assertion,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Assertion {
@@ -10,9 +34,15 @@ pub struct Assertion {
 #[derive(Serialize, Deserialize)]
 pub enum AssertionKind {
     Expr(Expression),
+    Matches(Expression, Pattern),
     And(Vec<Assertion>),
+    Or(Vec<Assertion>),
     Implies(Assertion, Assertion),
+    Iff(Assertion, Assertion),
+    TypeCond(ForAllVars, Assertion),
     ForAll(ForAllVars, Assertion, TriggerSet),
+    Exists(ForAllVars, Assertion, TriggerSet),
+    Let(ForAllVars, Expression, Assertion),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,6 +51,17 @@ pub struct Expression {
     pub spec_id: untyped::SpecificationId,
     /// Identifier of the expression within the specification.
     pub expr_id: untyped::ExpressionId,
+    /// Whether the expression contains an `old(..)` call.
+    pub contains_old: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Pattern {
+    /// Identifier of the specification to which this pattern belongs.
+    pub spec_id: untyped::SpecificationId,
+    /// Identifier of the `matches!(scrutinee, pattern)` check within the
+    /// specification.
+    pub expr_id: untyped::ExpressionId,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -28,6 +69,13 @@ pub struct ForAllVars {
     pub spec_id: untyped::SpecificationId,
     pub expr_id: untyped::ExpressionId,
     pub count: usize,
+    /// The instantiation weight of the quantifier, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    /// The strategies that infer each trigger of the quantifier, if its
+    /// triggers were inferred.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trigger_strategies: Vec<Vec<TriggerStrategy>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,11 +84,28 @@ pub struct TriggerSet(pub Vec<Trigger>);
 #[derive(Serialize, Deserialize)]
 pub struct Trigger(pub Vec<Expression>);
 
+#[derive(Serialize, Deserialize)]
+pub struct Pledge {
+    pub reference: Option<Expression>,
+    pub lhs: Option<Assertion>,
+    pub rhs: Assertion,
+}
+
 impl untyped::Expression {
     fn to_structure(&self) -> Expression {
         Expression {
             spec_id: self.spec_id.clone(),
             expr_id: self.id.clone(),
+            contains_old: self.contains_old,
+        }
+    }
+}
+
+impl untyped::Pattern {
+    fn to_structure(&self) -> Pattern {
+        Pattern {
+            spec_id: self.spec_id.clone(),
+            expr_id: self.id.clone(),
         }
     }
 }
@@ -51,6 +116,8 @@ impl common::ForAllVars<untyped::ExpressionId, untyped::Arg> {
             spec_id: self.spec_id.clone(),
             count: self.vars.len(),
             expr_id: self.id.clone(),
+            weight: self.weight,
+            trigger_strategies: self.trigger_strategies.clone(),
         }
     }
 }
@@ -81,6 +148,10 @@ impl untyped::AssertionKind {
         use super::common::AssertionKind::*;
         match self {
             Expr(expr) => AssertionKind::Expr(expr.to_structure()),
+            Matches(scrutinee, pattern) => AssertionKind::Matches(
+                scrutinee.to_structure(),
+                pattern.to_structure(),
+            ),
             And(assertions) => {
                 AssertionKind::And(
                     assertions.into_iter()
@@ -88,18 +159,57 @@ impl untyped::AssertionKind {
                               .collect()
                 )
             }
+            Or(assertions) => {
+                AssertionKind::Or(
+                    assertions.into_iter()
+                              .map(|assertion| Assertion { kind: box assertion.kind.to_structure() })
+                              .collect()
+                )
+            }
             Implies(lhs, rhs) => AssertionKind::Implies(
                 lhs.to_structure(),
                 rhs.to_structure()
             ),
+            Iff(lhs, rhs) => AssertionKind::Iff(
+                lhs.to_structure(),
+                rhs.to_structure()
+            ),
             ForAll(vars, triggers, body) => AssertionKind::ForAll(
                 vars.to_structure(),
                 body.to_structure(),
                 triggers.to_structure(),
             ),
-            x => {
-                unimplemented!("{:?}", x);
-            }
+            Exists(vars, triggers, body) => AssertionKind::Exists(
+                vars.to_structure(),
+                body.to_structure(),
+                triggers.to_structure(),
+            ),
+            TypeCond(vars, body) => AssertionKind::TypeCond(
+                vars.to_structure(),
+                body.to_structure(),
+            ),
+            // The bounds are only recognized when lowering to typed
+            // assertions, so the JSON form keeps the plain implication.
+            BoundedForAll(vars, lower, upper, body) => AssertionKind::ForAll(
+                vars.to_structure(),
+                Assertion {
+                    kind: box AssertionKind::Implies(
+                        Assertion {
+                            kind: box AssertionKind::And(vec![
+                                Assertion { kind: box AssertionKind::Expr(lower.to_structure()) },
+                                Assertion { kind: box AssertionKind::Expr(upper.to_structure()) },
+                            ]),
+                        },
+                        body.to_structure(),
+                    ),
+                },
+                TriggerSet(vec![]),
+            ),
+            Let(vars, value, body) => AssertionKind::Let(
+                vars.to_structure(),
+                value.to_structure(),
+                body.to_structure(),
+            ),
         }
     }
 }
@@ -112,12 +222,270 @@ impl untyped::Assertion {
     }
 }
 
+impl untyped::Pledge {
+    fn to_structure(&self) -> Pledge {
+        Pledge {
+            reference: self.reference.as_ref().map(|reference| reference.to_structure()),
+            lhs: self.lhs.as_ref().map(|lhs| lhs.to_structure()),
+            rhs: self.rhs.to_structure(),
+        }
+    }
+}
+
 pub fn to_json_string(assertion: &untyped::Assertion) -> String {
-    serde_json::to_string(&assertion.to_structure()).unwrap()
+    let specification = Specification {
+        version: SPECIFICATION_VERSION,
+        assertion: assertion.to_structure(),
+    };
+    serde_json::to_string(&specification).unwrap()
+}
+
+pub fn pledge_to_json_string(pledge: &untyped::Pledge) -> String {
+    serde_json::to_string(&pledge.to_structure()).unwrap()
+}
+
+impl Specification {
+    /// Parses a specification emitted by `to_json_string`. A bare assertion,
+    /// as emitted before the format was versioned, is read as version 0.
+    pub fn from_json_string(json: &str) -> Self {
+        serde_json::from_str(&json).unwrap_or_else(|_| Specification {
+            version: 0,
+            assertion: Assertion::from_json_string(json),
+        })
+    }
+
+    /// Reads a sequence of (whitespace-separated) specifications emitted by
+    /// `to_json_string` from `reader`, one at a time, so that each one can be
+    /// processed and dropped before the next one is parsed. As in
+    /// `from_json_string`, bare assertions are read as version 0.
+    pub fn read_all<R: Read>(
+        reader: R,
+    ) -> impl Iterator<Item = serde_json::Result<Specification>> {
+        serde_json::Deserializer::from_reader(reader)
+            .into_iter::<serde_json::Value>()
+            .map(|value| {
+                let value = value?;
+                if value.get("assertion").is_some() {
+                    serde_json::from_value(value)
+                } else {
+                    Ok(Specification {
+                        version: 0,
+                        assertion: serde_json::from_value(value)?,
+                    })
+                }
+            })
+    }
 }
 
 impl Assertion {
     pub fn from_json_string(json: &str) -> Self {
         serde_json::from_str(&json).unwrap()
     }
+
+    /// The nesting depth of the assertion, counted in the same way as
+    /// `common::Assertion::depth`.
+    pub fn depth(&self) -> usize {
+        let nested_depth = match *self.kind {
+            AssertionKind::Expr(_) |
+            AssertionKind::Matches(..) => 0,
+            AssertionKind::And(ref conjuncts) |
+            AssertionKind::Or(ref conjuncts) => {
+                conjuncts.iter().map(|conjunct| conjunct.depth()).max().unwrap_or(0)
+            }
+            AssertionKind::Implies(ref lhs, ref rhs) |
+            AssertionKind::Iff(ref lhs, ref rhs) => lhs.depth().max(rhs.depth()),
+            AssertionKind::TypeCond(_, ref body) |
+            AssertionKind::ForAll(_, ref body, _) |
+            AssertionKind::Exists(_, ref body, _) |
+            AssertionKind::Let(_, _, ref body) => body.depth(),
+        };
+        nested_depth + 1
+    }
+}
+
+impl Pledge {
+    pub fn from_json_string(json: &str) -> Self {
+        serde_json::from_str(&json).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::specifications::common::{ExpressionIdGenerator, SpecificationIdGenerator};
+
+    #[test]
+    fn test_type_cond_round_trip() {
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let vars_id = id_generator.generate();
+        let body_id = id_generator.generate();
+        let assertion = untyped::Assertion {
+            kind: box common::AssertionKind::TypeCond(
+                common::ForAllVars {
+                    spec_id,
+                    id: vars_id,
+                    vars: vec![syn::parse_quote! { a: T }, syn::parse_quote! { b: i32 }],
+                    enumerated: vec![],
+                    weight: None,
+                    trigger_strategies: vec![],
+                },
+                untyped::Assertion {
+                    kind: box common::AssertionKind::Expr(untyped::Expression {
+                        spec_id,
+                        id: body_id,
+                        expr: syn::parse_quote! { true },
+                        contains_old: false,
+                    }),
+                },
+            ),
+        };
+
+        let reloaded = Specification::from_json_string(&to_json_string(&assertion));
+        assert_eq!(reloaded.version, SPECIFICATION_VERSION);
+        match *reloaded.assertion.kind {
+            AssertionKind::TypeCond(vars, body) => {
+                assert_eq!(vars.spec_id, spec_id);
+                assert_eq!(vars.expr_id, vars_id);
+                assert_eq!(vars.count, 2);
+                match *body.kind {
+                    AssertionKind::Expr(expr) => {
+                        assert_eq!(expr.spec_id, spec_id);
+                        assert_eq!(expr.expr_id, body_id);
+                    }
+                    _ => panic!("expected the body to be an expression"),
+                }
+            }
+            _ => panic!("expected a type condition"),
+        }
+    }
+
+    #[test]
+    fn test_forall_vars_round_trip() {
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let body = untyped::Assertion {
+            kind: box common::AssertionKind::Expr(untyped::Expression {
+                spec_id,
+                id: id_generator.generate(),
+                expr: syn::parse_quote! { true },
+                contains_old: false,
+            }),
+        };
+        let assertion = untyped::Assertion {
+            kind: box common::AssertionKind::ForAll(
+                common::ForAllVars {
+                    spec_id,
+                    id: id_generator.generate(),
+                    vars: vec![syn::parse_quote! { i: u32 }],
+                    enumerated: vec![],
+                    weight: Some(2),
+                    trigger_strategies: vec![vec![TriggerStrategy::Aggressive]],
+                },
+                common::TriggerSet(vec![]),
+                body,
+            ),
+        };
+
+        let reloaded = Specification::from_json_string(&to_json_string(&assertion));
+        match *reloaded.assertion.kind {
+            AssertionKind::ForAll(vars, _, _) => {
+                assert_eq!(vars.weight, Some(2));
+                assert_eq!(vars.trigger_strategies, vec![vec![TriggerStrategy::Aggressive]]);
+            }
+            _ => panic!("expected a quantifier"),
+        }
+    }
+
+    #[test]
+    fn test_pledge_round_trip() {
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let reference_id = id_generator.generate();
+        let rhs_id = id_generator.generate();
+        let pledge = untyped::Pledge {
+            reference: Some(untyped::Expression {
+                spec_id,
+                id: reference_id,
+                expr: syn::parse_quote! { result },
+                contains_old: false,
+            }),
+            lhs: None,
+            rhs: untyped::Assertion {
+                kind: box common::AssertionKind::Expr(untyped::Expression {
+                    spec_id,
+                    id: rhs_id,
+                    expr: syn::parse_quote! { true },
+                    contains_old: false,
+                }),
+            },
+        };
+
+        let reloaded = Pledge::from_json_string(&pledge_to_json_string(&pledge));
+        let reference = reloaded.reference.expect("expected a reference");
+        assert_eq!(reference.spec_id, spec_id);
+        assert_eq!(reference.expr_id, reference_id);
+        assert!(reloaded.lhs.is_none());
+        match *reloaded.rhs.kind {
+            AssertionKind::Expr(expr) => assert_eq!(expr.expr_id, rhs_id),
+            _ => panic!("expected the rhs to be an expression"),
+        }
+    }
+
+    #[test]
+    fn test_unversioned_specification() {
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let expr_id = ExpressionIdGenerator::new().generate();
+        let assertion = untyped::Assertion {
+            kind: box common::AssertionKind::Expr(untyped::Expression {
+                spec_id,
+                id: expr_id,
+                expr: syn::parse_quote! { true },
+                contains_old: false,
+            }),
+        };
+
+        let json = serde_json::to_string(&assertion.to_structure()).unwrap();
+        let reloaded = Specification::from_json_string(&json);
+        assert_eq!(reloaded.version, 0);
+        match *reloaded.assertion.kind {
+            AssertionKind::Expr(expr) => assert_eq!(expr.expr_id, expr_id),
+            _ => panic!("expected an expression"),
+        }
+
+        let streamed: Vec<_> = Specification::read_all(json.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(streamed.len(), 1);
+        assert_eq!(streamed[0].version, 0);
+    }
+
+    #[test]
+    fn test_read_all() {
+        let spec_id = SpecificationIdGenerator::new().generate();
+        let mut id_generator = ExpressionIdGenerator::new();
+        let expr_ids = vec![id_generator.generate(), id_generator.generate()];
+        let json: Vec<_> = expr_ids.iter().map(|&expr_id| to_json_string(&untyped::Assertion {
+            kind: box common::AssertionKind::Expr(untyped::Expression {
+                spec_id,
+                id: expr_id,
+                expr: syn::parse_quote! { true },
+                contains_old: false,
+            }),
+        })).collect();
+
+        let reloaded: Vec<_> = Specification::read_all(json.join("\n").as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(reloaded.len(), 2);
+        for (specification, &expr_id) in reloaded.iter().zip(&expr_ids) {
+            match *specification.assertion.kind {
+                AssertionKind::Expr(ref expr) => assert_eq!(expr.expr_id, expr_id),
+                _ => panic!("expected an expression"),
+            }
+        }
+
+        let mut truncated = Specification::read_all(&json[0].as_bytes()[..json[0].len() - 1]);
+        assert!(truncated.next().unwrap().is_err());
+    }
 }
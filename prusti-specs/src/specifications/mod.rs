@@ -1,26 +1,49 @@
-/// Each atomic Prusti assertion (`A`) is a Rust expression (`E`)
-/// or a `forall` expression. Atomic Prusti assertions can be joined together
-/// using the following two operators, forming Prusti assertions:
+/// Each atomic Prusti assertion (`A`) is a Rust expression (`E`),
+/// a `forall` expression, an `exists` expression, a `let` binding,
+/// a `matches!` pattern or a `type_cond` expression. Atomic Prusti
+/// assertions can be joined together using the following operators,
+/// forming Prusti assertions:
 /// - `A && A` (conjunction)
+/// - `A || A` (disjunction)
 /// - `A ==> A` (implication)
+/// - `A <==> A` (bi-implication)
 ///
-/// `==>` has weaker binding than `&&` and is right-associative.
+/// `==>` and `<==>` have weaker binding than `&&` and `||` and are
+/// right-associative.
 ///
-/// Parentheses can be used as usual, i.e. `(A ==> A) && A`
-/// is a Prusti assertion.
+/// Parentheses can be used as usual, i.e. `(A ==> A) && A` or
+/// `(A ==> A) || A` are Prusti assertions.
 ///
 /// `forall` expression has the following syntax
 /// (`A` is an arbitrary Prusti assertion):
 /// `forall(|NAME1: TYPE1, NAME2: TYPE2, ...| A)`
 /// `forall(|NAME1: TYPE1, NAME2: TYPE2, ...| A, triggers=[(E, ...), ...])`
+/// `forall(|NAME1: TYPE1, ...| A, triggers=[(E, ...), ...], weight=N)`
+/// `forall(|NAME1: TYPE1, ...| A, weight=N)`
 ///
-/// Prusti assertions can only be joined together by `&&` and `==>`, for example
-/// the following is not allowed, since `(E ==> E)` is a Prusti assertion:
-/// `(E ==> E) || E`
+/// The weight `N` is an integer literal that is passed to the SMT solver as
+/// the instantiation weight of the quantifier.
 ///
-/// However, this is fine, as `&&` is also a Rust operator, and this whole thing
-/// is interpreted as a Rust expression:
-/// `(E && E) || E`
+/// Each variable can be restricted to a range with a lower and an upper
+/// bound, so that the quantifier only ranges over the values in the range:
+/// `forall(|NAME1: TYPE1 in E..E, NAME2: TYPE2 in E..=E, ...| A)`
+///
+/// `exists` expression has the same syntax as `forall`:
+/// `exists(|NAME1: TYPE1, NAME2: TYPE2, ...| A)`
+/// `exists(|NAME1: TYPE1, NAME2: TYPE2, ...| A, triggers=[(E, ...), ...])`
+///
+/// `let` binds a variable to the value of a Rust expression; the body extends
+/// to the end of the enclosing assertion, as for the rhs of an implication:
+/// `let NAME: TYPE = E in A`
+///
+/// `matches!(E, PATTERN)` without an `if` guard holds if the value of the
+/// expression matches the pattern, and `type_cond(TYPE1 == TYPE2, A)` holds if
+/// the two types are different or if `A` holds.
+///
+/// A chained comparison `E < E <= E` abbreviates the conjunction of the
+/// comparisons `E < E && E <= E`; any of `<`, `<=`, `>`, `>=`, `==` and `!=`
+/// can be chained. A parenthesized comparison, as in `(E < E) == E`, is a
+/// Rust expression.
 ///
 /// Having `&&` and `||` in the same subexpression is not allowed to prevent
 /// ambiguities. Those are all illegal:
@@ -41,5 +64,6 @@ pub mod common;
 pub mod json;
 pub mod untyped;
 pub mod preparser;
+pub mod triggers;
 
 pub use common::SpecType;
@@ -3,10 +3,12 @@
 /// Prusti assertion.
 
 use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use quote::quote_spanned;
 use std::collections::VecDeque;
 use std::mem;
 use syn::parse::{ParseStream, Parse};
 use syn::{self, Token, Error};
+use syn::visit::Visit;
 
 use super::common;
 use crate::specifications::common::{ForAllVars, TriggerSet, Trigger};
@@ -16,6 +18,36 @@ pub type AssertionWithoutId = common::Assertion<(), syn::Expr, Arg>;
 pub type PledgeWithoutId = common::Pledge<(), syn::Expr, Arg>;
 pub type ExpressionWithoutId = common::Expression<(), syn::Expr>;
 
+impl ExpressionWithoutId {
+    /// Wrap a parsed Rust expression that is not yet assigned an id.
+    pub(crate) fn new(expr: syn::Expr) -> Self {
+        let mut finder = OldCallFinder { found: false };
+        finder.visit_expr(&expr);
+        Self {
+            spec_id: common::SpecificationId::dummy(),
+            id: (),
+            expr,
+            contains_old: finder.found,
+        }
+    }
+}
+
+/// Detects calls to `old(..)` in a Rust expression.
+struct OldCallFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for OldCallFinder {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(syn::ExprPath { path, .. }) = &*call.func {
+            if path.segments.last().map_or(false, |segment| segment.ident == "old") {
+                self.found = true;
+            }
+        }
+        syn::visit::visit_expr_call(self, call);
+    }
+}
+
 /// A helper to operate the stream of tokens.
 #[derive(Debug, Clone)]
 struct ParserStream {
@@ -61,8 +93,8 @@ impl ParserStream {
                 contains_or = true;
                 or_span = Some(stream.tokens.front().span());
             }
-            // implies met - reset subexpression
-            else if stream.peek_operator("==>") {
+            // implies or iff met - reset subexpression
+            else if stream.peek_operator("==>") || stream.peek_operator("<==>") {
                 contains_and = false;
                 contains_or = false;
             }
@@ -112,6 +144,14 @@ impl ParserStream {
         }
         false
     }
+    /// Check if the input starts with the keyword. Does not set the span.
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        if let Some(TokenTree::Ident(ident)) = self.tokens.front() {
+            ident.to_string() == keyword
+        } else {
+            false
+        }
+    }
     /// Check if the input starts with the operator. Does not set the span.
     fn peek_operator(&self, operator: &str) -> bool {
         for (i, c) in operator.char_indices() {
@@ -136,7 +176,8 @@ impl ParserStream {
     }
     /// Check whether the input starts with an operator. Does not set the span.
     fn peek_any_operator(&self) -> bool {
-        self.peek_operator("==>") || self.peek_operator("&&")
+        self.peek_operator("==>") || self.peek_operator("<==>") || self.peek_operator("&&") ||
+            self.peek_operator("||")
     }
     /// Check if the input starts with the operator and if yes, consume it
     /// and set the span to it.
@@ -250,22 +291,181 @@ impl Parse for Arg {
     }
 }
 
+/// The representation of an argument to `forall` that is optionally restricted
+/// to a range (for example `a: i32` or `a: i32 in 0..n`)
+#[derive(Debug)]
+struct ForAllArg {
+    arg: Arg,
+    range: Option<syn::ExprRange>,
+}
+
+impl Parse for ForAllArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let arg = input.parse()?;
+        let range = if input.peek(Token![in]) {
+            input.parse::<Token![in]>()?;
+            match input.parse()? {
+                syn::Expr::Range(range) if range.from.is_some() && range.to.is_some() => {
+                    Some(range)
+                }
+                expr => return Err(syn::Error::new(
+                    expr.span(),
+                    "expected a range with a lower and an upper bound, e.g. `0..n`",
+                )),
+            }
+        } else {
+            None
+        };
+        Ok(Self {
+            arg,
+            range
+        })
+    }
+}
+
 /// The representation of all arguments to `forall`
 /// (for example `a: i32, b: i32, c: i32`)
 #[derive(Debug)]
 struct ForAllArgs {
-    args: syn::punctuated::Punctuated<Arg, Token![,]>
+    args: syn::punctuated::Punctuated<ForAllArg, Token![,]>
 }
 
 impl Parse for ForAllArgs {
     fn parse(input: ParseStream) -> syn::Result<Self>{
-        let parsed: syn::punctuated::Punctuated<Arg, Token![,]> = input.parse_terminated(Arg::parse)?;
+        let parsed: syn::punctuated::Punctuated<ForAllArg, Token![,]> = input.parse_terminated(ForAllArg::parse)?;
         Ok(Self{
             args: parsed
         })
     }
 }
 
+/// The representation of the arguments to `type_cond`
+/// (for example `T == i32, body`)
+struct TypeCondArgs {
+    lhs: syn::Type,
+    rhs: syn::Type,
+    body: TokenStream,
+}
+
+impl Parse for TypeCondArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lhs = input.parse()?;
+        input.parse::<Token![==]>()?;
+        let rhs = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let body = input.parse()?;
+        Ok(Self {
+            lhs,
+            rhs,
+            body
+        })
+    }
+}
+
+/// The representation of the arguments to `matches!` that are supported as a
+/// Prusti assertion (for example `self.state, State::Open`), i.e. without an
+/// `if` guard.
+struct MatchesArgs {
+    scrutinee: syn::Expr,
+}
+
+impl Parse for MatchesArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let scrutinee = input.parse()?;
+        input.parse::<Token![,]>()?;
+        input.parse::<syn::Pat>()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self {
+            scrutinee,
+        })
+    }
+}
+
+impl ForAllArg {
+    /// The bounds `lo <= a` and `a < hi` (or `a <= hi` for an inclusive range)
+    /// of an argument `a: T in lo..hi`.
+    fn range_bounds(&self) -> Vec<ExpressionWithoutId> {
+        let range = match &self.range {
+            Some(range) => range,
+            None => return vec![],
+        };
+        let name = &self.arg.name;
+        let lower = range.from.as_ref().unwrap();
+        let upper = range.to.as_ref().unwrap();
+        let span = range.span();
+        let upper_op = match range.limits {
+            syn::RangeLimits::HalfOpen(_) => quote_spanned! { span => < },
+            syn::RangeLimits::Closed(_) => quote_spanned! { span => <= },
+        };
+        let lower_bound = quote_spanned! { span => (#lower) <= #name };
+        let upper_bound = quote_spanned! { span => #name #upper_op (#upper) };
+        vec![
+            ExpressionWithoutId::new(syn::parse2(lower_bound).unwrap()),
+            ExpressionWithoutId::new(syn::parse2(upper_bound).unwrap()),
+        ]
+    }
+}
+
+/// The conjunction of the bounds of the variables of a quantifier.
+fn bounds_conjunction(bounds: Vec<ExpressionWithoutId>) -> AssertionWithoutId {
+    AssertionWithoutId {
+        kind: box common::AssertionKind::And(
+            bounds
+                .into_iter()
+                .map(|bound| AssertionWithoutId { kind: box common::AssertionKind::Expr(bound) })
+                .collect()
+        )
+    }
+}
+
+/// The scrutinee of `expr` if it is a call `matches!(scrutinee, pattern)`
+/// without an `if` guard, which is then parsed as a pattern match.
+fn matches_scrutinee(expr: &syn::Expr) -> Option<syn::Expr> {
+    match expr {
+        syn::Expr::Macro(syn::ExprMacro { mac, .. }) if mac.path.is_ident("matches") => {
+            syn::parse2::<MatchesArgs>(mac.tokens.clone()).ok().map(|args| args.scrutinee)
+        }
+        _ => None,
+    }
+}
+
+fn is_comparison(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::Lt(_) | syn::BinOp::Le(_) | syn::BinOp::Gt(_) |
+        syn::BinOp::Ge(_) | syn::BinOp::Eq(_) | syn::BinOp::Ne(_)
+    )
+}
+
+/// Splits a chained comparison `a < b <= c`, which is not valid Rust, into the
+/// comparisons `a < b` and `b <= c` that it abbreviates. Each comparison keeps
+/// the spans of its operands and operator. Any other expression, including a
+/// parenthesized comparison `(a < b) == c`, is returned unchanged.
+fn split_chained_comparison(expr: syn::Expr) -> Vec<syn::Expr> {
+    match expr {
+        syn::Expr::Binary(syn::ExprBinary { attrs, left, op, right }) if is_comparison(&op) => {
+            let middle = match &*left {
+                syn::Expr::Binary(inner) if is_comparison(&inner.op) => Some(inner.right.clone()),
+                _ => None,
+            };
+            match middle {
+                Some(middle) => {
+                    let mut comparisons = split_chained_comparison(*left);
+                    comparisons.push(syn::Expr::Binary(syn::ExprBinary {
+                        attrs,
+                        left: middle,
+                        op,
+                        right,
+                    }));
+                    comparisons
+                }
+                None => vec![syn::Expr::Binary(syn::ExprBinary { attrs, left, op, right })],
+            }
+        }
+        expr => vec![expr],
+    }
+}
+
 /// The structure to parse Prusti assertions.
 ///
 /// Check common::AssertionKind to see all types of Prusti assertions.
@@ -278,8 +478,12 @@ impl Parse for ForAllArgs {
 pub struct Parser {
     /// The helper to manipulate input.
     input: ParserStream,
-    /// Members of the conjunction.
+    /// Members of the conjunction, or of the disjunction if `disjunction` is
+    /// set.
     conjuncts: Vec<AssertionWithoutId>,
+    /// A flag to denote that the members parsed so far are separated by `||`
+    /// instead of `&&`. Both cannot be mixed without parentheses.
+    disjunction: bool,
     /// Currently being parsed Rust expression.
     expr: Vec<TokenTree>,
     /// A flag to denote whether the previous expression is already resolved
@@ -302,6 +506,7 @@ impl Parser {
         Self {
             input,
             conjuncts: Vec::new(),
+            disjunction: false,
             expr: Vec::new(),
             previous_expression_resolved: false,
             expected_operator: false,
@@ -313,6 +518,7 @@ impl Parser {
         Self {
             input,
             conjuncts: Vec::new(),
+            disjunction: false,
             expr: Vec::new(),
             previous_expression_resolved: false,
             expected_operator: false,
@@ -321,7 +527,15 @@ impl Parser {
         }
     }
     fn resolve_and(&mut self) -> syn::Result<()>{
-        // handles the case when there is no lhs of the && operator
+        self.resolve_associative_operator()
+    }
+    fn resolve_or(&mut self) -> syn::Result<()>{
+        self.disjunction = true;
+        self.resolve_associative_operator()
+    }
+    /// Resolve the lhs of a just-consumed `&&` or `||` operator.
+    fn resolve_associative_operator(&mut self) -> syn::Result<()>{
+        // handles the case when there is no lhs of the operator
         if !self.expected_operator {
             return Err(self.error_expected_assertion());
         }
@@ -340,7 +554,7 @@ impl Parser {
             }
         }
 
-        // handles the case when there is no rhs of the && operator
+        // handles the case when there is no rhs of the operator
         if self.input.is_empty() {
             return Err(self.error_expected_assertion());
         }
@@ -350,7 +564,24 @@ impl Parser {
         Ok(())
     }
     fn resolve_implies(&mut self) -> syn::Result<AssertionWithoutId>{
-        // handles the case when there is no lhs of the ==> operator
+        let (lhs, rhs) = self.resolve_right_associative_operator()?;
+        Ok(AssertionWithoutId{
+            kind: box common::AssertionKind::Implies(lhs, rhs)
+        })
+    }
+    fn resolve_iff(&mut self) -> syn::Result<AssertionWithoutId>{
+        let (lhs, rhs) = self.resolve_right_associative_operator()?;
+        Ok(AssertionWithoutId{
+            kind: box common::AssertionKind::Iff(lhs, rhs)
+        })
+    }
+    /// Resolve the operands of a just-consumed `==>` or `<==>` operator: the
+    /// lhs is made of the conjuncts parsed so far and the rhs is the rest of
+    /// the input.
+    fn resolve_right_associative_operator(
+        &mut self
+    ) -> syn::Result<(AssertionWithoutId, AssertionWithoutId)> {
+        // handles the case when there is no lhs of the operator
         if !self.expected_operator {
             return Err(self.error_expected_assertion());
         }
@@ -366,7 +597,7 @@ impl Parser {
             }
         }
 
-        // handles the case when there is no rhs of the ==> operator
+        // handles the case when there is no rhs of the operator
         if self.input.is_empty() {
             return Err(self.error_expected_assertion());
         }
@@ -387,11 +618,120 @@ impl Parser {
             return Err(err);
         }
 
-        return Ok(AssertionWithoutId{
-            kind: box common::AssertionKind::Implies(lhs.unwrap(), rhs.unwrap())
-        });
+        return Ok((lhs.unwrap(), rhs.unwrap()));
     }
     fn resolve_forall(&mut self) -> syn::Result<()> {
+        let (vars, trigger_set, bounds, mut body) = self.parse_quantifier()?;
+        // `forall(|i: T in lo..hi| body)` is sugar for
+        // `forall(|i: T| (lo <= i && i < hi) ==> body)`.
+        if !bounds.is_empty() {
+            body = AssertionWithoutId {
+                kind: box common::AssertionKind::Implies(bounds_conjunction(bounds), body)
+            };
+        }
+        self.push_quantifier(common::AssertionKind::ForAll(vars, trigger_set, body));
+        Ok(())
+    }
+    fn resolve_exists(&mut self) -> syn::Result<()> {
+        let (vars, trigger_set, bounds, mut body) = self.parse_quantifier()?;
+        // `exists(|i: T in lo..hi| body)` is sugar for
+        // `exists(|i: T| lo <= i && i < hi && body)`.
+        if !bounds.is_empty() {
+            body = AssertionWithoutId {
+                kind: box common::AssertionKind::And(vec![bounds_conjunction(bounds), body])
+            };
+        }
+        self.push_quantifier(common::AssertionKind::Exists(vars, trigger_set, body));
+        Ok(())
+    }
+    /// Parse `type_cond(T == U, body)`, which requires `body` to hold only
+    /// if the types `T` and `U` are the same.
+    fn resolve_type_cond(&mut self) -> syn::Result<()> {
+        if self.expected_operator {
+            return Err(self.error_expected_operator());
+        }
+        let group = match self.input.check_and_consume_parenthesized_block() {
+            Some(group) => group,
+            None => return Err(self.error_expected_parenthesis()),
+        };
+        let args: TypeCondArgs = syn::parse2(group.stream())?;
+        if args.body.is_empty() {
+            return Err(self.error_expected_assertion());
+        }
+        let body = Parser::from_token_stream(args.body).extract_assertion()?;
+        // The types are bound to variables of a type-checking closure, in
+        // the same way as the variables of a quantifier.
+        let vars = vec![
+            Arg { name: syn::Ident::new("_prusti_type_cond_lhs", args.lhs.span()), typ: args.lhs },
+            Arg { name: syn::Ident::new("_prusti_type_cond_rhs", args.rhs.span()), typ: args.rhs },
+        ];
+        let vars = ForAllVars {
+            spec_id: common::SpecificationId::dummy(),
+            id: (),
+            vars,
+            enumerated: vec![],
+            weight: None,
+            trigger_strategies: vec![],
+        };
+        self.push_quantifier(common::AssertionKind::TypeCond(vars, body));
+        Ok(())
+    }
+    /// Parse `let x: T = value in body`, which binds `x` to the value of a
+    /// Rust expression in `body`. As for the rhs of an implication, the body
+    /// extends to the end of the input.
+    fn resolve_let(&mut self) -> syn::Result<()> {
+        if self.expected_operator {
+            return Err(self.error_expected_operator());
+        }
+        let arg: Arg = syn::parse2(self.input.create_stream_until("="))?;
+        if !self.input.check_and_consume_operator("=") {
+            return Err(self.error_expected_equals());
+        }
+        let mut value = TokenStream::new();
+        while !self.input.is_empty() && !self.input.peek_keyword("in") {
+            value.extend(self.input.pop());
+        }
+        if !self.input.check_and_consume_keyword("in") {
+            return Err(self.error_expected_in());
+        }
+        let value = self.parse_rust_expression(value)?;
+        if self.input.is_empty() {
+            return Err(self.error_expected_assertion());
+        }
+        let mut parser = Parser::from_parser_stream(
+            mem::replace(&mut self.input, ParserStream::empty())
+        );
+        let body = parser.extract_assertion()?;
+        // The variable is bound by a type-checking closure, in the same way as
+        // the variables of a quantifier.
+        let vars = ForAllVars {
+            spec_id: common::SpecificationId::dummy(),
+            id: (),
+            vars: vec![arg],
+            enumerated: vec![],
+            weight: None,
+            trigger_strategies: vec![],
+        };
+        self.push_quantifier(common::AssertionKind::Let(vars, ExpressionWithoutId::new(value), body));
+        Ok(())
+    }
+    /// Push a parsed quantifier as a conjunct; it has to be followed by an
+    /// operator (or nothing).
+    fn push_quantifier(&mut self, kind: common::AssertionKind<(), syn::Expr, Arg>) {
+        self.conjuncts.push(AssertionWithoutId { kind: box kind });
+        self.previous_expression_resolved = true;
+        self.expected_only_operator = true;
+        self.expected_operator = true;
+    }
+    /// Parse the parenthesized part of a quantifier (after the `forall` or
+    /// `exists` keyword): the bound variables, the bounds of the variables that
+    /// are restricted to a range, the body, and the triggers.
+    fn parse_quantifier(&mut self) -> syn::Result<(
+        ForAllVars<(), Arg>,
+        TriggerSet<(), syn::Expr>,
+        Vec<ExpressionWithoutId>,
+        AssertionWithoutId,
+    )> {
         if self.expected_operator {
             return Err(self.error_expected_operator());
         }
@@ -415,10 +755,12 @@ impl Parser {
                 return Err(self.error_expected_or());
             }
             let mut vars = vec![];
+            let mut bounds = vec![];
             for var in all_args.args {
+                bounds.extend(var.range_bounds());
                 vars.push(Arg {
-                    typ: var.typ,
-                    name: var.name
+                    typ: var.arg.typ,
+                    name: var.arg.name
                 })
             }
 
@@ -429,17 +771,22 @@ impl Parser {
 
             // create triggers in case they are not present
             let mut trigger_set = TriggerSet(vec![]);
+            let mut weight = None;
 
             // parse triggers (check if they are present at all)
             if stream.peek_operator(",") {
                 stream.check_and_consume_operator(",");
-                if !stream.check_and_consume_keyword("triggers") {
+                if stream.check_and_consume_keyword("weight") {
+                    weight = Some(self.parse_quantifier_weight(&mut stream)?);
+                } else if !stream.check_and_consume_keyword("triggers") {
                     return Err(self.error_expected_triggers());
                 }
+            }
+            if weight.is_none() && !stream.is_empty() {
                 if !stream.check_and_consume_operator("=") {
                     return Err(self.error_expected_equals());
                 }
-                let token_stream = stream.create_stream();
+                let token_stream = stream.create_stream_until(",");
 
                 let maybe_arr: Result<syn::ExprArray, Error> = syn::parse2(token_stream);
                 if let Err(err) = maybe_arr {
@@ -455,10 +802,7 @@ impl Parser {
                         vec_of_triggers.push(
                             Trigger(tuple.elems
                                 .into_iter()
-                                .map(|x| ExpressionWithoutId {
-                                    id: (),
-                                    spec_id: common::SpecificationId::dummy(),
-                                    expr: x })
+                                .map(ExpressionWithoutId::new)
                                 .collect()
                             )
                         );
@@ -470,30 +814,48 @@ impl Parser {
                 }
 
                 trigger_set = TriggerSet(vec_of_triggers);
+
+                // parse the weight, which follows the triggers
+                if stream.peek_operator(",") {
+                    stream.check_and_consume_operator(",");
+                    if !stream.check_and_consume_keyword("weight") {
+                        return Err(self.error_expected_weight());
+                    }
+                    weight = Some(self.parse_quantifier_weight(&mut stream)?);
+                }
             }
 
-            let conjunct = AssertionWithoutId {
-                kind: box common::AssertionKind::ForAll(
-                    ForAllVars {
-                        spec_id: common::SpecificationId::dummy(),
-                        id: (),
-                        vars
-                    },
-                    trigger_set,
-                    body,
-                )
+            let vars = ForAllVars {
+                spec_id: common::SpecificationId::dummy(),
+                id: (),
+                vars,
+                enumerated: vec![],
+                weight,
+                trigger_strategies: vec![],
             };
-
-            self.conjuncts.push(conjunct);
-            self.previous_expression_resolved = true;
-            self.expected_only_operator = true;
-            self.expected_operator = true;
-            return Ok(());
+            return Ok((vars, trigger_set, bounds, body));
         }
         else {
             return Err(self.error_expected_parenthesis());
         }
     }
+    /// Parse the `= N` part of the instantiation weight of a quantifier.
+    fn parse_quantifier_weight(&mut self, stream: &mut ParserStream) -> syn::Result<u32> {
+        if !stream.check_and_consume_operator("=") {
+            return Err(self.error_expected_equals());
+        }
+        let maybe_lit: Result<syn::LitInt, Error> = syn::parse2(stream.create_stream());
+        match maybe_lit {
+            Ok(lit) => {
+                self.input.span = lit.span();
+                lit.base10_parse().map_err(|_| self.error_expected_weight())
+            }
+            Err(err) => {
+                self.input.span = err.span();
+                Err(self.error_expected_weight())
+            }
+        }
+    }
     fn resolve_parenthesized_block(&mut self, group: Group) -> syn::Result<()>{
         // handling a parenthesized block
         if self.expected_only_operator {
@@ -555,6 +917,14 @@ impl Parser {
                     return Err(err);
                 }
             }
+            else if self.input.check_and_consume_operator("||") {
+                if let Err(err) = self.resolve_or() {
+                    return Err(err);
+                }
+            }
+            else if self.input.check_and_consume_operator("<==>") {
+                return self.resolve_iff();
+            }
             else if self.input.check_and_consume_operator("==>") {
                 return self.resolve_implies();
             }
@@ -563,6 +933,22 @@ impl Parser {
                     return Err(err);
                 }
             }
+            else if self.input.check_and_consume_keyword("exists") {
+                if let Err(err) = self.resolve_exists() {
+                    return Err(err);
+                }
+            }
+            else if self.input.check_and_consume_keyword("type_cond") {
+                if let Err(err) = self.resolve_type_cond() {
+                    return Err(err);
+                }
+            }
+            // `let` is part of the Rust expression in e.g. `if let`
+            else if self.expr.is_empty() && self.input.check_and_consume_keyword("let") {
+                if let Err(err) = self.resolve_let() {
+                    return Err(err);
+                }
+            }
             else if let Some(group) = self.input.check_and_consume_parenthesized_block() {
                 if let Err(err) = self.resolve_parenthesized_block(group) {
                     return Err(err);
@@ -585,7 +971,7 @@ impl Parser {
             }
         }
 
-        // build a conjunction off of the assertions parsed
+        // build a conjunction (or a disjunction) off of the assertions parsed
         self.conjuncts_to_assertion()
     }
     fn parse_rust_expression(&mut self, tokens: TokenStream) -> syn::Result<syn::Expr> {
@@ -619,13 +1005,7 @@ impl Parser {
         if self.input.contains_operator("=>") {
             let ref_stream = self.input.create_stream_until("=>");
             let parsed_expr = self.parse_rust_expression(ref_stream)?;
-
-            let expr = ExpressionWithoutId {
-                spec_id: common::SpecificationId::dummy(),
-                id: (),
-                expr: parsed_expr,
-            };
-            reference = Some(expr);
+            reference = Some(ExpressionWithoutId::new(parsed_expr));
             self.input.check_and_consume_operator("=>");
         }
 
@@ -637,7 +1017,8 @@ impl Parser {
             rhs: assertion
         })
     }
-    /// Convert all conjuncts into And assertion.
+    /// Convert all conjuncts into And assertion, or into Or assertion if they
+    /// are disjuncts.
     fn conjuncts_to_assertion(&mut self) -> syn::Result<AssertionWithoutId> {
         let mut conjuncts = mem::replace(&mut self.conjuncts, Vec::new());
 
@@ -646,6 +1027,11 @@ impl Parser {
         if conjuncts.len() == 1 {
             Ok(conjuncts.pop().unwrap())
         }
+        else if self.disjunction {
+            Ok(AssertionWithoutId{
+                kind: box common::AssertionKind::Or(conjuncts)
+            })
+        }
         else{
             Ok(AssertionWithoutId{
                 kind: box common::AssertionKind::And(conjuncts)
@@ -661,14 +1047,39 @@ impl Parser {
 
         let parsed_expr = self.parse_rust_expression(token_stream.clone())?;
 
-        let expr = ExpressionWithoutId {
-            spec_id: common::SpecificationId::dummy(),
-            id: (),
-            expr: parsed_expr,
-        };
-        self.conjuncts.push(AssertionWithoutId{
-            kind: box common::AssertionKind::Expr(expr)
-        });
+        if let Some(scrutinee) = matches_scrutinee(&parsed_expr) {
+            let kind = common::AssertionKind::Matches(
+                ExpressionWithoutId::new(scrutinee),
+                common::Pattern {
+                    spec_id: common::SpecificationId::dummy(),
+                    id: (),
+                    check: parsed_expr,
+                },
+            );
+            self.conjuncts.push(AssertionWithoutId{
+                kind: box kind
+            });
+            return Ok(());
+        }
+
+        // `a < b <= c` holds iff both `a < b` and `b <= c` hold
+        let comparisons = split_chained_comparison(parsed_expr).into_iter()
+            .map(|comparison| AssertionWithoutId{
+                kind: box common::AssertionKind::Expr(ExpressionWithoutId::new(comparison))
+            });
+        if self.disjunction {
+            let mut comparisons: Vec<_> = comparisons.collect();
+            let disjunct = if comparisons.len() == 1 {
+                comparisons.pop().unwrap()
+            } else {
+                AssertionWithoutId{
+                    kind: box common::AssertionKind::And(comparisons)
+                }
+            };
+            self.conjuncts.push(disjunct);
+        } else {
+            self.conjuncts.extend(comparisons);
+        }
         Ok(())
     }
     fn error_expected_expr_without_implication(&self) -> syn::Error {
@@ -679,7 +1090,7 @@ impl Parser {
         syn::Error::new(self.input.span, "expected Prusti assertion")
     }
     fn error_expected_operator(&self) -> syn::Error {
-        syn::Error::new(self.input.span, "expected `&&` or `==>`")
+        syn::Error::new(self.input.span, "expected `&&`, `||` or `==>`")
     }
     fn error_expected_parenthesis(&self) -> syn::Error {
         syn::Error::new(self.input.span, "expected `(`")
@@ -696,6 +1107,12 @@ impl Parser {
     fn error_expected_equals(&self) -> syn::Error {
         syn::Error::new(self.input.span, "expected `=`")
     }
+    fn error_expected_in(&self) -> syn::Error {
+        syn::Error::new(self.input.span, "expected `in`")
+    }
+    fn error_expected_weight(&self) -> syn::Error {
+        syn::Error::new(self.input.span, "the `weight` of a quantifier must be an integer literal")
+    }
     fn error_expected_tuple(&self) -> syn::Error {
         syn::Error::new(self.input.span, "`triggers` must be an array of tuples containing Rust expressions")
     }
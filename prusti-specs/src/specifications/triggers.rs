@@ -0,0 +1,316 @@
+//! Inference of the triggers of quantifiers written without
+//! `triggers = [..]`.
+//!
+//! The triggers are inferred on the untyped specifications, so that the type
+//! of the inferred terms is checked like for the triggers written by the user.
+//! Since the expansion of the specifications does not depend on the
+//! configuration of Prusti, the triggers of all the strategies are inferred
+//! and tagged with their strategies; the triggers of the configured strategy
+//! are selected when the specifications are type-checked.
+
+use super::common::{self, ExpressionIdGenerator};
+use super::preparser::{Arg, ExpressionWithoutId};
+use super::untyped::{AssignExpressionId, Assertion, Expression, TriggerSet};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use syn::visit::Visit;
+
+/// How the triggers of a quantifier written without `triggers = [..]` are
+/// chosen. The strategy is given by the `TRIGGER_STRATEGY` configuration
+/// flag (`none`, `shallow` or `aggressive`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerStrategy {
+    /// Do not infer triggers, and leave the choice to the verifier.
+    None,
+    /// Use the outermost function calls (and indexing operations) of the body
+    /// that mention the bound variables.
+    Shallow,
+    /// Like `Shallow`, but also use the nested function calls, and always add
+    /// a trigger of several terms if they together cover the bound variables.
+    Aggressive,
+}
+
+impl TriggerStrategy {
+    /// The strategies that infer triggers.
+    pub const INFERRING: [TriggerStrategy; 2] = [TriggerStrategy::Shallow, TriggerStrategy::Aggressive];
+
+    /// The strategy with the given name, ignoring the case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(TriggerStrategy::None),
+            "shallow" => Some(TriggerStrategy::Shallow),
+            "aggressive" => Some(TriggerStrategy::Aggressive),
+            _ => None,
+        }
+    }
+}
+
+impl TriggerSet {
+    /// Infers triggers for the quantifier over `vars` with the given `body`
+    /// with each of the strategies that infer triggers. Each trigger is
+    /// returned once, together with the strategies that infer it.
+    ///
+    /// Each term that mentions all the variables is a trigger on its own;
+    /// otherwise, terms are combined into a single trigger that covers all
+    /// the variables. A term is a function call or an indexing operation that
+    /// mentions a bound variable, but not as an operand of an arithmetic or
+    /// logical operator, which the verifier does not allow in triggers. The
+    /// result is empty if the variables cannot be covered.
+    pub fn infer(
+        body: &Assertion,
+        vars: &common::ForAllVars<common::ExpressionId, Arg>,
+        id_generator: &mut ExpressionIdGenerator,
+    ) -> (TriggerSet, Vec<Vec<TriggerStrategy>>) {
+        let mut tagged: Vec<(Vec<syn::Expr>, Vec<TriggerStrategy>)> = vec![];
+        for &strategy in TriggerStrategy::INFERRING.iter() {
+            for terms in infer_trigger_terms(body, vars, strategy) {
+                let tokens = terms_tokens(&terms);
+                match tagged.iter_mut().find(|(existing, _)| terms_tokens(existing) == tokens) {
+                    Some((_, strategies)) => strategies.push(strategy),
+                    None => tagged.push((terms, vec![strategy])),
+                }
+            }
+        }
+        let (triggers, strategies) = tagged.into_iter()
+            .map(|(terms, strategies)| (trigger_with_ids(terms, vars, id_generator), strategies))
+            .unzip();
+        (TriggerSet::new(triggers), strategies)
+    }
+}
+
+/// The tokens of the terms of a trigger, to compare inferred triggers.
+fn terms_tokens(terms: &[syn::Expr]) -> Vec<String> {
+    terms.iter().map(|term| term.to_token_stream().to_string()).collect()
+}
+
+/// A trigger of the quantifier over `vars` with the given terms.
+fn trigger_with_ids(
+    terms: Vec<syn::Expr>,
+    vars: &common::ForAllVars<common::ExpressionId, Arg>,
+    id_generator: &mut ExpressionIdGenerator,
+) -> common::Trigger<common::ExpressionId, syn::Expr> {
+    common::Trigger::new(
+        terms.into_iter()
+            .map(|term| -> Expression {
+                ExpressionWithoutId::new(term).assign_id(vars.spec_id, id_generator)
+            })
+            .collect()
+    )
+}
+
+/// The terms of each trigger inferred by `strategy`, see `TriggerSet::infer`.
+fn infer_trigger_terms(
+    body: &Assertion,
+    vars: &common::ForAllVars<common::ExpressionId, Arg>,
+    strategy: TriggerStrategy,
+) -> Vec<Vec<syn::Expr>> {
+    if strategy == TriggerStrategy::None {
+        return vec![];
+    }
+    let names: Vec<String> = vars.vars.iter().map(|var| var.name.to_string()).collect();
+    let mut collector = TriggerTermCollector {
+        names: &names,
+        nested_names: nested_bound_names(body).into_iter().collect(),
+        nested: strategy == TriggerStrategy::Aggressive,
+        terms: vec![],
+        seen: HashSet::new(),
+    };
+    for expr in body.expressions() {
+        collector.visit_expr(&expr.expr);
+    }
+    let terms = collector.terms;
+
+    let mentions: Vec<HashSet<String>> = terms.iter()
+        .map(|term| mentioned_names(term, &names))
+        .collect();
+    let mut triggers: Vec<Vec<syn::Expr>> = terms.iter()
+        .zip(&mentions)
+        .filter(|(_, mentioned)| mentioned.len() == names.len())
+        .map(|(term, _)| vec![term.clone()])
+        .collect();
+    if triggers.is_empty() || strategy == TriggerStrategy::Aggressive {
+        let mut uncovered: HashSet<String> = names.iter().cloned().collect();
+        let mut combined = vec![];
+        for (term, mentioned) in terms.iter().zip(&mentions) {
+            if mentioned.len() < names.len() && mentioned.iter().any(|name| uncovered.contains(name)) {
+                uncovered.retain(|name| !mentioned.contains(name));
+                combined.push(term.clone());
+            }
+        }
+        if uncovered.is_empty() && combined.len() > 1 {
+            triggers.push(combined);
+        }
+    }
+    triggers
+}
+
+impl Assertion {
+    /// Infers the triggers of every quantifier without triggers, see
+    /// `TriggerSet::infer`. The strategies that infer each trigger are
+    /// recorded in the `trigger_strategies` of the quantifier.
+    pub fn infer_missing_triggers(self, id_generator: &mut ExpressionIdGenerator) -> Assertion {
+        use common::AssertionKind::*;
+        let mut infer = |assertion: Assertion| assertion.infer_missing_triggers(id_generator);
+        let kind = match *self.kind {
+            kind @ Expr(_) => kind,
+            kind @ Matches(..) => kind,
+            And(conjuncts) => And(conjuncts.into_iter().map(&mut infer).collect()),
+            Or(disjuncts) => Or(disjuncts.into_iter().map(&mut infer).collect()),
+            Implies(lhs, rhs) => Implies(infer(lhs), infer(rhs)),
+            Iff(lhs, rhs) => Iff(infer(lhs), infer(rhs)),
+            TypeCond(vars, body) => TypeCond(vars, infer(body)),
+            ForAll(mut vars, triggers, body) => {
+                let body = infer(body);
+                let triggers = infer_if_missing(&mut vars, triggers, &body, id_generator);
+                ForAll(vars, triggers, body)
+            }
+            Exists(mut vars, triggers, body) => {
+                let body = infer(body);
+                let triggers = infer_if_missing(&mut vars, triggers, &body, id_generator);
+                Exists(vars, triggers, body)
+            }
+            BoundedForAll(vars, lower, upper, body) => BoundedForAll(vars, lower, upper, infer(body)),
+            Let(vars, value, body) => Let(vars, value, infer(body)),
+        };
+        Assertion { kind: box kind }
+    }
+}
+
+/// The written `triggers` of the quantifier over `vars`, or the inferred ones
+/// if there are none.
+fn infer_if_missing(
+    vars: &mut common::ForAllVars<common::ExpressionId, Arg>,
+    triggers: TriggerSet,
+    body: &Assertion,
+    id_generator: &mut ExpressionIdGenerator,
+) -> TriggerSet {
+    if !triggers.triggers().is_empty() {
+        return triggers;
+    }
+    let (inferred, strategies) = TriggerSet::infer(body, vars, id_generator);
+    vars.trigger_strategies = strategies;
+    inferred
+}
+
+/// The names of the variables bound inside of `assertion`, which cannot be
+/// used in the triggers of an enclosing quantifier.
+fn nested_bound_names(assertion: &Assertion) -> HashSet<String> {
+    use common::AssertionKind::*;
+    let mut names = HashSet::new();
+    let mut pending = vec![assertion];
+    while let Some(assertion) = pending.pop() {
+        match *assertion.kind {
+            Expr(_) | Matches(..) => {}
+            And(ref conjuncts) | Or(ref conjuncts) => pending.extend(conjuncts),
+            Implies(ref lhs, ref rhs) | Iff(ref lhs, ref rhs) => {
+                pending.push(lhs);
+                pending.push(rhs);
+            }
+            TypeCond(ref vars, ref body) |
+            ForAll(ref vars, _, ref body) |
+            Exists(ref vars, _, ref body) |
+            BoundedForAll(ref vars, _, _, ref body) |
+            Let(ref vars, _, ref body) => {
+                names.extend(vars.vars.iter().map(|var| var.name.to_string()));
+                pending.push(body);
+            }
+        }
+    }
+    names
+}
+
+/// Calls `f` on `expr` and on each of its subexpressions.
+fn for_each_subexpr(expr: &syn::Expr, f: &mut dyn FnMut(&syn::Expr)) {
+    struct SubexprVisitor<'a> {
+        f: &'a mut dyn FnMut(&syn::Expr),
+    }
+
+    impl<'a, 'ast> Visit<'ast> for SubexprVisitor<'a> {
+        fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+            (self.f)(expr);
+            syn::visit::visit_expr(self, expr);
+        }
+    }
+
+    SubexprVisitor { f }.visit_expr(expr);
+}
+
+/// The names among `names` that are mentioned by `expr`.
+fn mentioned_names(expr: &syn::Expr, names: &[String]) -> HashSet<String> {
+    let mut mentioned = HashSet::new();
+    for_each_subexpr(expr, &mut |subexpr| {
+        if let syn::Expr::Path(syn::ExprPath { qself: None, path, .. }) = subexpr {
+            if let Some(ident) = path.get_ident() {
+                let ident = ident.to_string();
+                if names.contains(&ident) {
+                    mentioned.insert(ident);
+                }
+            }
+        }
+    });
+    mentioned
+}
+
+/// A visitor that collects the candidate trigger terms of a quantifier body.
+struct TriggerTermCollector<'a> {
+    /// The names of the variables of the quantifier.
+    names: &'a [String],
+    /// The names of the variables bound inside of the body.
+    nested_names: Vec<String>,
+    /// Whether to look for terms inside of other terms.
+    nested: bool,
+    terms: Vec<syn::Expr>,
+    /// The tokens of the collected terms, to skip duplicates.
+    seen: HashSet<String>,
+}
+
+impl<'a> TriggerTermCollector<'a> {
+    fn is_trigger_term(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Call(syn::ExprCall { func, .. }) => {
+                // `old(..)` is not a function of the encoding
+                if let syn::Expr::Path(syn::ExprPath { path, .. }) = &**func {
+                    if path.is_ident("old") || path.is_ident("before_expiry") {
+                        return false;
+                    }
+                }
+            }
+            syn::Expr::MethodCall(_) | syn::Expr::Index(_) => {}
+            _ => return false,
+        }
+        if mentioned_names(expr, self.names).is_empty() ||
+            !mentioned_names(expr, &self.nested_names).is_empty() {
+            return false;
+        }
+        let mut has_operator = false;
+        for_each_subexpr(expr, &mut |subexpr| {
+            // Dereferences are not part of the encoding
+            let is_operator = match subexpr {
+                syn::Expr::Binary(_) => true,
+                syn::Expr::Unary(syn::ExprUnary { op, .. }) => !matches!(op, syn::UnOp::Deref(_)),
+                _ => false,
+            };
+            if is_operator {
+                if !mentioned_names(subexpr, self.names).is_empty() {
+                    has_operator = true;
+                }
+            }
+        });
+        !has_operator
+    }
+}
+
+impl<'a, 'ast> Visit<'ast> for TriggerTermCollector<'a> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        if self.is_trigger_term(expr) {
+            if self.seen.insert(expr.to_token_stream().to_string()) {
+                self.terms.push(expr.clone());
+            }
+            if !self.nested {
+                return;
+            }
+        }
+        syn::visit::visit_expr(self, expr);
+    }
+}
@@ -1,9 +1,11 @@
 use super::common::{self, ExpressionIdGenerator};
-use proc_macro2::{TokenStream, Span, Spacing, Punct};
+use proc_macro2::{TokenStream, TokenTree, Span, Spacing, Punct};
 use quote::{quote_spanned, ToTokens, TokenStreamExt};
 use std::collections::HashMap;
 use syn::parse::{Parse, ParseStream};
 use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
 
 pub use common::{ExpressionId, SpecType, SpecificationId};
 pub use super::preparser::{Parser, Arg};
@@ -25,6 +27,8 @@ pub type Assertion = common::Assertion<ExpressionId, syn::Expr, Arg>;
 pub type AssertionKind = common::AssertionKind<ExpressionId, syn::Expr, Arg>;
 /// An expression that has no types associated with it.
 pub type Expression = common::Expression<ExpressionId, syn::Expr>;
+/// A pattern that has no types associated with it.
+pub type Pattern = common::Pattern<ExpressionId, syn::Expr>;
 /// A trigger set that has not types associated with it.
 pub type TriggerSet = common::TriggerSet<ExpressionId, syn::Expr>;
 /// A pledge that has not types associated with it.
@@ -94,15 +98,254 @@ impl Assertion {
         let assertion = parser.extract_assertion()?;
         Ok(assertion.assign_id(spec_id, id_generator))
     }
+
+    /// Whether both assertions are equal up to a consistent renaming of their
+    /// bound variables, e.g. `forall(|i: usize| i < n)` and
+    /// `forall(|j: usize| j < n)`. Expressions are compared token by token;
+    /// variables bound by closures inside of expressions are not renamed.
+    pub fn alpha_eq(&self, other: &Self) -> bool {
+        self.alpha_eq_with(
+            other,
+            &|lhs, rhs| tokens_eq(lhs.typ.to_token_stream(), rhs.typ.to_token_stream(), &[]),
+            &|lhs, rhs, bindings| tokens_eq(lhs.to_token_stream(), rhs.to_token_stream(), bindings),
+        )
+    }
+
+    /// Collapse nested implications and rewrite implications `!a ==> b` into
+    /// `a || b`, see `normalize_implications_with`.
+    pub fn normalize_implications(self) -> Self {
+        self.normalize_implications_with(&negated_operand)
+    }
+
+    /// Replaces the `result` variable with `replacement` everywhere in the
+    /// assertion, including inside compound expressions such as
+    /// `result + 1 > 0`, e.g. to inline the value returned by a call into the
+    /// postcondition of the callee. The replacement is parenthesized, so that
+    /// it keeps its precedence. A `result` that is bound by a quantifier, a
+    /// `let` or a closure of an expression refers to that binding and is not
+    /// replaced.
+    pub fn subst_result(self, replacement: &Expression) -> Self {
+        let subst = |assertion: Assertion| assertion.subst_result(replacement);
+        let kind = match *self.kind {
+            common::AssertionKind::Expr(expr) => {
+                common::AssertionKind::Expr(expr.subst_result(replacement))
+            }
+            common::AssertionKind::Matches(scrutinee, mut pattern) => {
+                pattern.check = subst_result_in_matches(pattern.check, replacement);
+                common::AssertionKind::Matches(scrutinee.subst_result(replacement), pattern)
+            }
+            common::AssertionKind::And(conjuncts) => {
+                common::AssertionKind::And(conjuncts.into_iter().map(subst).collect())
+            }
+            common::AssertionKind::Or(disjuncts) => {
+                common::AssertionKind::Or(disjuncts.into_iter().map(subst).collect())
+            }
+            common::AssertionKind::Implies(lhs, rhs) => {
+                common::AssertionKind::Implies(subst(lhs), subst(rhs))
+            }
+            common::AssertionKind::Iff(lhs, rhs) => {
+                common::AssertionKind::Iff(subst(lhs), subst(rhs))
+            }
+            common::AssertionKind::TypeCond(vars, body) => {
+                common::AssertionKind::TypeCond(vars, subst(body))
+            }
+            common::AssertionKind::ForAll(vars, triggers, body) if !binds_result(&vars) => {
+                common::AssertionKind::ForAll(vars, triggers.subst_result(replacement), subst(body))
+            }
+            common::AssertionKind::Exists(vars, triggers, body) if !binds_result(&vars) => {
+                common::AssertionKind::Exists(vars, triggers.subst_result(replacement), subst(body))
+            }
+            common::AssertionKind::BoundedForAll(vars, lower, upper, body) if !binds_result(&vars) => {
+                common::AssertionKind::BoundedForAll(
+                    vars,
+                    lower.subst_result(replacement),
+                    upper.subst_result(replacement),
+                    subst(body),
+                )
+            }
+            // The value of a `let` is outside of the scope of its variable
+            common::AssertionKind::Let(vars, value, body) => {
+                let value = value.subst_result(replacement);
+                let body = if binds_result(&vars) { body } else { subst(body) };
+                common::AssertionKind::Let(vars, value, body)
+            }
+            // The quantifier binds its own `result`
+            kind => kind,
+        };
+        Assertion { kind: box kind }
+    }
+}
+
+impl Expression {
+    fn subst_result(mut self, replacement: &Expression) -> Self {
+        if subst_result_in_expr(&mut self.expr, &replacement.expr) {
+            self.contains_old |= replacement.contains_old;
+        }
+        self
+    }
+}
+
+impl TriggerSet {
+    fn subst_result(self, replacement: &Expression) -> Self {
+        common::TriggerSet(
+            self.0.into_iter()
+                .map(|trigger| common::Trigger(
+                    trigger.0.into_iter().map(|term| term.subst_result(replacement)).collect()
+                ))
+                .collect()
+        )
+    }
+}
+
+/// Whether one of the variables of `vars` is called `result`.
+fn binds_result(vars: &ForAllVars<ExpressionId, Arg>) -> bool {
+    vars.vars.iter().any(|var| var.name == "result")
+}
+
+/// The check `matches!(scrutinee, pattern)` of a pattern, with `result`
+/// replaced in the scrutinee. The pattern itself is left as it is.
+fn subst_result_in_matches(check: syn::Expr, replacement: &Expression) -> syn::Expr {
+    let mut mac = match check {
+        syn::Expr::Macro(mac) => mac,
+        check => return check,
+    };
+    let (mut scrutinee, rest) = match syn::parse2::<MatchesScrutinee>(mac.mac.tokens.clone()) {
+        Ok(MatchesScrutinee { scrutinee, rest }) => (scrutinee, rest),
+        Err(_) => return syn::Expr::Macro(mac),
+    };
+    subst_result_in_expr(&mut scrutinee, &replacement.expr);
+    mac.mac.tokens = quote_spanned! { mac.mac.tokens.span() => #scrutinee, #rest };
+    syn::Expr::Macro(mac)
+}
+
+/// The arguments of a `matches!` call, split after the scrutinee.
+struct MatchesScrutinee {
+    scrutinee: syn::Expr,
+    rest: TokenStream,
+}
+
+impl Parse for MatchesScrutinee {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let scrutinee = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        Ok(Self { scrutinee, rest: input.parse()? })
+    }
+}
+
+/// Replaces `result` in `expr`, see `ResultReplacer`. Returns whether it
+/// occurred in the expression.
+fn subst_result_in_expr(expr: &mut syn::Expr, replacement: &syn::Expr) -> bool {
+    let mut replacer = ResultReplacer { replacement, replaced: false };
+    replacer.visit_expr_mut(expr);
+    replacer.replaced
+}
+
+/// Replaces the path expressions `result`, except in the closures that bind
+/// their own `result`.
+struct ResultReplacer<'a> {
+    replacement: &'a syn::Expr,
+    replaced: bool,
+}
+
+impl<'a> VisitMut for ResultReplacer<'a> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        match expr {
+            syn::Expr::Path(syn::ExprPath { qself: None, path, .. }) if path.is_ident("result") => {
+                *expr = syn::Expr::Paren(syn::ExprParen {
+                    attrs: vec![],
+                    paren_token: Default::default(),
+                    expr: Box::new(self.replacement.clone()),
+                });
+                self.replaced = true;
+            }
+            syn::Expr::Closure(closure) if closure.inputs.iter().any(pat_binds_result) => {}
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_field_value_mut(&mut self, field: &mut syn::FieldValue) {
+        // The shorthand `S { result }` needs an explicit field name once the
+        // value is replaced
+        let is_result = match field.member {
+            syn::Member::Named(ref name) => name == "result",
+            syn::Member::Unnamed(_) => false,
+        };
+        if is_result && field.colon_token.is_none() {
+            field.colon_token = Some(Default::default());
+        }
+        syn::visit_mut::visit_field_value_mut(self, field);
+    }
+}
+
+/// Whether the pattern binds a variable called `result`.
+fn pat_binds_result(pat: &syn::Pat) -> bool {
+    struct BindingFinder {
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for BindingFinder {
+        fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+            self.found |= pat.ident == "result";
+            syn::visit::visit_pat_ident(self, pat);
+        }
+    }
+    let mut finder = BindingFinder { found: false };
+    finder.visit_pat(pat);
+    finder.found
+}
+
+/// The operand `a` of a (possibly parenthesized) negation `!a`.
+fn negated_operand(expr: &syn::Expr) -> Option<syn::Expr> {
+    match expr {
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Not(_), expr, .. }) => Some((**expr).clone()),
+        syn::Expr::Paren(syn::ExprParen { expr, .. }) => negated_operand(expr),
+        _ => None,
+    }
+}
+
+/// Whether the token streams are equal, where an identifier bound in
+/// `bindings` on the left must correspond to the identifier bound at the same
+/// position on the right.
+fn tokens_eq(lhs: TokenStream, rhs: TokenStream, bindings: &[(&Arg, &Arg)]) -> bool {
+    let mut lhs = lhs.into_iter();
+    let mut rhs = rhs.into_iter();
+    loop {
+        match (lhs.next(), rhs.next()) {
+            (None, None) => return true,
+            (Some(TokenTree::Ident(l)), Some(TokenTree::Ident(r))) => {
+                let l_binding = bindings.iter().rposition(|(var, _)| var.name == l);
+                let r_binding = bindings.iter().rposition(|(_, var)| var.name == r);
+                let eq = match (l_binding, r_binding) {
+                    (Some(l_binding), Some(r_binding)) => l_binding == r_binding,
+                    (None, None) => l == r,
+                    _ => false,
+                };
+                if !eq {
+                    return false;
+                }
+            }
+            (Some(TokenTree::Group(l)), Some(TokenTree::Group(r))) => {
+                if l.delimiter() != r.delimiter() || !tokens_eq(l.stream(), r.stream(), bindings) {
+                    return false;
+                }
+            }
+            (Some(TokenTree::Punct(l)), Some(TokenTree::Punct(r))) => {
+                if l.as_char() != r.as_char() || l.spacing() != r.spacing() {
+                    return false;
+                }
+            }
+            (Some(TokenTree::Literal(l)), Some(TokenTree::Literal(r))) => {
+                if l.to_string() != r.to_string() {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
 }
 
 impl Parse for common::Expression<(), syn::Expr> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(Self {
-            spec_id: SpecificationId::dummy(),
-            id: (),
-            expr: input.parse()?,
-        })
+        Ok(Self::new(input.parse()?))
     }
 }
 
@@ -164,6 +407,7 @@ impl AssignExpressionId<Expression> for common::Expression<(), syn::Expr> {
             spec_id,
             id: id_generator.generate(),
             expr: self.expr,
+            contains_old: self.contains_old,
         }
     }
 }
@@ -194,6 +438,20 @@ impl AssignExpressionId<Option<Expression>> for Option<common::Expression<(), sy
     }
 }
 
+impl AssignExpressionId<Pattern> for common::Pattern<(), syn::Expr> {
+    fn assign_id(
+        self,
+        spec_id: SpecificationId,
+        id_generator: &mut ExpressionIdGenerator,
+    ) -> Pattern {
+        Pattern {
+            spec_id,
+            id: id_generator.generate(),
+            check: self.check,
+        }
+    }
+}
+
 impl AssignExpressionId<ForAllVars<ExpressionId, Arg>> for common::ForAllVars<(), Arg> {
     fn assign_id(
         self,
@@ -203,7 +461,10 @@ impl AssignExpressionId<ForAllVars<ExpressionId, Arg>> for common::ForAllVars<()
         ForAllVars {
             spec_id,
             id: id_generator.generate(),
-            vars: self.vars
+            vars: self.vars,
+            enumerated: self.enumerated,
+            weight: self.weight,
+            trigger_strategies: self.trigger_strategies,
         }
     }
 }
@@ -238,21 +499,53 @@ impl AssignExpressionId<AssertionKind> for common::AssertionKind<(), syn::Expr,
         use common::AssertionKind::*;
         match self {
             Expr(expr) => Expr(expr.assign_id(spec_id, id_generator)),
+            Matches(scrutinee, pattern) => Matches(
+                scrutinee.assign_id(spec_id, id_generator),
+                pattern.assign_id(spec_id, id_generator)
+            ),
             And(assertions) => And(
                 assertions.into_iter()
                           .map(|assertion|
                               Assertion { kind: assertion.kind.assign_id(spec_id, id_generator) })
                           .collect()),
+            Or(assertions) => Or(
+                assertions.into_iter()
+                          .map(|assertion|
+                              Assertion { kind: assertion.kind.assign_id(spec_id, id_generator) })
+                          .collect()),
             Implies(lhs, rhs) => Implies(
                 lhs.assign_id(spec_id, id_generator),
                 rhs.assign_id(spec_id, id_generator)
             ),
+            Iff(lhs, rhs) => Iff(
+                lhs.assign_id(spec_id, id_generator),
+                rhs.assign_id(spec_id, id_generator)
+            ),
             ForAll(vars, triggers, body) => ForAll(
                 vars.assign_id(spec_id, id_generator),
                 triggers.assign_id(spec_id, id_generator),
                 body.assign_id(spec_id, id_generator)
             ),
-            x => unimplemented!("{:?}", x),
+            Exists(vars, triggers, body) => Exists(
+                vars.assign_id(spec_id, id_generator),
+                triggers.assign_id(spec_id, id_generator),
+                body.assign_id(spec_id, id_generator)
+            ),
+            TypeCond(vars, body) => TypeCond(
+                vars.assign_id(spec_id, id_generator),
+                body.assign_id(spec_id, id_generator)
+            ),
+            BoundedForAll(vars, lower, upper, body) => BoundedForAll(
+                vars.assign_id(spec_id, id_generator),
+                lower.assign_id(spec_id, id_generator),
+                upper.assign_id(spec_id, id_generator),
+                body.assign_id(spec_id, id_generator)
+            ),
+            Let(vars, value, body) => Let(
+                vars.assign_id(spec_id, id_generator),
+                value.assign_id(spec_id, id_generator),
+                body.assign_id(spec_id, id_generator)
+            ),
         }
     }
 }
@@ -332,16 +625,45 @@ impl EncodeTypeCheck for Assertion {
             AssertionKind::Expr(expression) => {
                 expression.encode_type_check(tokens);
             }
-            AssertionKind::And(assertions) => {
+            AssertionKind::Matches(scrutinee, pattern) => {
+                // The scrutinee is not a boolean, so it is only borrowed to
+                // resolve its type and span; the check itself is evaluated.
+                let scrutinee_span = scrutinee.expr.span();
+                let scrutinee_expr = &scrutinee.expr;
+                let scrutinee_identifier = format!("{}_{}", scrutinee.spec_id, scrutinee.id);
+                let check_span = pattern.check.span();
+                let check = &pattern.check;
+                let check_identifier = format!("{}_{}", pattern.spec_id, pattern.id);
+                let typeck_call = quote_spanned! { scrutinee_span =>
+                    #[prusti::spec_only]
+                    #[prusti::expr_id = #scrutinee_identifier]
+                    || {
+                        let _ = &#scrutinee_expr;
+                    };
+                };
+                tokens.extend(typeck_call);
+                let typeck_call = quote_spanned! { check_span =>
+                    #[prusti::spec_only]
+                    #[prusti::expr_id = #check_identifier]
+                    || -> bool {
+                        #check
+                    };
+                };
+                tokens.extend(typeck_call);
+            }
+            AssertionKind::And(assertions) |
+            AssertionKind::Or(assertions) => {
                 for assertion in assertions {
                     assertion.encode_type_check(tokens);
                 }
             }
-            AssertionKind::Implies(lhs, rhs) => {
+            AssertionKind::Implies(lhs, rhs) |
+            AssertionKind::Iff(lhs, rhs) => {
                 lhs.encode_type_check(tokens);
                 rhs.encode_type_check(tokens);
             }
-            AssertionKind::ForAll(vars, triggers, body) => {
+            AssertionKind::ForAll(vars, triggers, body) |
+            AssertionKind::Exists(vars, triggers, body) => {
                 let vec_of_vars = &vars.vars;
                 let span = Span::call_site();
                 let identifier = format!("{}_{}", vars.spec_id, vars.id);
@@ -359,6 +681,52 @@ impl EncodeTypeCheck for Assertion {
                 };
                 tokens.extend(typeck_call);
             }
+            AssertionKind::TypeCond(vars, body) => {
+                let vec_of_vars = &vars.vars;
+                let span = Span::call_site();
+                let identifier = format!("{}_{}", vars.spec_id, vars.id);
+
+                let mut nested_assertion = TokenStream::new();
+                body.encode_type_check(&mut nested_assertion);
+
+                let typeck_call = quote_spanned! { span =>
+                    #[prusti::spec_only]
+                    #[prusti::expr_id = #identifier]
+                    |#(#vec_of_vars),*| {
+                        #nested_assertion
+                    };
+                };
+                tokens.extend(typeck_call);
+            }
+            AssertionKind::Let(vars, value, body) => {
+                let vec_of_vars = &vars.vars;
+                let span = Span::call_site();
+                let identifier = format!("{}_{}", vars.spec_id, vars.id);
+                let value_span = value.expr.span();
+                let value_expr = &value.expr;
+                let value_typ = &vars.vars[0].typ;
+                let value_identifier = format!("{}_{}", value.spec_id, value.id);
+
+                let mut nested_assertion = TokenStream::new();
+                body.encode_type_check(&mut nested_assertion);
+
+                let value_typeck_call = quote_spanned! { value_span =>
+                    #[prusti::spec_only]
+                    #[prusti::expr_id = #value_identifier]
+                    || -> #value_typ {
+                        #value_expr
+                    };
+                };
+                let typeck_call = quote_spanned! { span =>
+                    #[prusti::spec_only]
+                    #[prusti::expr_id = #identifier]
+                    |#(#vec_of_vars),*| {
+                        #nested_assertion
+                    };
+                };
+                tokens.extend(value_typeck_call);
+                tokens.extend(typeck_call);
+            }
             x => {
                 unimplemented!("{:?}", x);
             }
@@ -381,3 +749,84 @@ impl EncodeTypeCheck for Expression {
         tokens.extend(typeck_call);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    fn parse(tokens: TokenStream, id_generator: &mut ExpressionIdGenerator) -> Assertion {
+        Assertion::parse(tokens, SpecificationId::dummy(), id_generator).unwrap()
+    }
+
+    fn replacement(tokens: TokenStream, id_generator: &mut ExpressionIdGenerator) -> Expression {
+        common::Expression {
+            spec_id: SpecificationId::dummy(),
+            id: id_generator.generate(),
+            expr: syn::parse2(tokens).unwrap(),
+            contains_old: false,
+        }
+    }
+
+    fn expressions(assertion: &Assertion) -> Vec<String> {
+        assertion.expressions().map(|expr| expr.expr.to_token_stream().to_string()).collect()
+    }
+
+    #[test]
+    fn test_subst_result_in_compound_expressions() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let assertion = parse(quote! { result + 1 > 0 && (x > 0 ==> result == 2 * x) }, &mut id_generator);
+        let replacement = replacement(quote! { f(x) }, &mut id_generator);
+        assert_eq!(
+            expressions(&assertion.subst_result(&replacement)),
+            vec![
+                quote! { (f(x)) + 1 > 0 }.to_string(),
+                quote! { x > 0 }.to_string(),
+                quote! { (f(x)) == 2 * x }.to_string(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_subst_result_keeps_bound_result() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let assertion = parse(
+            quote! {
+                forall(|result: u32| result > 0) &&
+                v.iter().all(|result| *result > 0) &&
+                let r: u32 = result in r >= result
+            },
+            &mut id_generator,
+        );
+        let replacement = replacement(quote! { f(x) }, &mut id_generator);
+        assert_eq!(
+            expressions(&assertion.subst_result(&replacement)),
+            vec![
+                quote! { result > 0 }.to_string(),
+                quote! { v.iter().all(|result| *result > 0) }.to_string(),
+                quote! { (f(x)) }.to_string(),
+                quote! { r >= (f(x)) }.to_string(),
+            ],
+        );
+
+        let assertion = parse(quote! { let result: u32 = result + 1 in result > 0 }, &mut id_generator);
+        assert_eq!(
+            expressions(&assertion.subst_result(&replacement)),
+            vec![
+                quote! { (f(x)) + 1 }.to_string(),
+                quote! { result > 0 }.to_string(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_subst_result_with_old() {
+        let mut id_generator = ExpressionIdGenerator::new();
+        let assertion = parse(quote! { result > 0 && x > 0 }, &mut id_generator);
+        let mut replacement = replacement(quote! { old(x) }, &mut id_generator);
+        replacement.contains_old = true;
+        let substituted = assertion.subst_result(&replacement);
+        let contains_old: Vec<_> = substituted.expressions().map(|expr| expr.contains_old).collect();
+        assert_eq!(contains_old, vec![true, false]);
+    }
+}
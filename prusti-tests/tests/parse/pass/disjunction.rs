@@ -0,0 +1,17 @@
+use prusti_contracts::*;
+
+#[requires(forall(|a: i32| a == 5) || forall(|a: i32| a == 5))]
+fn test1() {}
+
+#[requires((true ==> true) || true)]
+fn test2() {}
+
+#[requires(true || (true && (true || true)) || 1 < 2 < 3)]
+fn test3() {}
+
+#[ensures(result == x || (result == y ==> x < y))]
+fn test4(x: u32, y: u32) -> u32 {
+    if x < y { y } else { x }
+}
+
+fn main() {}
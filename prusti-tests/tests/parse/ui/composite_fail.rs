@@ -7,7 +7,4 @@ use prusti_contracts::*;
 #[requires(true && true || true)]
 fn test1() {}
 
-#[requires(forall(|a: i32| a == 5) || forall(|a: i32| a == 5))]
-fn test2() {}
-
 fn main() {}
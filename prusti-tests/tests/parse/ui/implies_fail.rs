@@ -10,7 +10,4 @@ fn test1() {}
 #[requires(==> true)]
 fn test2() {}
 
-#[requires((true ==> true) || true)]
-fn test3() {}
-
 fn main() {}
@@ -0,0 +1,87 @@
+mod utils;
+
+const SPECS: &str = include_str!("spec_details/specs.rs");
+
+fn dump_spec_details() -> Vec<String> {
+    let args = ["-Zdump-spec-details", "-Zskip-verify"];
+    utils::run_prusti_rustc(&["spec_details", "specs.rs"], &args)
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// The byte ranges of a comma-separated list of `lo..hi` spans.
+fn parse_spans(spans: &str) -> Vec<(u32, u32)> {
+    spans
+        .split(", ")
+        .filter(|span| !span.is_empty())
+        .map(|span| {
+            let mut bounds = span.split("..").map(|bound| bound.parse().unwrap());
+            (bounds.next().unwrap(), bounds.next().unwrap())
+        })
+        .collect()
+}
+
+/// The text between the first pair of backticks after `prefix` in `line`.
+fn quoted<'a>(line: &'a str, prefix: &str) -> &'a str {
+    let start = line.find(prefix).unwrap() + prefix.len();
+    let end = start + line[start..].find('`').unwrap();
+    &line[start..end]
+}
+
+/// The text between `start` and `end` in `line`.
+fn between<'a>(line: &'a str, start: &str, end: &str) -> &'a str {
+    let from = line.find(start).unwrap() + start.len();
+    let to = from + line[from..].find(end).unwrap();
+    &line[from..to]
+}
+
+fn expression_lines(lines: &[String]) -> Vec<&String> {
+    lines.iter().filter(|line| line.starts_with("  expr ")).collect()
+}
+
+#[test]
+fn spec_details_describe_each_expression() {
+    let lines = dump_spec_details();
+    let exprs = expression_lines(&lines);
+    let sources: Vec<_> = exprs.iter().map(|line| quoted(line, "expr `")).collect();
+    for expected in &["a > 0", "b > 0", "result == a + b", "i < n", "i <= n"] {
+        assert!(sources.contains(expected), "no expression `{}` in {:#?}", expected, lines);
+    }
+    for line in exprs {
+        // The smallest subassertion at the span of an expression is the
+        // expression itself.
+        assert_eq!(quoted(line, "expr `"), quoted(line, "subassertion `"), "{}", line);
+        // The spans start on the line of the expression in the source file.
+        let source = quoted(line, "expr `");
+        let expected_line = SPECS.lines().position(|text| text.contains(source)).unwrap() + 1;
+        let range = between(line, " (", "): ");
+        let start_line: usize = range.rsplit(':').nth(2).unwrap().parse().unwrap();
+        assert_eq!(start_line, expected_line, "{}", line);
+    }
+    assert!(
+        lines.iter().any(|line| line == "spec add: a > 0 && b > 0"),
+        "no precondition of `add` in {:#?}",
+        lines,
+    );
+}
+
+#[test]
+fn spec_details_describe_the_quantified_variables() {
+    let lines = dump_spec_details();
+    let quantifier = lines.iter().find(|line| line.starts_with("  quantifier ")).unwrap();
+    assert_eq!(quoted(quantifier, "quantifier `"), "forall(|i: usize| i < n ==> i <= n)");
+    assert_eq!(&quantifier[quantifier.find("vars ").unwrap() + "vars ".len()..], "i");
+}
+
+#[test]
+fn spec_details_index_every_expression_span() {
+    let lines = dump_spec_details();
+    let index = lines.iter().find(|line| line.starts_with("index ")).unwrap();
+    let index = parse_spans(&index["index ".len()..]);
+    for line in expression_lines(&lines) {
+        for span in parse_spans(between(line, " at ", " (")) {
+            assert!(index.contains(&span), "{:?} of `{}` is not indexed", span, quoted(line, "expr `"));
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+#[requires(a > 0 && b > 0)]
+#[ensures(result == a + b)]
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[requires(forall(|i: usize| i < n ==> i <= n))]
+fn count(n: usize) -> usize {
+    n
+}
+
+fn main() {}
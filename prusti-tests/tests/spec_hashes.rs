@@ -0,0 +1,15 @@
+mod utils;
+
+/// Compiles `tests/spec_hashes/specs.rs` and returns the printed hashes.
+fn dump_spec_hashes() -> String {
+    utils::run_prusti_rustc(&["spec_hashes", "specs.rs"], &["-Zdump-spec-hashes", "-Zskip-verify"])
+}
+
+#[test]
+fn spec_hashes_are_stable_across_compilations() {
+    // Every compilation generates new (random) specification ids.
+    let first = dump_spec_hashes();
+    let second = dump_spec_hashes();
+    assert_eq!(first.lines().count(), 5, "unexpected hashes:\n{}", first);
+    assert_eq!(first, second);
+}
@@ -0,0 +1,28 @@
+use prusti_contracts::*;
+
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[pure]
+fn norm(p: &Point) -> i32 {
+    p.x + p.y
+}
+
+#[requires(a > 0 && b > 0)]
+#[ensures(result == a + b)]
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[requires(forall(|i: i32| forall(|j: i32| i + j == j + i)))]
+#[ensures(exists(|i: i32| i == norm(&p)))]
+fn quantified(p: Point) {}
+
+#[ensures(norm(&result) == norm(&Point { x: 1, y: 2 }))]
+fn origin() -> Point {
+    Point { x: 1, y: 2 }
+}
+
+fn main() {}
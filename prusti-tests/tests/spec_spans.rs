@@ -0,0 +1,51 @@
+mod utils;
+
+/// The byte ranges of the spans printed by `-Zdump-spec-spans`, ordered by
+/// position.
+fn parse_spans(output: &str) -> Vec<(u32, u32)> {
+    let mut spans: Vec<_> = output
+        .lines()
+        .flat_map(|line| line.splitn(2, ": ").nth(1).unwrap().split(", "))
+        .map(|range| {
+            let mut bounds = range.split("..").map(|bound| bound.parse().unwrap());
+            (bounds.next().unwrap(), bounds.next().unwrap())
+        })
+        .collect();
+    spans.sort();
+    spans
+}
+
+fn dump_spec_spans(extra_args: &[&str]) -> Vec<(u32, u32)> {
+    let mut args = vec!["-Zdump-spec-spans", "-Zskip-verify"];
+    args.extend(extra_args);
+    parse_spans(&utils::run_prusti_rustc(&["spec_spans", "specs.rs"], &args))
+}
+
+#[test]
+fn remapped_spec_spans_follow_an_insertion() {
+    let spans = dump_spec_spans(&[]);
+    assert_eq!(spans.len(), 5, "unexpected spans: {:?}", spans);
+    // Insert code right before the specifications of `max`.
+    let pos = spans[2].0;
+    let inserted = 17;
+    let remapped = dump_spec_spans(&[&format!("-Zremap-spec-spans={}:0:{}", pos, inserted)]);
+    let expected: Vec<_> = spans
+        .iter()
+        .map(|&(lo, hi)| if lo < pos { (lo, hi) } else { (lo + inserted, hi + inserted) })
+        .collect();
+    assert_eq!(remapped, expected);
+}
+
+#[test]
+fn remapped_spec_spans_follow_a_deletion() {
+    let spans = dump_spec_spans(&[]);
+    // Delete the code between the specifications of `add` and `max`.
+    let (pos, end) = (spans[1].1, spans[2].0);
+    let removed = end - pos;
+    let remapped = dump_spec_spans(&[&format!("-Zremap-spec-spans={}:{}:0", pos, removed)]);
+    let expected: Vec<_> = spans
+        .iter()
+        .map(|&(lo, hi)| if lo < pos { (lo, hi) } else { (lo - removed, hi - removed) })
+        .collect();
+    assert_eq!(remapped, expected);
+}
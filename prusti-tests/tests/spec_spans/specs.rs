@@ -0,0 +1,16 @@
+use prusti_contracts::*;
+
+#[requires(a > 0)]
+#[ensures(result == a + b)]
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[requires(a != b)]
+#[ensures(result >= a)]
+#[ensures(result >= b)]
+fn max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+// This test checks that the fields of a tuple result are checked to exist.
+
+use prusti_contracts::*;
+
+#[ensures(result.0 <= result.2)]  //~ ERROR no field `2` on type `(i32, i32)`
+pub fn min_max(a: i32, b: i32) -> (i32, i32) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+#[pure]
+fn id(x: u32) -> u32 {
+    x
+}
+
+#[ensures(forall(|y: u32| id(y) == y, triggers=[(id(y),)], weight=2))]
+fn test_with_triggers() {}
+
+#[ensures(forall(|y: u32| y + 1 > y, weight=1))]
+fn test_without_triggers() {}
+
+fn main() {}
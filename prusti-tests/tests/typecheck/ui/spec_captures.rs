@@ -0,0 +1,13 @@
+// compile-flags: -Zdump-spec-captures -Zskip-verify -Zhide-uuids
+// normalize-stdout-test: "[a-z0-9]{32}" -> "$(NUM_UUID)"
+// normalize-stdout-test: "[a-z0-9]{8}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{12}" -> "$(UUID)"
+
+use prusti_contracts::*;
+
+#[requires(x > 0)]
+#[ensures(result == x)]
+fn test(x: u32) -> u32 {
+    x
+}
+
+fn main() {}
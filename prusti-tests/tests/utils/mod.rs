@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+fn get_prusti_rustc_path() -> PathBuf {
+    let target_directory = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    let executable_name = if cfg!(windows) {
+        "prusti-rustc.exe"
+    } else {
+        "prusti-rustc"
+    };
+    let local_prusti_rustc_path: PathBuf = ["target", target_directory, executable_name].iter().collect();
+    if local_prusti_rustc_path.exists() {
+        return local_prusti_rustc_path;
+    }
+    let workspace_prusti_rustc_path: PathBuf = ["..", "target", target_directory, executable_name].iter().collect();
+    if workspace_prusti_rustc_path.exists() {
+        return workspace_prusti_rustc_path;
+    }
+    panic!("Could not find the prusti-rustc binary to be used in tests");
+}
+
+/// Runs prusti-rustc with `args` on the file `path` below `tests` and returns
+/// what it printed, panicking if the compilation fails.
+pub fn run_prusti_rustc(path: &[&str], args: &[&str]) -> String {
+    let output = Command::new(get_prusti_rustc_path())
+        .arg("--edition=2018")
+        .args(args)
+        .arg(std::iter::once("tests").chain(path.iter().copied()).collect::<PathBuf>())
+        .env("PRUSTI_QUIET", "true")
+        .output()
+        .expect("failed to run prusti-rustc");
+    assert!(
+        output.status.success(),
+        "prusti-rustc failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
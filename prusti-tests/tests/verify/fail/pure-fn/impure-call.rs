@@ -0,0 +1,9 @@
+use prusti_contracts::*;
+
+#[pure]
+fn foo(mut v: Vec<i32>) -> bool {
+    v.push(1); //~ ERROR use of impure function
+    true
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use prusti_contracts::*;
+
+struct Pair {
+    first: u32,
+    second: u32,
+}
+
+impl Pair {
+    #[pure]
+    fn first(&self) -> &u32 {
+        &self.first
+    }
+
+    #[ensures(*self.first() == old(*self.first()))] //~ ERROR postcondition might not hold
+    fn increment_first(&mut self) {
+        self.first += 1;
+    }
+}
+
+#[requires(*pair.first() == 5)]
+#[ensures(result == 6)] //~ ERROR postcondition might not hold
+fn call_first(pair: &Pair) -> u32 {
+    *pair.first()
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use prusti_contracts::*;
+
+#[ensures(forall(|i: usize| 0 <= i && i < n ==> i > 0))] //~ ERROR postcondition might not hold
+fn test(n: usize) {}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use prusti_contracts::*;
+
+#[requires(0 <= n < 100)]
+#[ensures(0 < result <= n)] //~ ERROR postcondition might not hold
+fn identity(n: usize) -> usize {
+    n
+}
+
+fn main() {}
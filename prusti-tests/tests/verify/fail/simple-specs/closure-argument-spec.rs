@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[requires(forall(|v: i32| f(v) >= v))] //~ ERROR calls of closures in specifications are not supported
+fn apply<F: Fn(i32) -> i32>(f: F, x: i32) -> i32 {
+    f(x)
+}
+
+#[ensures(result == f(x))] //~ ERROR calls of closures in specifications are not supported
+fn apply_ptr(f: fn(i32) -> i32, x: i32) -> i32 {
+    f(x)
+}
+
+fn main() {}
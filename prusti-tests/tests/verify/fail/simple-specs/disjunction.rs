@@ -0,0 +1,15 @@
+use prusti_contracts::*;
+
+#[ensures(result == a || result == b)] //~ ERROR postcondition might not hold
+fn max(a: i32, b: i32) -> i32 {
+    0
+}
+
+#[requires(x < 10 || x > 20)]
+fn outside(x: u32) {}
+
+fn test_outside() {
+    outside(15); //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use prusti_contracts::*;
+
+#[requires(x > 0)]
+#[requires(x < 100)]
+#[requires(x > 0)] //~ WARN duplicate precondition
+#[ensures(result == x + 1)]
+#[ensures(result > 1)]
+#[ensures(result == x + 1)] //~ WARN duplicate postcondition
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+#[requires(forall(|i: u32| i < x ==> i < 100))]
+#[requires(forall(|j: u32| j < x ==> j < 100))]
+#[requires(forall(|i: u32| i < x ==> i < 100))] //~ WARN duplicate precondition
+fn bounded(x: u32) {}
+
+#[ensures(result == x)]
+#[ensures(result == x && true)]
+fn identity(x: u32) -> u32 {
+    x
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use prusti_contracts::*;
+
+// Two hand-written specification items with the same id.
+#[allow(unused_must_use, unused_variables)]
+#[prusti::spec_only]
+#[prusti::spec_id = "6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b"]
+#[prusti::assertion = "{\"version\":2,\"assertion\":{\"kind\":{\"Expr\":{\"spec_id\":\"6b1e9d2c-4a7f-4e3b-9c8d-7a6f5e4d3c2b\",\"expr_id\":101,\"contains_old\":false}}}}"]
+fn prusti_pre_item_first_6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b() {
+    #[prusti::spec_only]
+    #[prusti::expr_id = "6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b_101"]
+    || -> bool { true };
+}
+
+#[allow(unused_must_use, unused_variables)]
+#[prusti::spec_only]
+#[prusti::spec_id = "6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b"]
+#[prusti::assertion = "{\"version\":2,\"assertion\":{\"kind\":{\"Expr\":{\"spec_id\":\"6b1e9d2c-4a7f-4e3b-9c8d-7a6f5e4d3c2b\",\"expr_id\":101,\"contains_old\":false}}}}"]
+fn prusti_pre_item_second_6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b() { //~ ERROR the specification id 6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b is used by several specification items
+    #[prusti::spec_only]
+    #[prusti::expr_id = "6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b_101"]
+    || -> bool { true };
+}
+
+#[prusti::pre_spec_id_ref = "6b1e9d2c4a7f4e3b9c8d7a6f5e4d3c2b"]
+fn test() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use prusti_contracts::*;
+
+#[ensures(exists(|y: i32| y != y))] //~ ERROR postcondition might not hold
+fn test() {}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use prusti_contracts::*;
+
+#[derive(Clone, Copy)]
+pub enum Light {
+    Red,
+    Yellow,
+    Green,
+}
+
+#[pure]
+fn is_red(l: Light) -> bool {
+    matches!(l, Light::Red)
+}
+
+#[ensures(forall(|l: Light| is_red(l) || !is_red(l)))] //~ ERROR can only be used to match its variant
+fn test() {}
+
+fn main() {}
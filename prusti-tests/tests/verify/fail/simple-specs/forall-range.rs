@@ -0,0 +1,6 @@
+use prusti_contracts::*;
+
+#[ensures(forall(|i: usize in 0..=n| i < n))] //~ ERROR postcondition might not hold
+fn test(n: usize) {}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use prusti_contracts::*;
+
+trait Container {
+    #[pure]
+    #[ensures(result > 0)]
+    fn size(&self) -> u32;
+}
+
+#[ensures(x.size() > 1)] //~ ERROR postcondition might not hold
+fn large_size<T: Container>(x: &T) {}
+
+fn main() {}
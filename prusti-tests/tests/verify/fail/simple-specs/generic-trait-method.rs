@@ -0,0 +1,15 @@
+use prusti_contracts::*;
+
+trait Valid {
+    #[pure]
+    fn valid(&self) -> bool;
+}
+
+#[requires(x.valid())]
+fn keep_valid<T: Valid>(x: &T) {}
+
+fn forward<T: Valid>(x: &T) {
+    keep_valid(x); //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[ghost]
+fn max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+#[ensures(result == max(a, b))]
+fn larger(a: i32, b: i32) -> i32 {
+    max(a, b) //~ ERROR ghost function `max` can only be used in specifications
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use prusti_contracts::*;
+
+#[ensures(result <==> x >= 0)] //~ ERROR postcondition might not hold
+fn is_positive(x: i32) -> bool {
+    x > 0
+}
+
+fn main() {}
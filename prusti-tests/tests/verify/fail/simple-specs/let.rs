@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    x * 2
+}
+
+#[requires(n > 0 && n < 1000)]
+#[ensures(let d: i32 = double(n) in d == n)] //~ ERROR postcondition might not hold
+fn test(n: i32) -> i32 {
+    n
+}
+
+fn main() {}
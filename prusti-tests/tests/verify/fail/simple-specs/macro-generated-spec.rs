@@ -0,0 +1,16 @@
+use prusti_contracts::*;
+
+macro_rules! positive_fn {
+    ($name:ident, $value:expr) => {
+        #[ensures(result > 0)]
+        fn $name() -> i32 {
+            $value
+        }
+    };
+}
+
+positive_fn!(one, 1);
+
+positive_fn!(zero, 0); //~ ERROR postcondition might not hold
+
+fn main() {}
@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+#[derive(Clone, Copy)]
+pub enum State {
+    Open,
+    Closed,
+}
+
+#[ensures(matches!(result, State::Open))] //~ ERROR postcondition might not hold
+fn close() -> State {
+    State::Closed
+}
+
+fn main() {}
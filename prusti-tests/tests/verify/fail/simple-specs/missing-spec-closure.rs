@@ -0,0 +1,18 @@
+use prusti_contracts::*;
+
+// A specification item whose assertion refers to an expression without a
+// type-checked closure, as if the closure had failed to compile.
+#[allow(unused_must_use, unused_variables)]
+#[prusti::spec_only]
+#[prusti::spec_id = "2f5d3c0a9b8e4d7c8a6b5e4d3c2b1a09"]
+#[prusti::assertion = "{\"version\":2,\"assertion\":{\"kind\":{\"Expr\":{\"spec_id\":\"2f5d3c0a-9b8e-4d7c-8a6b-5e4d3c2b1a09\",\"expr_id\":101,\"contains_old\":false}}}}"]
+fn prusti_pre_item_test_2f5d3c0a9b8e4d7c8a6b5e4d3c2b1a09() { //~ ERROR cannot find the type-checked closure of expression 101
+    #[prusti::spec_only]
+    #[prusti::expr_id = "2f5d3c0a9b8e4d7c8a6b5e4d3c2b1a09_102"]
+    || -> bool { true };
+}
+
+#[prusti::pre_spec_id_ref = "2f5d3c0a9b8e4d7c8a6b5e4d3c2b1a09"]
+fn test() {}
+
+fn main() {}
@@ -0,0 +1,9 @@
+// rustc-env:PRUSTI_WARN_MISSING_TRIGGERS=true
+
+use prusti_contracts::*;
+
+#[requires(forall(|a: i32| a + 1 > a))] //~ WARN the quantifier has no triggers
+#[ensures(false)] //~ ERROR postcondition might not hold
+fn test() {}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use prusti_contracts::*;
+
+struct Counter {
+    count: u32,
+}
+
+struct Inner {
+    counter: Counter,
+}
+
+struct Outer {
+    inner: Inner,
+}
+
+impl Outer {
+    #[ensures(self.inner.counter.count == old(self.inner.counter.count))] //~ ERROR postcondition might not hold
+    fn reset(&mut self) {
+        self.inner.counter.count = 0;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use prusti_contracts::*;
+
+#[ensures(old(result) == x)] //~ ERROR `old(result)` is not allowed
+fn test1(x: i32) -> i32 {
+    x
+}
+
+#[ensures(result == old(x) && old(result + 1) > x)] //~ ERROR `old(result)` is not allowed
+fn test2(x: i32) -> i32 {
+    x
+}
+
+#[ensures(result == old(x))]
+fn test3(x: i32) -> i32 {
+    x
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[ensures(result.is_some())] //~ ERROR postcondition might not hold
+fn nothing() -> Option<u32> {
+    None
+}
+
+#[ensures(result.is_ok() ==> result.unwrap() > 0)] //~ ERROR postcondition might not hold
+fn zero() -> Result<u32, u32> {
+    Ok(0)
+}
+
+fn main() {}
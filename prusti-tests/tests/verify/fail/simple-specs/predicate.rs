@@ -0,0 +1,21 @@
+use prusti_contracts::*;
+
+predicate! {
+    fn is_positive(x: i32) -> bool {
+        x > 0
+    }
+}
+
+#[requires(is_positive(x))]
+fn consume(x: i32) {}
+
+#[ensures(is_positive(result))] //~ ERROR postcondition might not hold
+fn zero() -> i32 {
+    0
+}
+
+fn test() {
+    consume(0); //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use prusti_contracts::*;
+
+#[requires(x < 10)]
+fn test1(x: u32) {
+    prusti_assert!(x < 10);
+    prusti_assert!(x < 5); //~ ERROR the asserted expression might not hold
+}
+
+fn test2(n: u32) {
+    let mut i = 0;
+    let mut sum = 0;
+    while i < n {
+        body_invariant!(i < n);
+        sum += 1;
+        prusti_assert!(sum == i + 1); //~ ERROR the asserted expression might not hold
+        i += 1;
+    }
+}
+
+fn main() {}
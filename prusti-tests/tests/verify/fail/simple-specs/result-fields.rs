@@ -0,0 +1,17 @@
+use prusti_contracts::*;
+
+struct Counter {
+    count: u32,
+}
+
+#[ensures(result.count == 1)] //~ ERROR postcondition might not hold
+fn new_counter() -> Counter {
+    Counter { count: 0 }
+}
+
+#[ensures(result.1 == x)] //~ ERROR postcondition might not hold
+fn pair(x: i32, y: i32) -> (i32, i32) {
+    (x, y)
+}
+
+fn main() {}
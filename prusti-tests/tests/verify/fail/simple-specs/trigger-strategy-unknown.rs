@@ -0,0 +1,13 @@
+// rustc-env:PRUSTI_TRIGGER_STRATEGY=eager
+
+use prusti_contracts::*;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    2 * x
+}
+
+#[requires(forall(|x: i32| double(x) == 2 * x))] //~ ERROR unknown trigger strategy `eager`
+fn test() {}
+
+fn main() {}
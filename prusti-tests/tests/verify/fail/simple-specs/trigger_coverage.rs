@@ -0,0 +1,26 @@
+use prusti_contracts::*;
+
+#[requires(forall(|a: i32, b: i32| a + b == b + a, triggers=[(f(a, a),)]))] //~ ERROR a trigger must mention all quantified variables
+fn test1() {}
+
+#[requires(forall(|a: i32| forall(|b: i32| a + b == b + a, triggers=[(f(a, a),)])))] //~ ERROR a trigger must mention all quantified variables
+fn test2() {}
+
+#[pure]
+fn f(a: i32, b: i32) -> bool {
+    true
+}
+
+// Triggers may mention the arguments of the function and the variables bound by
+// enclosing quantifiers and `let`s.
+
+#[requires(forall(|a: i32| f(a, x), triggers=[(f(a, x),)]))]
+fn test3(x: i32) {}
+
+#[requires(forall(|a: i32| forall(|b: i32| f(a, b), triggers=[(f(a, b),)])))]
+fn test4() {}
+
+#[requires(let y: i32 = x + 1 in forall(|a: i32| f(a, y), triggers=[(f(a, y),)]))]
+fn test5(x: i32) {}
+
+fn main() {}
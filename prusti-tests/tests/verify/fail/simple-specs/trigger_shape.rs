@@ -0,0 +1,20 @@
+use prusti_contracts::*;
+
+#[pure]
+fn f(a: i32) -> bool {
+    true
+}
+
+#[requires(forall(|a: i32| f(a), triggers=[(a + 1,)]))] //~ ERROR a trigger term must be a function call
+fn test1() {}
+
+#[requires(forall(|a: i32| f(a), triggers=[(a,)]))] //~ ERROR a trigger term must be a function call
+fn test2() {}
+
+#[requires(forall(|a: i32| f(a) && f(a + 1), triggers=[(f(a),), (f(a + 1),)]))]
+fn test3() {}
+
+#[requires(forall(|i: usize| i < s.len() ==> s[i] > 0, triggers=[(s[i],)]))]
+fn test4(s: &[i32]) {}
+
+fn main() {}
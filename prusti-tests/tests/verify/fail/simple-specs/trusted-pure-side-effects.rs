@@ -0,0 +1,51 @@
+use prusti_contracts::*;
+
+struct Counter {
+    value: u32,
+    history: Vec<u32>,
+}
+
+impl Counter {
+    #[trusted]
+    fn reset(&mut self) {
+        self.value = 0;
+    }
+
+    #[pure]
+    #[trusted]
+    fn get(&self) -> u32 {
+        self.value
+    }
+
+    #[pure]
+    #[trusted]
+    fn get_and_increment(&mut self) -> u32 { //~ WARN the trusted pure function `Counter::get_and_increment` has side effects
+        self.value += 1;
+        self.value
+    }
+
+    #[pure]
+    #[trusted]
+    fn get_and_record(&mut self) -> u32 { //~ WARN the trusted pure function `Counter::get_and_record` has side effects
+        self.history.push(self.value);
+        self.value
+    }
+
+    #[pure]
+    #[trusted]
+    fn get_and_reset(&mut self) -> u32 { //~ WARN the trusted pure function `Counter::get_and_reset` has side effects
+        let value = self.value;
+        self.reset();
+        value
+    }
+}
+
+#[pure]
+#[trusted]
+fn local_mutation(x: u32) -> u32 {
+    let mut y = x;
+    y += 1;
+    y
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use prusti_contracts::*;
+
+#[trusted]
+#[ensures(type_cond(u32 == i32, result))] //~ ERROR the type condition `u32 == i32` does not mention a type parameter of the function
+fn is_i32(_value: u32) -> bool {
+    unimplemented!()
+}
+
+#[trusted]
+#[ensures(type_cond(T == i32, result))]
+fn is_generic_i32<T>(_value: T) -> bool {
+    unimplemented!()
+}
+
+fn main() {}
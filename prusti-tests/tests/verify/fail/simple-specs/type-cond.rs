@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[trusted]
+#[ensures(type_cond(T == i32, result))]
+fn is_i32<T>(_value: T) -> bool {
+    unimplemented!()
+}
+
+fn test() {
+    assert!(is_i32(5u32)); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+// rustc-env:PRUSTI_WARN_UNGUARDED_SUBTRACTIONS=true
+
+use prusti_contracts::*;
+
+#[ensures(result == x - 1)] //~ WARN the subtraction may underflow
+fn dec(x: u32) -> u32 {
+    x - 1
+}
+
+#[requires(x > 0)]
+#[ensures(result == x - 1)]
+fn dec_precondition(x: u32) -> u32 {
+    x - 1
+}
+
+#[ensures(x > 0 ==> result == x - 1)]
+#[ensures(x >= 1 && result == x - 1 || x == 0)]
+fn dec_implication(x: u32) -> u32 {
+    if x > 0 { x - 1 } else { 0 }
+}
+
+#[ensures(result == x - 0)]
+#[ensures(result - 0 == x)]
+fn sub_zero(x: u32) -> u32 {
+    x
+}
+
+#[ensures(false)] //~ ERROR postcondition might not hold
+fn test() {}
+
+fn main() {}
@@ -0,0 +1,62 @@
+//! The `doors1` example of `rosetta/100_doors.rs`, with the loops written as
+//! `loop {}` and the invariants placed before the loop exit.
+
+use prusti_contracts::*;
+
+pub struct VecWrapperBool{
+    v: Vec<bool>
+}
+
+impl VecWrapperBool {
+    #[trusted]
+    #[pure]
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    #[trusted]
+    #[ensures(result.len() == size)]
+    pub fn new(value: bool, size: usize) -> Self {
+        VecWrapperBool{ v: vec![value; size] }
+    }
+
+    #[trusted]
+    #[pure]
+    #[requires(0 <= index && index < self.len())]
+    pub fn lookup(&self, index: usize) -> bool {
+        self.v[index]
+    }
+
+    #[trusted]
+    #[requires(0 <= index && index < self.len())]
+    #[ensures(self.len() == old(self.len()))]
+    pub fn store(&mut self, index: usize, value: bool) {
+        self.v[index] = value;
+    }
+}
+
+fn doors1() {
+    let mut door_open = VecWrapperBool::new(false, 100);
+    let mut pass = 1;
+    loop {
+        body_invariant!(1 <= pass && pass <= 100);
+        body_invariant!(door_open.len() == 100);
+        if pass >= 100 {
+            break;
+        }
+        let mut door = pass;
+        loop {
+            body_invariant!(1 <= door);
+            body_invariant!(door_open.len() == 100);
+            if door > 100 {
+                break;
+            }
+            let door_state = door_open.lookup(door - 1);
+            door_open.store(door - 1, !door_state);
+            door += pass;
+        }
+        pass += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use prusti_contracts::*;
+
+#[pure]
+#[requires(n >= 0)]
+#[ensures(result)]
+fn reaches_zero(n: i32) -> bool {
+    n == 0 || reaches_zero(n - 1)
+}
+
+#[pure]
+#[requires(n >= 0)]
+#[ensures(result)]
+fn reaches_zero_with_let(n: i32) -> bool {
+    let m = n - 1;
+    n == 0 || reaches_zero_with_let(m)
+}
+
+fn test(n: i32) {
+    if n >= 0 {
+        assert!(reaches_zero(n));
+        assert!(reaches_zero_with_let(n));
+    }
+}
+
+fn main() {}
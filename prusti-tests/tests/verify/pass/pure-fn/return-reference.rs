@@ -0,0 +1,44 @@
+use prusti_contracts::*;
+
+struct Pair {
+    first: u32,
+    second: u32,
+}
+
+impl Pair {
+    #[pure]
+    fn first(&self) -> &u32 {
+        &self.first
+    }
+
+    #[ensures(*self.first() == old(*self.first()) + 1)]
+    fn increment_first(&mut self) {
+        self.first += 1;
+    }
+
+    #[ensures(*result == *self.first())]
+    fn get_first(&self) -> &u32 {
+        &self.first
+    }
+}
+
+#[requires(*pair.first() == 5)]
+#[ensures(result == 5)]
+fn read_first(pair: &Pair) -> u32 {
+    pair.first
+}
+
+#[requires(*pair.first() == 5)]
+#[ensures(result == 5)]
+fn call_first(pair: &Pair) -> u32 {
+    *pair.first()
+}
+
+#[ensures(result == *pair.first())]
+fn call_first_twice(pair: &Pair) -> u32 {
+    let first = pair.first();
+    assert!(*first == *pair.first());
+    *first
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use prusti_contracts::*;
+
+struct Buffer {
+    len: usize,
+}
+
+impl Buffer {
+    const CAPACITY: usize = 16;
+
+    #[requires(self.len < Self::CAPACITY)]
+    #[ensures(self.len <= Self::CAPACITY)]
+    fn push(&mut self) {
+        self.len += 1;
+    }
+}
+
+fn main() {}
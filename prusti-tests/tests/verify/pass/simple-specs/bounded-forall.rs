@@ -0,0 +1,7 @@
+use prusti_contracts::*;
+
+#[requires(forall(|i: usize| 0 <= i && i < n ==> i < n + 1))]
+#[ensures(forall(|i: usize| 1 <= i && i <= n ==> i > 0))]
+fn test(n: usize) {}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use prusti_contracts::*;
+
+#[requires(0 < n <= 100)]
+#[ensures(0 <= result < n)]
+fn last(n: usize) -> usize {
+    n - 1
+}
+
+#[requires(a <= b <= c)]
+#[ensures(a <= c)]
+fn transitive(a: i32, b: i32, c: i32) {}
+
+#[ensures(forall(|i: usize| 0 <= i < 10 ==> i != 10))]
+fn quantified() {}
+
+#[ensures((a < b) == (b > a))]
+fn parenthesized(a: i32, b: i32) {}
+
+fn test(n: usize) {
+    let mut i = 0;
+    while i < n {
+        body_invariant!(0 <= i < n);
+        i += 1;
+    }
+}
+
+fn main() {}
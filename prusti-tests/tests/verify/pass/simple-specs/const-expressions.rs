@@ -0,0 +1,17 @@
+use prusti_contracts::*;
+
+const ENABLED: bool = true;
+
+#[requires(true)]
+#[requires(ENABLED)]
+#[ensures(true && result == x)]
+fn test(x: i32) -> i32 {
+    x
+}
+
+#[requires(false)]
+fn unreachable() {
+    assert!(false);
+}
+
+fn main() {}
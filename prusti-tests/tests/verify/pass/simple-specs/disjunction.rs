@@ -0,0 +1,26 @@
+use prusti_contracts::*;
+
+#[ensures(result == a || result == b)]
+#[ensures(result >= a && result >= b)]
+fn max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+#[ensures(x == 0 || (x > 0 ==> result == x) || 0 < x < 100)]
+fn clamp(x: i32) -> i32 {
+    if x > 0 { x } else { 0 }
+}
+
+#[requires(x < 10 || x > 20)]
+#[ensures(result != 15)]
+fn outside(x: u32) -> u32 {
+    x
+}
+
+fn test_outside() {
+    let z = max(3, 4);
+    assert!(z == 4);
+    outside(25);
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use prusti_contracts::*;
+
+#[ensures(exists(|y: u32| y == x))]
+fn test1(x: u32) {}
+
+#[ensures(exists(|a: i32, b: i32| a + b == 0))]
+fn test2() {}
+
+#[ensures(forall(|y: u32| exists(|z: u32| z == y)))]
+fn test3() {}
+
+fn main() {}
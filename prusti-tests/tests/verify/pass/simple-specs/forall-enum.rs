@@ -0,0 +1,16 @@
+use prusti_contracts::*;
+
+#[derive(Clone, Copy)]
+pub enum Light {
+    Red,
+    Yellow,
+    Green,
+}
+
+#[ensures(forall(|l: Light| matches!(l, Light::Red) || matches!(l, Light::Yellow) || matches!(l, Light::Green)))]
+fn test() {}
+
+#[ensures(forall(|l: Light, x: u32| x >= 0 || matches!(l, Light::Red)))]
+fn test_mixed() {}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use prusti_contracts::*;
+
+#[trusted]
+#[pure]
+fn is_valid(x: usize) -> bool {
+    x < 100
+}
+
+// The bounded quantifier is instantiated through its call to `is_valid`.
+#[requires(n > 3)]
+#[requires(forall(|i: usize in 0..n| is_valid(i)))]
+#[ensures(is_valid(2) && is_valid(n - 1))]
+fn test(n: usize) {}
+
+fn main() {}
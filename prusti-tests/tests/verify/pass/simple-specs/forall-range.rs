@@ -0,0 +1,8 @@
+use prusti_contracts::*;
+
+#[requires(forall(|i: usize in 0..n| i < n + 1))]
+#[ensures(forall(|i: usize in 1..=n| i > 0))]
+#[ensures(exists(|i: usize in 0..n + 1| i == n))]
+fn test(n: usize) {}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use prusti_contracts::*;
+
+#[ensures(forall(|y: u32| y <= y, weight=1))]
+fn test1() {}
+
+#[requires(forall(|y: u32| y > x ==> y > 0, weight=5))]
+#[ensures(exists(|y: u32| y == x, weight=2))]
+fn test2(x: u32) {}
+
+#[ensures(forall(|i: usize in 0..n| i < n, weight=3))]
+fn test3(n: usize) {}
+
+fn main() {}
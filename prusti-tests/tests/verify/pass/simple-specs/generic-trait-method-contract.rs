@@ -0,0 +1,20 @@
+use prusti_contracts::*;
+
+trait Container {
+    #[pure]
+    #[ensures(result > 0)]
+    fn size(&self) -> u32;
+
+    #[pure]
+    #[requires(index < self.size())]
+    fn weight(&self, index: u32) -> u32;
+}
+
+#[ensures(x.size() > 0)]
+fn positive_size<T: Container>(x: &T) {}
+
+#[requires(x.weight(0) > 2)]
+#[ensures(x.weight(0) > 1)]
+fn first_weight<T: Container>(x: &T) {}
+
+fn main() {}
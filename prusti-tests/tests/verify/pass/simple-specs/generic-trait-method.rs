@@ -0,0 +1,34 @@
+use prusti_contracts::*;
+
+trait Valid {
+    #[pure]
+    fn valid(&self) -> bool;
+}
+
+struct Counter {
+    value: u32,
+}
+
+impl Valid for Counter {
+    #[pure]
+    fn valid(&self) -> bool {
+        self.value > 0
+    }
+}
+
+#[requires(x.valid())]
+#[ensures(x.valid())]
+fn keep_valid<T: Valid>(x: &T) {}
+
+#[requires(x.valid())]
+fn forward<T: Valid>(x: &T) {
+    keep_valid(x);
+    assert!(x.valid());
+}
+
+#[requires(counter.valid())]
+fn use_counter(counter: &Counter) {
+    assert!(counter.value > 0);
+}
+
+fn main() {}
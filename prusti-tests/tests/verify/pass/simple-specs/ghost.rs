@@ -0,0 +1,32 @@
+use prusti_contracts::*;
+
+struct Doors {
+    open: u32,
+    closed: u32,
+}
+
+impl Doors {
+    #[ghost]
+    fn total(&self) -> u32 {
+        self.open + self.closed
+    }
+
+    #[requires(self.total() <= 100 && self.closed > 0)]
+    #[ensures(self.total() <= 100)]
+    fn open_one(&mut self) {
+        self.closed -= 1;
+        self.open += 1;
+    }
+}
+
+#[ghost]
+fn max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+#[ensures(result == max(a, b))]
+fn larger(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use prusti_contracts::*;
+
+#[ensures(result <==> x > 0)]
+fn is_positive(x: i32) -> bool {
+    x > 0
+}
+
+#[ensures(result <==> (x > 0 && y > 0))]
+fn both_positive(x: i32, y: i32) -> bool {
+    is_positive(x) && is_positive(y)
+}
+
+#[requires(x > 0)]
+#[ensures(x > 0 ==> result <==> true)]
+fn always_positive(x: i32) -> bool {
+    is_positive(x)
+}
+
+fn main() {}
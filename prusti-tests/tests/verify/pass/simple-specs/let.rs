@@ -0,0 +1,29 @@
+use prusti_contracts::*;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    x * 2
+}
+
+#[requires(n > 0 && n < 1000)]
+#[ensures(let d: i32 = double(n) in d > n && d == result)]
+fn test1(n: i32) -> i32 {
+    double(n)
+}
+
+#[requires(n < 1000)]
+#[ensures(forall(|i: i32| 0 <= i && i < n ==> let d: i32 = double(i) in d >= i && d < 2 * n))]
+fn test2(n: i32) {}
+
+#[ensures(let positive: bool = n > 0 in positive ==> result == n)]
+#[ensures(let positive: bool = n > 0 in !positive ==> result == 0)]
+fn test3(n: i32) -> i32 {
+    if n > 0 { n } else { 0 }
+}
+
+#[ensures(let c: char = 'a' in result == c)]
+fn test4() -> char {
+    'a'
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use prusti_contracts::*;
+
+#[derive(Clone, Copy)]
+pub enum State {
+    Open,
+    Closed(u32),
+}
+
+pub struct Door {
+    state: State,
+}
+
+impl Door {
+    #[requires(matches!(self.state, State::Open))]
+    #[ensures(matches!(self.state, State::Closed(_)))]
+    pub fn close(&mut self) {
+        self.state = State::Closed(0);
+    }
+}
+
+#[ensures(x > 0 ==> matches!(result, Some(_)))]
+fn test(x: u32) -> Option<u32> {
+    Some(x)
+}
+
+fn main() {}
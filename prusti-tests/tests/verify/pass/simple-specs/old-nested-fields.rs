@@ -0,0 +1,31 @@
+use prusti_contracts::*;
+
+struct Counter {
+    count: u32,
+}
+
+struct Inner {
+    counter: Counter,
+    limit: u32,
+}
+
+struct Outer {
+    inner: Inner,
+}
+
+impl Outer {
+    #[requires(self.inner.counter.count < self.inner.limit)]
+    #[ensures(self.inner.counter.count == old(self.inner.counter.count) + 1)]
+    #[ensures(self.inner.limit == old(self.inner.limit))]
+    fn increment(&mut self) {
+        self.inner.counter.count += 1;
+    }
+}
+
+#[requires(outer.inner.counter.count < outer.inner.limit)]
+#[ensures(outer.inner.counter.count == old(outer.inner.counter.count) + 1)]
+fn increment(outer: &mut Outer) {
+    outer.increment();
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use prusti_contracts::*;
+
+#[ensures(x > 0 ==> result.is_some())]
+#[ensures(x == 0 ==> result.is_none())]
+#[ensures(result.is_some() ==> result.unwrap() == x)]
+fn positive(x: u32) -> Option<u32> {
+    if x > 0 {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+#[ensures(x < 10 ==> result.is_ok())]
+#[ensures(result.is_err() ==> x >= 10)]
+#[ensures(result.is_ok() ==> result.unwrap() == x)]
+fn small(x: u32) -> Result<u32, u32> {
+    if x < 10 {
+        Ok(x)
+    } else {
+        Err(x)
+    }
+}
+
+fn main() {}
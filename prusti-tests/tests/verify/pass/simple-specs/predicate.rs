@@ -0,0 +1,43 @@
+use prusti_contracts::*;
+
+predicate! {
+    fn is_positive(x: i32) -> bool {
+        x > 0
+    }
+}
+
+predicate! {
+    fn both_positive(x: i32, y: i32) -> bool {
+        is_positive(x) && is_positive(y)
+    }
+}
+
+predicate! {
+    fn below(n: u32, m: u32) -> bool {
+        forall(|i: u32| i < n ==> i < m)
+    }
+}
+
+#[requires(is_positive(x))]
+#[ensures(is_positive(result))]
+fn identity(x: i32) -> i32 {
+    x
+}
+
+#[requires(both_positive(x, y))]
+#[ensures(is_positive(result))]
+fn first(x: i32, y: i32) -> i32 {
+    identity(x)
+}
+
+#[requires(n <= m)]
+#[ensures(below(n, m))]
+fn within(n: u32, m: u32) {}
+
+fn test() {
+    let a = identity(3);
+    assert!(a > 0);
+    first(a, 4);
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use prusti_contracts::*;
+
+#[requires(x < 10)]
+#[ensures(result < 20)]
+fn test1(x: u32) -> u32 {
+    prusti_assert!(x < 10);
+    let y = x + x;
+    prusti_assert!(y == 2 * x);
+    y
+}
+
+fn test2(n: u32) {
+    let mut i = 0;
+    let mut sum = 0;
+    while i < n {
+        body_invariant!(i < n && sum == i);
+        sum += 1;
+        prusti_assert!(sum == i + 1);
+        i += 1;
+    }
+}
+
+fn main() {}
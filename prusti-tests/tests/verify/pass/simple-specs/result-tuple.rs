@@ -0,0 +1,14 @@
+use prusti_contracts::*;
+
+#[ensures(result.0 <= result.1)]
+#[ensures(result.0 == a || result.0 == b)]
+#[ensures(result.1 == a || result.1 == b)]
+fn min_max(a: i32, b: i32) -> (i32, i32) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+// rustc-env:PRUSTI_TRIGGER_STRATEGY=aggressive
+
+use prusti_contracts::*;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    2 * x
+}
+
+#[pure]
+fn is_even(x: i32) -> bool {
+    x % 2 == 0
+}
+
+#[requires(forall(|x: i32| is_even(double(x))))]
+#[ensures(is_even(double(a)))]
+fn test(a: i32) {}
+
+fn main() {}
@@ -0,0 +1,16 @@
+// rustc-env:PRUSTI_TRIGGER_STRATEGY=shallow
+
+use prusti_contracts::*;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    2 * x
+}
+
+#[requires(forall(|x: i32| double(x) == 2 * x))]
+#[ensures(exists(|x: i32| double(x) == 2 * a))]
+fn test(a: i32) {
+    assert!(double(a) == 2 * a);
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use prusti_contracts::*;
+
+#[ensures(x > 5 ==> result > 10)]
+fn client(x: u32) -> u32 { x }
+
+fn main() {}
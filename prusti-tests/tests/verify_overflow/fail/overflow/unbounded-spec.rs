@@ -0,0 +1,6 @@
+use prusti_contracts::*;
+
+#[ensures(a + b >= a)] //~ ERROR postcondition might not hold
+fn sum_is_larger(a: u8, b: u8) {}
+
+fn main() {}
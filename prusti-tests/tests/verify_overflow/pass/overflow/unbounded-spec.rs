@@ -0,0 +1,12 @@
+use prusti_contracts::*;
+
+#[ensures(#[unbounded] a + b >= a)]
+fn sum_is_larger(a: u8, b: u8) {}
+
+#[requires(#[unbounded] a + b <= 255)]
+#[ensures(#[unbounded] result == a + b)]
+fn add(a: u8, b: u8) -> u8 {
+    a + b
+}
+
+fn main() {}
@@ -115,7 +115,7 @@ impl<'tcx, L: fmt::Debug, P: fmt::Debug> ProcedureContractGeneric<'tcx, L, P> {
 
     pub fn pledges(&self) -> &[typed::Pledge<'tcx>] {
         if let typed::SpecificationSet::Procedure(spec) = &self.specification {
-            &spec.pledges
+            spec.pledges()
         } else {
             unreachable!("Unexpected: {:?}", self.specification)
         }
@@ -13,14 +13,15 @@ use crate::encoder::errors::{ErrorCtxt, ErrorManager, EncodingError};
 use crate::encoder::foldunfold;
 use crate::encoder::places;
 use crate::encoder::procedure_encoder::ProcedureEncoder;
-use crate::encoder::pure_function_encoder::PureFunctionEncoder;
+use crate::encoder::pure_function_encoder::{encode_trait_method_declaration, PureFunctionEncoder};
 use crate::encoder::stub_function_encoder::StubFunctionEncoder;
 use crate::encoder::spec_encoder::encode_spec_assertion;
+use crate::encoder::spec_resolver::SpecResolver;
 use crate::encoder::snapshot_encoder::{Snapshot, SnapshotEncoder};
 use crate::encoder::type_encoder::{
     compute_discriminant_values, compute_discriminant_bounds, TypeEncoder};
 use prusti_common::vir;
-use prusti_common::vir::WithIdentifier;
+use prusti_common::vir::{ExprIterator, WithIdentifier};
 use prusti_common::config;
 use prusti_common::report::log;
 // use prusti_interface::constants::PRUSTI_SPEC_ATTR;
@@ -28,7 +29,7 @@ use prusti_interface::data::ProcedureDefId;
 use prusti_interface::environment::Environment;
 use prusti_interface::specs::typed;
 use prusti_interface::specs::typed::SpecificationId;
-use prusti_interface::utils::{has_spec_only_attr, read_prusti_attrs, has_prusti_attr};
+use prusti_interface::utils::{has_spec_only_attr, read_prusti_attr, read_prusti_attrs, read_prusti_attrs_with_spans, has_prusti_attr};
 use prusti_interface::PrustiError;
 // use prusti_interface::specs::{
 //     SpecID, SpecificationSet, TypedAssertion,
@@ -40,6 +41,7 @@ use rustc_hir::def_id::DefId;
 use rustc_middle::mir;
 // use rustc::mir::interpret::GlobalId;
 use rustc_middle::ty;
+use rustc_middle::ty::TypeFoldable;
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::io::Write;
@@ -53,22 +55,13 @@ use ::log::info;
 use std::convert::TryInto;
 use std::borrow::Borrow;
 
-/// A reference to a procedure specification.
-///
-/// TODO: Move this type and the associated functions into a separate file.
-#[derive(Debug)]
-enum SpecIdRef {
-    Precondition(SpecificationId),
-    Postcondition(SpecificationId),
-    Pledge { lhs: Option<SpecificationId>, rhs: SpecificationId },
-}
-
 const SNAPSHOT_MIRROR_DOMAIN: &str = "$SnapshotMirrors$";
 
 pub struct Encoder<'v, 'tcx: 'v> {
     env: &'v Environment<'tcx>,
     spec: &'v typed::SpecificationMap<'tcx>,
     extern_spec: &'v typed::ExternSpecificationMap<'tcx>,
+    spec_resolver: SpecResolver<'v, 'tcx>,
     error_manager: RefCell<ErrorManager<'tcx>>,
     procedure_contracts: RefCell<HashMap<ProcedureDefId, ProcedureContractMirDef<'tcx>>>,
     builtin_methods: RefCell<HashMap<BuiltinMethodKind, vir::BodylessMethod>>,
@@ -135,6 +128,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             env,
             spec,
             extern_spec,
+            spec_resolver: SpecResolver::new(env, spec, extern_spec),
             error_manager: RefCell::new(ErrorManager::new(env.codemap())),
             procedure_contracts: RefCell::new(HashMap::new()),
             builtin_methods: RefCell::new(HashMap::new()),
@@ -207,6 +201,11 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         self.spec
     }
 
+    /// Returns the span of the external specification of `def_id`, if any.
+    pub fn extern_spec_span(&self, def_id: ProcedureDefId) -> Option<rustc_span::Span> {
+        self.extern_spec.get(&def_id).map(|(_, _, span)| *span)
+    }
+
     /// Returns the def_id of the element containing the specifications.
     /// This can be different from the def_id that was passed in if the
     /// specifications were externally declared.
@@ -215,13 +214,9 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             self.get_procedure_specs(*def_id).is_some() {
             self.register_encoding_error(EncodingError::Incorrect(
                 format!("external specification found for already specified function"),
-                rustc_span::MultiSpan::from_span(self.env.tcx().def_span(self.extern_spec.get(def_id).unwrap().1))));
-        }
-        if (self.extern_spec.contains_key(def_id)) {
-            &self.extern_spec.get(def_id).unwrap().1
-        } else {
-            def_id
+                rustc_span::MultiSpan::from_span(self.extern_spec_span(*def_id).unwrap())));
         }
+        self.spec_resolver.get_specification_def_id(def_id)
     }
 
     pub fn error_manager(&self) -> RefMut<ErrorManager<'tcx>> {
@@ -455,49 +450,39 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         has_spec_only_attr(self.env().tcx().get_attrs(def_id))
     }
 
-    /// Return the specification ids that are attached to `def_id` with one of the following
-    /// attributes:
-    /// * `prusti::pre_spec_id_ref="..."` for preconditions,
-    /// * `prusti::post_spec_id_ref="..."` for postconditions,
-    /// * `prusti::pledge_spec_id_ref="..."` for pledges.
-    fn get_procedure_spec_ids(&self, def_id: DefId) -> Vec<SpecIdRef> {
-        let mut spec_id_refs = vec![];
+    /// Return the kind and the span of every specification attribute attached to
+    /// `def_id`, in declaration order. The span is the one of the specification
+    /// macro (e.g. the `#[requires(..)]` attribute) and can be used to point at
+    /// the declaration of a specification in an error message, while the
+    /// `Spanned` trait gives the spans of the body of the assertion.
+    ///
+    /// The spans are not stored in `ProcedureSpecification` because that type
+    /// is shared with `prusti-specs`, which does not depend on `rustc_span`.
+    pub fn get_procedure_spec_spans(&self, def_id: DefId) -> Vec<(typed::SpecType, rustc_span::Span)> {
         let attrs = self.env().tcx().get_attrs(def_id);
-
-        let parse_spec_id = |spec_id: String| -> SpecificationId {
-            spec_id.try_into().expect(
-                &format!("cannot parse the spec_id attached to {:?}", def_id)
+        let mut spec_spans = vec![];
+        spec_spans.extend(
+            read_prusti_attrs_with_spans("pre_spec_id_ref", attrs).into_iter().map(
+                |(_, span)| (typed::SpecType::Precondition, span)
             )
-        };
-
-        spec_id_refs.extend(
-            read_prusti_attrs("pre_spec_id_ref", attrs).into_iter().map(
-                |raw_spec_id| SpecIdRef::Precondition(parse_spec_id(raw_spec_id))
+        );
+        spec_spans.extend(
+            read_prusti_attrs_with_spans("post_spec_id_ref", attrs).into_iter().map(
+                |(_, span)| (typed::SpecType::Postcondition, span)
             )
         );
-        spec_id_refs.extend(
-            read_prusti_attrs("post_spec_id_ref", attrs).into_iter().map(
-                |raw_spec_id| SpecIdRef::Postcondition(parse_spec_id(raw_spec_id))
+        spec_spans.extend(
+            read_prusti_attrs_with_spans("pred_spec_id_ref", attrs).into_iter().map(
+                |(_, span)| (typed::SpecType::Predicate, span)
             )
         );
-        spec_id_refs.extend(
-            read_prusti_attrs("pledge_spec_id_ref", attrs).into_iter().map(
-                |value| {
-                    let mut value = value.splitn(2, ":");
-                    let raw_lhs_spec_id = value.next().unwrap();
-                    let raw_rhs_spec_id = value.next().unwrap();
-                    let lhs_spec_id = if !raw_lhs_spec_id.is_empty() {
-                        Some(parse_spec_id(raw_lhs_spec_id.to_string()))
-                    } else {
-                        None
-                    };
-                    let rhs_spec_id = parse_spec_id(raw_rhs_spec_id.to_string());
-                    SpecIdRef::Pledge{ lhs: lhs_spec_id, rhs: rhs_spec_id }
-                }
+        spec_spans.extend(
+            read_prusti_attrs_with_spans("pledge_spec_id_ref", attrs).into_iter().map(
+                |(_, span)| (typed::SpecType::Postcondition, span)
             )
         );
-        debug!("Function {:?} has specification ids {:?}", def_id, spec_id_refs);
-        spec_id_refs
+        spec_spans.sort_by_key(|&(_, span)| span.lo());
+        spec_spans
     }
 
     /// Get the loop invariant attached to a function with a
@@ -512,45 +497,33 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         ).collect()
     }
 
-    /// Get the specifications attached to the `def_id` function.
-    pub fn get_procedure_specs(&self, def_id: DefId) -> Option<typed::SpecificationSet<'tcx>> {
-        debug!("get spec for: {:?}", def_id);
-        // Currently, we don't support specifications for external functions.
-        // Since we have a collision of PRUSTI_SPEC_ATTR between different crates, we manually check
-        // that the def_id does not point to an external crate.
-        if !def_id.is_local() {
+    /// Get the specification of the `prusti_assert!(..)` desugared into the `def_id` closure,
+    /// if any.
+    pub fn get_assertion_spec(&self, def_id: DefId) -> Option<SpecificationId> {
+        let attrs = self.env().tcx().get_attrs(def_id);
+        if !has_prusti_attr(attrs, "assert_spec") {
             return None;
         }
-        let refs = self.get_procedure_spec_ids(def_id);
-        if refs.is_empty() {
-            None
-        } else {
-            let mut pres = Vec::new();
-            let mut posts = Vec::new();
-            let mut pledges = Vec::new();
-            for spec_id_ref in refs {
-                match spec_id_ref {
-                    SpecIdRef::Precondition(spec_id) => {
-                        pres.push(self.spec().get(&spec_id).unwrap().clone());
-                    }
-                    SpecIdRef::Postcondition(spec_id) => {
-                        posts.push(self.spec().get(&spec_id).unwrap().clone());
-                    }
-                    SpecIdRef::Pledge{ lhs, rhs } => {
-                        pledges.push(typed::Pledge {
-                            reference: None,    // FIXME: Currently only `result` is supported.
-                            lhs: lhs.map(|spec_id| self.spec().get(&spec_id).unwrap().clone()),
-                            rhs: self.spec().get(&rhs).unwrap().clone(),
-                        })
-                    }
-                }
-            }
-            Some(typed::SpecificationSet::Procedure(typed::ProcedureSpecification::new(pres, posts, pledges)))
-        }
+        read_prusti_attr("spec_id", attrs).map(
+            |raw_spec_id| raw_spec_id.try_into().expect(
+                &format!("cannot parse the spec_id attached to {:?}", def_id)
+            )
+        )
+    }
+
+    /// Get the specifications attached to the `def_id` function.
+    pub fn get_procedure_specs(&self, def_id: DefId) -> Option<typed::SpecificationSet<'tcx>> {
+        self.spec_resolver.get_procedure_specs(def_id)
+    }
+
+    /// Get the specifications of the `def_id` function, following external
+    /// specifications.
+    pub fn resolve_procedure_specs(&self, def_id: ProcedureDefId) -> Option<typed::SpecificationSet<'tcx>> {
+        self.spec_resolver.resolve(def_id)
     }
 
     fn get_procedure_contract(&self, proc_def_id: ProcedureDefId) -> ProcedureContractMirDef<'tcx> {
-        let opt_fun_spec = self.get_procedure_specs(proc_def_id);
+        let opt_fun_spec = self.resolve_procedure_specs(proc_def_id);
         let fun_spec = match opt_fun_spec {
             Some(fun_spec) => fun_spec.clone(),
             None => {
@@ -580,7 +553,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         target: places::Local,
     ) -> ProcedureContract<'tcx> {
         // get specification on trait declaration method or inherent impl
-        let fun_spec = if let Some(spec) = self.get_procedure_specs(proc_def_id) {
+        let fun_spec = if let Some(spec) = self.resolve_procedure_specs(proc_def_id) {
             spec.clone()
         } else {
             debug!("Procedure {:?} has no specification", proc_def_id);
@@ -610,7 +583,7 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
                     // FIXME(@jakob): if several methods are found, we currently don't know which
                     // one to pick.
                     let item = procs[0];
-                    if let Some(spec) = self.get_procedure_specs(item.def_id) {
+                    if let Some(spec) = self.resolve_procedure_specs(item.def_id) {
                         impl_spec = spec.clone();
                     } else {
                         debug!("Procedure {:?} has no specification", item.def_id);
@@ -1101,19 +1074,49 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         error: ErrorCtxt,
     ) -> vir::Expr {
         trace!("encode_assertion {:?}", assertion);
-        let encoded_assertion = encode_spec_assertion(
-            self,
-            assertion,
-            pre_label,
-            target_args,
-            target_return,
-            targets_are_values,
-            assertion_location,
-        );
-        encoded_assertion.set_default_pos(
-            self.error_manager()
-                .register(typed::Spanned::get_spans(assertion, mir, self.env().tcx()), error),
-        )
+        // Viper checks the conjuncts of an assertion one at a time and reports
+        // the position of the failing one, so each conjunct gets its own spans.
+        let encoded_assertion = match assertion.kind {
+            box typed::AssertionKind::And(ref conjuncts) if !conjuncts.is_empty() => {
+                let encoded_conjuncts: Vec<_> = conjuncts.iter()
+                    .map(|conjunct| self.encode_assertion(
+                        conjunct,
+                        mir,
+                        pre_label,
+                        target_args,
+                        target_return,
+                        targets_are_values,
+                        assertion_location,
+                        error.clone(),
+                    ))
+                    .collect();
+                // The simplifier would fold the conjunction into this literal
+                // at the position of the whole conjunction.
+                let falsity = encoded_conjuncts.iter()
+                    .find(|conjunct| matches!(conjunct, vir::Expr::Const(vir::Const::Bool(false), _)));
+                match falsity {
+                    Some(falsity) => return falsity.clone(),
+                    None => encoded_conjuncts.into_iter().conjoin(),
+                }
+            }
+            _ => encode_spec_assertion(
+                self,
+                assertion,
+                pre_label,
+                target_args,
+                target_return,
+                targets_are_values,
+                assertion_location,
+            ),
+        };
+        let tcx = self.env().tcx();
+        let position = self.error_manager()
+            .register(typed::Spanned::get_spans(assertion, mir, tcx), error);
+        if let box typed::AssertionKind::Implies(..) = assertion.kind {
+            let consequent_spans = typed::TaggedSpans::get_implies_consequent_spans(assertion, mir, tcx);
+            self.error_manager().register_consequent(&position, consequent_spans);
+        }
+        encoded_assertion.set_default_pos(position)
     }
 
     pub fn encode_type_predicate_use(&self, ty: ty::Ty<'tcx>) -> Result<String, ErrorCtxt> {
@@ -1482,14 +1485,18 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
 
         if !self.pure_functions.borrow().contains_key(&key) {
             trace!("not encoded: {:?}", key);
-            let procedure = self.env.get_procedure(proc_def_id);
-            let pure_function_encoder =
-                PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
-            let function = if self.is_trusted(proc_def_id) {
-                pure_function_encoder.encode_bodyless_function()
+            let function = if !self.env.tcx().is_mir_available(proc_def_id) {
+                encode_trait_method_declaration(self, proc_def_id)
             } else {
-                let pure_function = pure_function_encoder.encode_function();
-                self.patch_pure_post_with_mirror_call(pure_function)
+                let procedure = self.env.get_procedure(proc_def_id);
+                let pure_function_encoder =
+                    PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
+                if self.is_trusted(proc_def_id) {
+                    pure_function_encoder.encode_bodyless_function()
+                } else {
+                    let pure_function = pure_function_encoder.encode_function();
+                    self.patch_pure_post_with_mirror_call(pure_function)
+                }
             };
 
             self.log_vir_program_before_viper(function.to_string());
@@ -1624,7 +1631,6 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         mut proc_def_id: ProcedureDefId,
     ) -> (String, vir::Type) {
         proc_def_id = *self.get_specification_def_id(&proc_def_id);
-        let procedure = self.env.get_procedure(proc_def_id);
 
         assert!(
             self.env.has_prusti_attribute(proc_def_id, "pure"),
@@ -1632,6 +1638,13 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
             proc_def_id
         );
 
+        if !self.env.tcx().is_mir_available(proc_def_id) {
+            self.queue_pure_function_encoding(proc_def_id);
+            let function = encode_trait_method_declaration(self, proc_def_id);
+            return (function.name, function.return_type);
+        }
+
+        let procedure = self.env.get_procedure(proc_def_id);
         let pure_function_encoder =
             PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
 
@@ -1644,6 +1657,40 @@ impl<'v, 'tcx> Encoder<'v, 'tcx> {
         )
     }
 
+    /// Resolves the call of the trait method `proc_def_id` to the method of
+    /// the impl selected by `substs`, after replacing the type parameters that
+    /// are known at this point of the encoding. The calls on a generic
+    /// receiver cannot be resolved and keep calling the method of the trait.
+    pub fn resolve_trait_method_call(
+        &self,
+        proc_def_id: ProcedureDefId,
+        substs: ty::subst::SubstsRef<'tcx>,
+    ) -> ProcedureDefId {
+        let tcx = self.env.tcx();
+        if tcx.trait_of_item(proc_def_id).is_none() {
+            return proc_def_id;
+        }
+        let substs = tcx.mk_substs(substs.iter().map(|arg| match arg.unpack() {
+            ty::subst::GenericArgKind::Type(ty) => self.resolve_typaram(ty).into(),
+            _ => arg,
+        }));
+        // No impl can be selected for a type parameter
+        if substs.needs_subst() {
+            return proc_def_id;
+        }
+        match ty::Instance::resolve(tcx, ty::ParamEnv::reveal_all(), proc_def_id, substs) {
+            Ok(Some(instance)) if matches!(instance.def, ty::InstanceDef::Item(_)) => {
+                let impl_def_id = instance.def.def_id();
+                if impl_def_id.is_local() && tcx.is_mir_available(impl_def_id) {
+                    impl_def_id
+                } else {
+                    proc_def_id
+                }
+            }
+            _ => proc_def_id,
+        }
+    }
+
     /// Encode the use (call) of either a comparison (equality or disequality)
     /// returning the name of the encoded function and its type.
     /// If the comparison is not supported, a stub function will be encoded
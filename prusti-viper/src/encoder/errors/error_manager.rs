@@ -7,7 +7,7 @@
 use prusti_common::vir::Position;
 use std::collections::HashMap;
 use rustc_span::source_map::SourceMap;
-use rustc_span::MultiSpan;
+use rustc_span::{MultiSpan, Span};
 use viper::VerificationError;
 use prusti_interface::PrustiError;
 use log::debug;
@@ -49,6 +49,8 @@ pub enum ErrorCtxt {
     /// A Viper `assert expr` that asserts the functional specification of a loop invariant `expr`
     AssertLoopInvariantOnEntry,
     AssertLoopInvariantAfterIteration,
+    /// A Viper `assert expr` that encodes a `prusti_assert!(expr)` statement
+    AssertPrustiAssertion,
     /// A Viper `assert false` that encodes the failure (panic) of an `assert` Rust terminator
     /// Arguments: the message of the Rust assertion
     AssertTerminator(String),
@@ -99,6 +101,8 @@ pub enum ErrorCtxt {
 pub struct ErrorManager<'tcx> {
     codemap: &'tcx SourceMap,
     source_span: HashMap<u64, MultiSpan>,
+    /// The spans of the consequent of the implications, by position.
+    consequent_spans: HashMap<u64, Vec<Span>>,
     error_contexts: HashMap<u64, ErrorCtxt>,
     next_pos_id: u64,
 }
@@ -109,6 +113,7 @@ impl<'tcx> ErrorManager<'tcx>
         ErrorManager {
             codemap,
             source_span: HashMap::new(),
+            consequent_spans: HashMap::new(),
             error_contexts: HashMap::new(),
             next_pos_id: 1,
         }
@@ -153,6 +158,12 @@ impl<'tcx> ErrorManager<'tcx>
         self.error_contexts.insert(pos.id(), error_ctxt);
     }
 
+    /// Register the spans of the consequent of the implication at `pos`, to
+    /// point out what did not follow if the implication fails.
+    pub fn register_consequent(&mut self, pos: &Position, spans: Vec<Span>) {
+        self.consequent_spans.insert(pos.id(), spans);
+    }
+
     pub fn translate_verification_error(&self, ver_error: &VerificationError) -> PrustiError {
         debug!("Verification error: {:?}", ver_error);
         let opt_pos_id: Option<u64> = match ver_error.pos_id {
@@ -197,8 +208,15 @@ impl<'tcx> ErrorManager<'tcx>
             if res.is_none() {
                 debug!("Unregistered reason position: {:?}", reason_pos_id);
             }
-            res
+            res.map(|span| {
+                let mut span = span.clone();
+                for &consequent_span in self.consequent_spans.get(&reason_pos_id).into_iter().flatten() {
+                    span.push_span_label(consequent_span, "this did not follow".to_string());
+                }
+                span
+            })
         });
+        let opt_cause_span = opt_cause_span.as_ref();
 
         let (error_span, error_ctxt) = if let Some(error_ctxt) = opt_error_ctxt {
             debug_assert!(opt_error_span.is_some());
@@ -316,6 +334,11 @@ impl<'tcx> ErrorManager<'tcx>
                 ).push_primary_span(opt_cause_span)
             }
 
+            ("assert.failed:assertion.false", ErrorCtxt::AssertPrustiAssertion) => {
+                PrustiError::verification("the asserted expression might not hold.", error_span)
+                    .push_primary_span(opt_cause_span)
+            }
+
             ("assert.failed:assertion.false", ErrorCtxt::AssertLoopInvariantOnEntry) => {
                 PrustiError::verification("loop invariant might not hold in the first loop iteration.", error_span)
                     .push_primary_span(opt_cause_span)
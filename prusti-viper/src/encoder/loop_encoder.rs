@@ -155,6 +155,7 @@ impl<'p, 'tcx: 'p> LoopEncoder<'p, 'tcx> {
                     && self.mir()[bb].terminator().successors().any(|&succ_bb| {
                         self.procedure.is_reachable_block(succ_bb)
                             && self.procedure.is_spec_block(succ_bb)
+                            && !self.procedure.is_spec_assertion_block(succ_bb)
                     })
             })
             .cloned()
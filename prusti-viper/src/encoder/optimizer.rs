@@ -84,13 +84,14 @@ impl vir::ExprFolder for Optimizer {
         variables: Vec<vir::LocalVar>,
         triggers: Vec<vir::Trigger>,
         body: Box<vir::Expr>,
+        weight: Option<u32>,
         pos: vir::Position,
     ) -> vir::Expr {
         debug!("original body: {}", body);
         let mut replacer = Replacer::new(&variables);
         let replaced_body = replacer.fold_boxed(body);
         debug!("replaced body: {}", replaced_body);
-        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, pos);
+        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, weight, pos);
 
         if replacer.counter > 0 {
             for (expr, variable) in replacer.map {
@@ -193,6 +194,7 @@ impl vir::ExprFolder for UnfoldingExtractor {
         variables: Vec<vir::LocalVar>,
         triggers: Vec<vir::Trigger>,
         body: Box<vir::Expr>,
+        weight: Option<u32>,
         pos: vir::Position,
     ) -> vir::Expr {
         assert!(
@@ -205,7 +207,7 @@ impl vir::ExprFolder for UnfoldingExtractor {
         let replaced_body = self.fold_boxed(body);
         self.in_quantifier = false;
 
-        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, pos);
+        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, weight, pos);
 
         let unfoldings = mem::replace(&mut self.unfoldings, HashMap::new());
 
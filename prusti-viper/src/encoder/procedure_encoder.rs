@@ -16,6 +16,7 @@ use crate::encoder::mir_encoder::{POSTCONDITION_LABEL, PRECONDITION_LABEL};
 use crate::encoder::mir_successor::MirSuccessor;
 use crate::encoder::optimizer;
 use crate::encoder::places::{Local, LocalVariableManager, Place};
+use crate::encoder::pure_function_encoder::returned_referent_ty;
 use crate::encoder::Encoder;
 use crate::encoder::snapshot_spec_patcher::SnapshotSpecPatcher;
 use prusti_common::{
@@ -61,6 +62,7 @@ use rustc_attr::IntType::SignedInt;
 // use syntax::codemap::{MultiSpan, Span};
 use rustc_span::{MultiSpan, Span};
 use prusti_interface::specs::typed;
+use prusti_interface::specs::typed::{AssertionBuilder, OrderedInvariants};
 use ::log::{trace, debug, error};
 use std::borrow::Borrow as StdBorrow;
 
@@ -271,23 +273,15 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                         proc_pre_specs
                             .extend_from_slice(procedure_trait_contract.functional_precondition())
                     } else {
-                        let proc_pre = typed::Assertion {
-                            kind: box typed::AssertionKind::And(
-                                proc_pre_specs.clone()
-                            ),
-                        };
-                        let proc_trait_pre = typed::Assertion {
-                            kind: box typed::AssertionKind::And(
-                                procedure_trait_contract
-                                    .functional_precondition()
-                                    .iter()
-                                    .cloned()
-                                    .collect(),
-                            ),
-                        };
-                        precondition_weakening = Some(typed::Assertion {
-                            kind: box typed::AssertionKind::Implies(proc_trait_pre, proc_pre),
-                        });
+                        let proc_pre = AssertionBuilder::and(proc_pre_specs.clone());
+                        let proc_trait_pre = AssertionBuilder::and(
+                            procedure_trait_contract
+                                .functional_precondition()
+                                .iter()
+                                .cloned()
+                                .collect(),
+                        );
+                        precondition_weakening = Some(AssertionBuilder::implies(proc_trait_pre, proc_pre));
                     }
 
                     if proc_post_specs.is_empty() && proc_pledge_specs.is_empty() {
@@ -299,23 +293,15 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                         if !proc_pledge_specs.is_empty() {
                             unimplemented!("Refining specifications with pledges is not supported");
                         }
-                        let proc_post = typed::Assertion {
-                            kind: box typed::AssertionKind::And(
-                                proc_post_specs.clone()
-                            ),
-                        };
-                        let proc_trait_post = typed::Assertion {
-                            kind: box typed::AssertionKind::And(
-                                procedure_trait_contract
-                                    .functional_postcondition()
-                                    .iter()
-                                    .cloned()
-                                    .collect(),
-                            ),
-                        };
-                        postcondition_strengthening = Some(typed::Assertion {
-                            kind: box typed::AssertionKind::Implies(proc_post, proc_trait_post),
-                        });
+                        let proc_post = AssertionBuilder::and(proc_post_specs.clone());
+                        let proc_trait_post = AssertionBuilder::and(
+                            procedure_trait_contract
+                                .functional_postcondition()
+                                .iter()
+                                .cloned()
+                                .collect(),
+                        );
+                        postcondition_strengthening = Some(AssertionBuilder::implies(proc_post, proc_trait_post));
                     }
                 }
             }
@@ -1676,7 +1662,15 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 };
 
                 // Is the target a specification block?
-                if self.procedure.is_spec_block(default_target) {
+                if self.procedure.is_spec_assertion_block(default_target) {
+                    stmts.push(vir::Stmt::comment(format!(
+                        "Assert the specification of default target {:?}, which is only used by \
+                        Prusti to type-check a `prusti_assert!`.",
+                        default_target
+                    )));
+                    stmts.extend(self.encode_prusti_assertions(default_target));
+                    kill_default_target = true;
+                } else if self.procedure.is_spec_block(default_target) {
                     stmts.push(vir::Stmt::comment(format!(
                         "Ignore default target {:?}, as it is only used by Prusti to type-check \
                         a loop invariant.",
@@ -2484,12 +2478,30 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
             pos
         );
 
-        let target_value = self.encode_pure_function_call_lhs_value(destination);
+        // A pure function that returns a reference is encoded on the value of
+        // the referent (see `returned_referent_ty`), so the call gives the value
+        // of the fresh referent of the lhs.
+        let referent_ty = destination.as_ref().and_then(|(dst, _)| {
+            let return_ty = dst.ty(self.mir, self.encoder.env().tcx()).ty;
+            returned_referent_ty(self.encoder, return_ty)
+        });
+        let (target_value, target_place) = match referent_ty {
+            Some(referent_ty) => {
+                let referent_place = self.encode_pure_function_call_lhs_value(destination);
+                (
+                    self.encoder.encode_value_expr(referent_place.clone(), referent_ty),
+                    referent_place,
+                )
+            }
+            None => (
+                self.encode_pure_function_call_lhs_value(destination),
+                self.encode_pure_function_call_lhs_place(destination),
+            ),
+        };
 
         let inhaled_expr = if return_type.is_domain() {
             let predicate_name = target_value.get_type().name();
             let snapshot = self.encoder.encode_snapshot_use(predicate_name);
-            let target_place = self.encode_pure_function_call_lhs_place(destination);
             let snap_call = snapshot.get_snap_call(target_place);
             vir::Expr::eq_cmp(snap_call.clone(), func_call)
         } else {
@@ -2882,18 +2894,19 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 .iter()
                 .map(|(place, mutability)| encode_place_perm(place, *mutability, pre_label))
                 .collect();
-            if let Some(typed::Pledge { reference, lhs: body_lhs, rhs: body_rhs}) = pledges.first() {
+            if let Some(pledge) = pledges.first() {
+                let typed::Pledge { reference, rhs: body_rhs, .. } = pledge;
                 debug!(
-                    "pledge reference={:?} lhs={:?} rhs={:?}",
-                    reference, body_lhs, body_rhs
+                    "pledge reference={:?} kind={:?} rhs={:?}",
+                    reference, pledge.kind(), body_rhs
                 );
                 assert!(
                     reference.is_none(),
                     "The reference should be none in postcondition."
                 );
-                let mut assertion_lhs = if let Some(body_lhs) = body_lhs {
+                let mut assertion_lhs = if let typed::PledgeKind::Conditional(body_lhs) = pledge.kind() {
                     self.encoder.encode_assertion(
-                        &body_lhs,
+                        body_lhs,
                         &self.mir,
                         Some(pre_label),
                         &encoded_args,
@@ -3088,6 +3101,8 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         // Encode functional specification
         let mut func_spec = vec![];
         let mut func_spec_spans = vec![];
+        // The spans of the postcondition are needed again for the strengthening
+        let mut spans_cache = typed::SpannedCache::new();
         let func_postcondition = contract.functional_postcondition();
         for typed_assertion in func_postcondition {
             let mut assertion = self.encoder.encode_assertion(
@@ -3100,7 +3115,12 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 None,
                 ErrorCtxt::GenericExpression,
             );
-            func_spec_spans.extend(typed::Spanned::get_spans(typed_assertion, &self.mir, self.encoder.env().tcx()));
+            func_spec_spans.extend(typed::Spanned::get_spans_cached(
+                typed_assertion,
+                &self.mir,
+                self.encoder.env().tcx(),
+                &mut spans_cache,
+            ));
             assertion = self.wrap_arguments_into_old(assertion, pre_label, contract, &encoded_args);
             func_spec.push(assertion);
         }
@@ -3119,7 +3139,12 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 ErrorCtxt::AssertMethodPostconditionStrengthening(MultiSpan::from_spans(
                     func_postcondition
                         .iter()
-                        .flat_map(|ts| typed::Spanned::get_spans(ts, &self.mir, self.encoder.env().tcx()))
+                        .flat_map(|ts| typed::Spanned::get_spans_cached(
+                            ts,
+                            &self.mir,
+                            self.encoder.env().tcx(),
+                            &mut spans_cache,
+                        ))
                         .collect(),
                 )),
             );
@@ -3729,6 +3754,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         for bbi in self.procedure.get_reachable_cfg_blocks() {
             if Some(loop_head) == self.loop_encoder.get_loop_head(bbi)
                 && self.procedure.is_spec_block(bbi)
+                && !self.procedure.is_spec_assertion_block(bbi)
             {
                 res.push(bbi)
             } else {
@@ -3743,6 +3769,47 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
         res
     }
 
+    /// Encode the `prusti_assert!(..)` statements type-checked in the specification block
+    /// `spec_block`. Failures are reported at the span of the statement.
+    fn encode_prusti_assertions(&self, spec_block: BasicBlockIndex) -> Vec<vir::Stmt> {
+        let encoded_args: Vec<vir::Expr> = self
+            .mir
+            .args_iter()
+            .map(|local| self.mir_encoder.encode_local(local).unwrap().into()) // will panic if attempting to encode unsupported type
+            .collect();
+        let mut stmts = vec![];
+        for stmt in &self.mir.basic_blocks()[spec_block].statements {
+            if let mir::StatementKind::Assign(box (
+                _,
+                mir::Rvalue::Aggregate(box mir::AggregateKind::Closure(cl_def_id, _), _),
+            )) = stmt.kind {
+                if let Some(spec_id) = self.encoder.get_assertion_spec(cl_def_id) {
+                    let assertion = self.encoder.spec().get(&spec_id).unwrap();
+                    let encoded_spec = self.encoder.encode_assertion(
+                        assertion,
+                        &self.mir,
+                        Some(PRECONDITION_LABEL),
+                        &encoded_args,
+                        None,
+                        false,
+                        Some(spec_block),
+                        ErrorCtxt::AssertPrustiAssertion,
+                    );
+                    let assert_pos = self.encoder.error_manager().register(
+                        stmt.source_info.span,
+                        ErrorCtxt::AssertPrustiAssertion,
+                    );
+                    stmts.push(vir::Stmt::Assert(
+                        encoded_spec,
+                        vir::FoldingBehaviour::Expr,
+                        assert_pos,
+                    ));
+                }
+            }
+        }
+        stmts
+    }
+
     /// Encode the functional specification of a loop
     fn encode_loop_invariant_specs(
         &self,
@@ -3781,8 +3848,13 @@ impl<'p, 'v: 'p, 'tcx: 'v> ProcedureEncoder<'p, 'v, 'tcx> {
                 .args_iter()
                 .map(|local| self.mir_encoder.encode_local(local).unwrap().into()) // will panic if attempting to encode unsupported type
                 .collect();
-            for spec_id in &spec_ids {
-                let assertion = self.encoder.spec().get(spec_id).unwrap();
+            let loop_spec = typed::LoopSpecification::new(
+                spec_ids.iter()
+                    .map(|spec_id| self.encoder.spec().get(spec_id).unwrap().clone())
+                    .collect()
+            );
+            let tcx = self.encoder.env().tcx();
+            for assertion in loop_spec.ordered_invariants(&self.mir, tcx) {
                 // TODO: Mmm... are these parameters correct?
                 let encoded_spec = self.encoder.encode_assertion(
                     &assertion,
@@ -16,6 +16,7 @@ use crate::encoder::mir_interpreter::{
 };
 use crate::encoder::Encoder;
 use crate::encoder::snapshot_spec_patcher::SnapshotSpecPatcher;
+use crate::encoder::spec_encoder::encode_spec_assertion;
 use prusti_common::vir;
 use prusti_common::vir::ExprIterator;
 use prusti_common::config;
@@ -103,7 +104,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
 
         // if the function returns a snapshot, we take a snapshot of the body
         if self.encode_function_return_type().is_domain() {
-            let ty = self.encoder.resolve_typaram(self.mir.return_ty());
+            let ty = self.returned_value_ty();
             let snapshot = self.encoder.encode_snapshot(&ty);
             let body_expr = snapshot.get_snap_call(body_expr);
             self.encode_function_given_body(Some(body_expr))
@@ -160,7 +161,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
         let patched_type_precondition = type_precondition.patch_types(&subst_strings);
 
         let mut precondition = vec![patched_type_precondition, func_precondition];
-        let mut postcondition = vec![self.encode_postcondition_expr(&contract)];
+        let mut postcondition = vec![self.encode_postcondition_expr(&contract, body.as_ref())];
 
         let formal_args: Vec<_> = self
             .mir
@@ -189,7 +190,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
                 .encoder
                 .encode_type_bounds(
                     &vir::Expr::local(pure_fn_return_variable),
-                    self.mir.return_ty(),
+                    self.returned_value_ty(),
                 )
                 .into_iter()
                 .map(|p| p.set_default_pos(res_value_range_pos))
@@ -204,7 +205,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
                 precondition.extend(bounds);
             }
         } else if config::encode_unsigned_num_constraint() {
-            if let ty::TyKind::Uint(_) = self.mir.return_ty().kind() {
+            if let ty::TyKind::Uint(_) = self.returned_value_ty().kind() {
                 let expr = vir::Expr::le_cmp(0.into(), pure_fn_return_variable.into());
                 postcondition.push(expr.set_default_pos(res_value_range_pos));
             }
@@ -309,7 +310,14 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
 
     /// Encode the postcondition with one expression just for the functional specification (no
     /// type encoding).
-    fn encode_postcondition_expr(&self, contract: &ProcedureContract<'tcx>) -> vir::Expr {
+    ///
+    /// If the function has a `body` that is a single expression, the body is inlined for
+    /// `result`, so that the postcondition can relate the result to recursive calls.
+    fn encode_postcondition_expr(
+        &self,
+        contract: &ProcedureContract<'tcx>,
+        body: Option<&vir::Expr>,
+    ) -> vir::Expr {
         let mut func_spec: Vec<vir::Expr> = vec![];
 
         // Encode functional specification
@@ -348,12 +356,36 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
         let pure_fn_return_variable =
             vir::LocalVar::new("__result", self.encode_function_return_type());
 
-        let post = post.replace_place(&encoded_return.into(), &pure_fn_return_variable.into())
+        let returned_value = match returned_referent_ty(self.encoder, self.mir.return_ty()) {
+            Some(referent_ty) => self.encoder.encode_value_expr(encoded_return.into(), referent_ty),
+            None => encoded_return.into(),
+        };
+        let inlined_body = body.filter(|_| self.has_single_expression_body());
+        let result = match inlined_body {
+            Some(body) => body.clone(),
+            None => pure_fn_return_variable.into(),
+        };
+        let post = post.replace_place(&returned_value, &result)
             .set_default_pos(postcondition_pos);
 
         SnapshotSpecPatcher::new(self.encoder).patch_spec(post)
     }
 
+    /// Whether the body of the function is a single expression, i.e. it has no statements such
+    /// as `let` bindings.
+    fn has_single_expression_body(&self) -> bool {
+        let local_def_id = match self.proc_def_id.as_local() {
+            Some(local_def_id) => local_def_id,
+            None => return false,
+        };
+        let hir_map = self.encoder.env().tcx().hir();
+        let body = hir_map.body(hir_map.body_owned_by(hir_map.local_def_id_to_hir_id(local_def_id)));
+        match body.value.kind {
+            hir::ExprKind::Block(block, _) => block.stmts.is_empty() && block.expr.is_some(),
+            _ => true,
+        }
+    }
+
     fn encode_local(&self, local: mir::Local) -> vir::LocalVar {
         let var_name = self.interpreter.mir_encoder().encode_local_var_name(local);
         let var_type = self
@@ -367,8 +399,126 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionEncoder<'p, 'v, 'tcx> {
     }
 
     pub fn encode_function_return_type(&self) -> vir::Type {
-        let ty = self.encoder.resolve_typaram(self.mir.return_ty());
-        self.encoder.encode_value_type(ty)
+        self.encoder.encode_value_type(self.returned_value_ty())
+    }
+
+    /// The type of the value returned by the encoded function, see
+    /// `returned_referent_ty`.
+    fn returned_value_ty(&self) -> ty::Ty<'tcx> {
+        let ty = self.mir.return_ty();
+        returned_referent_ty(self.encoder, ty).unwrap_or_else(|| self.encoder.resolve_typaram(ty))
+    }
+}
+
+/// The type of the referent of `ty` if it is a shared reference to a
+/// primitive type, an ADT or a tuple. A pure function that returns such a
+/// reference is encoded as a function that returns the value of the referent,
+/// so that specifications can use `*f(..)` without holding permissions to the
+/// memory location of the result.
+pub(super) fn returned_referent_ty<'v, 'tcx: 'v>(
+    encoder: &Encoder<'v, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> Option<ty::Ty<'tcx>> {
+    if let ty::TyKind::Ref(_, referent_ty, hir::Mutability::Not) = encoder.resolve_typaram(ty).kind() {
+        let referent_ty = encoder.resolve_typaram(*referent_ty);
+        match referent_ty.kind() {
+            ty::TyKind::Bool |
+            ty::TyKind::Int(_) |
+            ty::TyKind::Uint(_) |
+            ty::TyKind::Char |
+            ty::TyKind::Adt(..) |
+            ty::TyKind::Tuple(_) => Some(referent_ty),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Encodes a pure trait method without a default body, e.g.
+/// `#[pure] fn valid(&self) -> bool;`, as a bodyless function. Such a method
+/// has no MIR, so the arguments are taken from its signature. Only the calls
+/// on a generic receiver use this function: the other calls are resolved to
+/// the method of the impl (see `Encoder::resolve_trait_method_call`). The
+/// contract of the trait method becomes the contract of the function, so
+/// that generic callers can rely on it. Since there is no MIR to resolve
+/// the spans of the contract, its errors are reported at the method.
+pub(super) fn encode_trait_method_declaration<'v, 'tcx: 'v>(
+    encoder: &Encoder<'v, 'tcx>,
+    proc_def_id: DefId,
+) -> vir::Function {
+    let tcx = encoder.env().tcx();
+    let fn_sig = tcx.erase_late_bound_regions(&tcx.fn_sig(proc_def_id));
+    let formal_args: Vec<_> = fn_sig
+        .inputs()
+        .iter()
+        .enumerate()
+        .map(|(index, &arg_ty)| {
+            // The first argument is `_1`, as in the MIR of the other functions
+            let var_name = format!("{:?}", mir::Local::from_usize(index + 1));
+            let var_type = encoder.encode_value_or_ref_type(encoder.resolve_typaram(arg_ty));
+            vir::LocalVar::new(var_name, var_type)
+        })
+        .collect();
+    // The function reads the arguments, so that it depends on the heap
+    let mut precondition: Vec<_> = formal_args
+        .iter()
+        .filter_map(|arg| {
+            vir::Expr::pred_permission(vir::Expr::local(arg.clone()), vir::PermAmount::Read)
+        })
+        .collect();
+    let return_ty = returned_referent_ty(encoder, fn_sig.output())
+        .unwrap_or_else(|| encoder.resolve_typaram(fn_sig.output()));
+    let return_type = encoder.encode_value_type(return_ty);
+
+    let contract = encoder.get_procedure_contract_for_def(proc_def_id);
+    let contract_pos = encoder
+        .error_manager()
+        .register(tcx.def_span(proc_def_id), ErrorCtxt::GenericExpression);
+    let encoded_args: Vec<vir::Expr> = formal_args
+        .iter()
+        .map(|arg| vir::Expr::local(arg.clone()))
+        .collect();
+    let encoded_return = vir::Expr::local(vir::LocalVar::new(
+        format!("{:?}", mir::RETURN_PLACE),
+        encoder.encode_value_or_ref_type(encoder.resolve_typaram(fn_sig.output())),
+    ));
+    let encode_contract_item = |item: &typed::Assertion<'tcx>| {
+        let encoded_item = encode_spec_assertion(
+            encoder,
+            item,
+            None,
+            &encoded_args,
+            Some(&encoded_return),
+            true,
+            None,
+        );
+        SnapshotSpecPatcher::new(encoder).patch_spec(encoded_item.set_default_pos(contract_pos))
+    };
+    precondition.extend(contract.functional_precondition().iter().map(&encode_contract_item));
+
+    // Fix return variable, as in `PureFunctionEncoder::encode_postcondition_expr`
+    let returned_value = match returned_referent_ty(encoder, fn_sig.output()) {
+        Some(referent_ty) => encoder.encode_value_expr(encoded_return.clone(), referent_ty),
+        None => encoded_return.clone(),
+    };
+    let pure_fn_return_variable = vir::LocalVar::new("__result", return_type.clone());
+    let postcondition = contract
+        .functional_postcondition()
+        .iter()
+        .map(|item| {
+            encode_contract_item(item)
+                .replace_place(&returned_value, &pure_fn_return_variable.clone().into())
+        })
+        .collect();
+
+    vir::Function {
+        name: encoder.encode_item_name(proc_def_id),
+        formal_args,
+        return_type,
+        pres: precondition,
+        posts: postcondition,
+        body: None,
     }
 }
 
@@ -381,6 +531,9 @@ pub(super) struct PureFunctionBackwardInterpreter<'p, 'v: 'p, 'tcx: 'v> {
     /// when to a undefined function calls. This distinction allows overflow checks to be checked
     /// on the caller side and assumed on the definition side.
     is_encoding_assertion: bool,
+    /// True if the encoder is encoding an assertion annotated with `#[unbounded]`, whose
+    /// arithmetic operations are on mathematical integers and thus never overflow.
+    is_unbounded_arithmetic: bool,
 }
 
 /// XXX: This encoding works backward, but there is the risk of generating expressions whose length
@@ -393,17 +546,53 @@ impl<'p, 'v: 'p, 'tcx: 'v> PureFunctionBackwardInterpreter<'p, 'v, 'tcx> {
         def_id: DefId,
         is_encoding_assertion: bool,
     ) -> Self {
+        // The closures of a specification are nested in its spec item, which carries the
+        // annotation.
+        let spec_item_def_id = encoder.env().tcx().closure_base_def_id(def_id);
+        let is_unbounded_arithmetic = is_encoding_assertion &&
+            encoder.env().has_prusti_attribute(spec_item_def_id, "unbounded_arithmetic");
         PureFunctionBackwardInterpreter {
             encoder,
             mir,
             mir_encoder: MirEncoder::new(encoder, mir, def_id),
             is_encoding_assertion,
+            is_unbounded_arithmetic,
         }
     }
 
     pub(super) fn mir_encoder(&self) -> &MirEncoder<'p, 'v, 'tcx> {
         &self.mir_encoder
     }
+
+    /// Returns the encoding of `def_id` if it is `is_some`, `is_none` or
+    /// `unwrap` of `Option`, or `is_ok`, `is_err` or `unwrap` of `Result`.
+    fn get_variant_method(&self, def_id: DefId) -> Option<VariantMethod> {
+        let tcx = self.encoder.env().tcx();
+        let impl_def_id = tcx.impl_of_method(def_id)?;
+        let adt_def = tcx.type_of(impl_def_id).ty_adt_def()?;
+        let method_name = tcx.item_name(def_id);
+        match (tcx.def_path_str(adt_def.did).as_str(), &*method_name.as_str()) {
+            ("std::option::Option", "is_some") => Some(VariantMethod::Is("Some")),
+            ("std::option::Option", "is_none") => Some(VariantMethod::Is("None")),
+            ("std::option::Option", "unwrap") => Some(VariantMethod::Unwrap("Some")),
+            ("std::result::Result", "is_ok") => Some(VariantMethod::Is("Ok")),
+            ("std::result::Result", "is_err") => Some(VariantMethod::Is("Err")),
+            ("std::result::Result", "unwrap") => Some(VariantMethod::Unwrap("Ok")),
+            _ => None,
+        }
+    }
+}
+
+/// A method of `Option` or `Result` that specifications can call without a
+/// pure wrapper, because it is encoded as an access to the discriminant or to
+/// a field of the receiver.
+#[derive(Clone, Copy, Debug)]
+enum VariantMethod {
+    /// Whether the receiver is the variant with the given name.
+    Is(&'static str),
+    /// The field of the variant with the given name, which is only defined
+    /// if the receiver is that variant.
+    Unwrap(&'static str),
 }
 
 impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
@@ -488,12 +677,16 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                 trace!("Return type: {:?}", self.mir.return_ty());
                 let return_type = self.encoder.encode_type(self.mir.return_ty());
                 let return_var = vir::LocalVar::new("_0", return_type);
-                MultiExprBackwardInterpreterState::new_single(
-                    self.encoder.encode_value_expr(
-                        vir::Expr::local(return_var.into()),
-                        self.mir.return_ty()
-                    )
-                )
+                let return_value = self.encoder.encode_value_expr(
+                    vir::Expr::local(return_var.into()),
+                    self.mir.return_ty()
+                );
+                // A returned reference is encoded by the value of its referent
+                let return_value = match returned_referent_ty(self.encoder, self.mir.return_ty()) {
+                    Some(referent_ty) => self.encoder.encode_value_expr(return_value, referent_ty),
+                    None => return_value,
+                };
+                MultiExprBackwardInterpreterState::new_single(return_value)
             }
 
             TerminatorKind::SwitchInt {
@@ -598,6 +791,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
             } => {
                 if let ty::TyKind::FnDef(def_id, substs) = ty.kind() {
                     let def_id = *self.encoder.get_specification_def_id(def_id);
+                    let def_id = self.encoder.resolve_trait_method_call(def_id, substs);
                     let full_func_proc_name: &str =
                         &self.encoder.env().tcx().def_path_str(def_id);
                         // &self.encoder.env().tcx().absolute_item_path_str(def_id);
@@ -631,6 +825,11 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                             .iter()
                             .map(|arg| self.mir_encoder.encode_operand_expr(arg))
                             .collect();
+                        let variant_method = if self.is_encoding_assertion {
+                            self.get_variant_method(def_id)
+                        } else {
+                            None
+                        };
 
                         match full_func_proc_name {
                             "prusti_contracts::old" => {
@@ -654,6 +853,66 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                                 state.substitute_value(&lhs_value, encoded_rhs);
                                 state
                             }
+                            _ if variant_method.is_some() => {
+                                let tcx = self.encoder.env().tcx();
+                                // `is_some` and the like take the receiver by reference
+                                let arg_ty = self.mir_encoder.get_operand_ty(&args[0]);
+                                let (receiver, receiver_ty) = match arg_ty.kind() {
+                                    ty::TyKind::Ref(..) => {
+                                        let (place, ty, _) = self
+                                            .mir_encoder
+                                            .encode_deref(encoded_args[0].clone(), arg_ty);
+                                        (place, ty)
+                                    }
+                                    _ => (encoded_args[0].clone(), arg_ty),
+                                };
+                                let (adt_def, subst) = match receiver_ty.kind() {
+                                    ty::TyKind::Adt(adt_def, subst) => (adt_def, subst),
+                                    ref x => unreachable!("{:?}", x),
+                                };
+                                let variant_name = match variant_method.unwrap() {
+                                    VariantMethod::Is(name) | VariantMethod::Unwrap(name) => name,
+                                };
+                                let (variant_index, variant_def) = adt_def.variants
+                                    .iter()
+                                    .enumerate()
+                                    .find(|(_, variant)| variant.ident.as_str() == variant_name)
+                                    .unwrap();
+                                let mut state = states[&target_block].clone();
+                                match variant_method.unwrap() {
+                                    VariantMethod::Is(_) => {
+                                        let discr_field = self.encoder.encode_discriminant_field();
+                                        let encoded_rhs = vir::Expr::eq_cmp(
+                                            receiver.field(discr_field),
+                                            variant_index.into(),
+                                        );
+                                        state.substitute_value(&lhs_value, encoded_rhs);
+                                    }
+                                    VariantMethod::Unwrap(_) => {
+                                        let field = &variant_def.fields[0];
+                                        let field_ty = field.ty(tcx, subst);
+                                        let encoded_field = self
+                                            .encoder
+                                            .encode_struct_field(&field.ident.as_str(), field_ty);
+                                        let field_place = receiver
+                                            .variant(&variant_def.ident.as_str())
+                                            .field(encoded_field);
+                                        match field_ty.kind() {
+                                            ty::TyKind::Adt(_, _) | ty::TyKind::Tuple(_) => {
+                                                state.substitute_place(&encoded_lhs, field_place);
+                                            }
+                                            _ => {
+                                                let encoded_rhs = self
+                                                    .encoder
+                                                    .encode_value_expr(field_place, field_ty);
+                                                state.substitute_value(&lhs_value, encoded_rhs);
+                                            }
+                                        }
+                                    }
+                                }
+                                state
+                            }
+
                             // simple function call
                             _ => {
                                 let mut is_cmp_call = false;
@@ -733,7 +992,32 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                                     pos,
                                 );
                                 let mut state = states[&target_block].clone();
-                                state.substitute_value(&lhs_value, encoded_rhs);
+                                let referent_ty = if is_pure_function {
+                                    returned_referent_ty(self.encoder, ty)
+                                } else {
+                                    None
+                                };
+                                match referent_ty {
+                                    Some(referent_ty) => {
+                                        // The function returns the value of the referent
+                                        let referent_value = self.encoder
+                                            .encode_value_expr(lhs_value, referent_ty);
+                                        state.substitute_value(&referent_value, encoded_rhs);
+                                        if state.use_place(&encoded_lhs) {
+                                            self.encoder.register_encoding_error(
+                                                EncodingError::unsupported(
+                                                    format!(
+                                                        "the reference returned by the pure function {:?} \
+                                                        can only be dereferenced",
+                                                        func_proc_name
+                                                    ),
+                                                    term.source_info.span,
+                                                )
+                                            );
+                                        }
+                                    }
+                                    None => state.substitute_value(&lhs_value, encoded_rhs),
+                                }
                                 state
                             }
                         }
@@ -997,12 +1281,16 @@ impl<'p, 'v: 'p, 'tcx: 'v> BackwardMirInterpreter<'tcx>
                             encoded_right.clone(),
                             operand_ty.expect_ty(),
                         );
-                        let encoded_check = self.mir_encoder.encode_bin_op_check(
-                            op,
-                            encoded_left,
-                            encoded_right,
-                            operand_ty.expect_ty(),
-                        );
+                        let encoded_check = if self.is_unbounded_arithmetic {
+                            false.into()
+                        } else {
+                            self.mir_encoder.encode_bin_op_check(
+                                op,
+                                encoded_left,
+                                encoded_right,
+                                operand_ty.expect_ty(),
+                            )
+                        };
 
                         let field_types = if let ty::TyKind::Tuple(ref x) = ty.kind() {
                             x
@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::encoder::errors::ErrorCtxt;
+use crate::encoder::errors::{EncodingError, ErrorCtxt};
 use crate::encoder::mir_encoder::{MirEncoder, PlaceEncoder};
 use crate::encoder::mir_encoder::PRECONDITION_LABEL;
 use crate::encoder::mir_interpreter::{
@@ -12,13 +12,16 @@ use crate::encoder::mir_interpreter::{
     MultiExprBackwardInterpreterState,
 };
 use crate::encoder::pure_function_encoder::PureFunctionBackwardInterpreter;
+use crate::encoder::type_encoder::compute_discriminant_values;
 use crate::encoder::Encoder;
 use prusti_common::vir;
 use prusti_common::vir::ExprIterator;
 use prusti_interface::specs::typed;
+use prusti_interface::specs::typed::{ConstEval, Simplify};
 use rustc_hir as hir;
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir;
+use rustc_middle::mir::interpret::ConstValue;
 use rustc_middle::ty;
 use std::collections::HashMap;
 use rustc_ast::ast;
@@ -63,7 +66,8 @@ pub fn encode_spec_assertion<'v, 'tcx: 'v>(
         targets_are_values,
         assertion_location,
     );
-    spec_encoder.encode_assertion(assertion)
+    let assertion = assertion.clone().simplify(encoder.env().tcx());
+    spec_encoder.encode_assertion(&assertion)
 }
 
 struct SpecEncoder<'p, 'v: 'p, 'tcx: 'v> {
@@ -110,45 +114,167 @@ impl<'p, 'v: 'p, 'tcx: 'v> SpecEncoder<'p, 'v, 'tcx> {
         forall_id: &str
     ) -> vir::LocalVar {
         trace!("encode_forall_arg: {:?} {:?} {:?}", arg, arg_ty, forall_id);
-        assert!(
-            match arg_ty.kind() {
-                ty::TyKind::Int(..) | ty::TyKind::Uint(..) => true,
-                _ => false,
-            },
-            "Quantification is only supported over integer values"
-        );
         let var_name = format!("{:?}_forall_{}", arg, forall_id);
-        vir::LocalVar::new(var_name, vir::Type::Int)
+        match arg_ty.kind() {
+            ty::TyKind::Int(..) | ty::TyKind::Uint(..) => vir::LocalVar::new(var_name, vir::Type::Int),
+            // Variables of fieldless enums are only bound by expanded
+            // quantifiers, see `encode_enumerated_forall`.
+            ty::TyKind::Adt(adt_def, _) if adt_def.is_enum() && adt_def.is_payloadfree() => {
+                vir::LocalVar::new(var_name, self.encoder.encode_type(arg_ty))
+            }
+            _ => panic!("Quantification is only supported over integer values"),
+        }
+    }
+
+    /// Encode a variable `arg` of type `arg_ty` that is bound by a `let` or a
+    /// quantifier identified by `binder_id`. Besides the types that can be
+    /// quantified over, see `encode_forall_arg`, the variable can have any type
+    /// whose value is stored in a value field, e.g. `bool`. The variable then
+    /// has the type of that field, which is the place that it replaces in the
+    /// encoded body.
+    fn encode_bound_var(
+        &self,
+        arg: mir::Local,
+        arg_ty: ty::Ty<'tcx>,
+        binder_id: &str
+    ) -> vir::LocalVar {
+        trace!("encode_bound_var: {:?} {:?} {:?}", arg, arg_ty, binder_id);
+        match arg_ty.kind() {
+            ty::TyKind::Bool | ty::TyKind::Char | ty::TyKind::Ref(..) => vir::LocalVar::new(
+                format!("{:?}_forall_{}", arg, binder_id),
+                self.encoder.encode_value_field(arg_ty).typ,
+            ),
+            _ => self.encode_forall_arg(arg, arg_ty, binder_id),
+        }
+    }
+
+    /// Encode a quantifier that binds variables of fieldless enums as the
+    /// conjunction of its instances for each combination of their variants.
+    /// The instances are obtained by replacing the discriminant of each such
+    /// variable by the discriminant of the variant. The other variables, if
+    /// any, are still quantified.
+    ///
+    /// A variant has no encoding other than its discriminant, so a body that
+    /// uses such a variable in another way, e.g. as the argument of a pure
+    /// function, is reported as unsupported.
+    fn encode_enumerated_forall(
+        &self,
+        vars: &typed::ForAllVars<'tcx>,
+        trigger_set: &typed::TriggerSet,
+        body: &typed::Assertion<'tcx>,
+    ) -> vir::Expr {
+        let tcx = self.encoder.env().tcx();
+        let forall_id = format!("{}_{}", vars.spec_id, vars.id);
+        let discriminant_field = self.encoder.encode_discriminant_field();
+        let mut instances = vec![self.encode_assertion(body)];
+        for &index in &vars.enumerated {
+            let (arg, ty) = vars.vars[index];
+            let adt_def = match ty.kind() {
+                ty::TyKind::Adt(adt_def, _) => adt_def,
+                _ => unreachable!("only enum variables are enumerated"),
+            };
+            let var: vir::Expr = self.encode_forall_arg(arg, ty, &forall_id).into();
+            let discriminant = var.clone().field(discriminant_field.clone());
+            let values = compute_discriminant_values(adt_def, tcx);
+            let mut expanded = vec![];
+            for instance in instances {
+                for &value in &values {
+                    expanded.push(instance.clone().replace_place(&discriminant, &value.into()));
+                }
+            }
+            if expanded.iter().any(|instance| instance.find(&var)) {
+                // The variable is used by an expression of the body
+                let span = typed::quantifier_span(vars, body, tcx).unwrap();
+                self.encoder.register_encoding_error(EncodingError::unsupported(
+                    format!(
+                        "the variable of type `{}` of a quantifier can only be used to match \
+                        its variant, e.g. with `matches!`",
+                        ty,
+                    ),
+                    span,
+                ));
+                // The error makes the verification fail, so the instances are
+                // not encoded.
+                return true.into();
+            }
+            instances = expanded;
+        }
+        let conjunction = instances.into_iter().conjoin();
+        let quantified: Vec<_> = vars.vars.iter()
+            .enumerate()
+            .filter(|(index, _)| !vars.enumerated.contains(index))
+            .map(|(_, (arg, ty))| self.encode_forall_arg(*arg, ty, &forall_id))
+            .collect();
+        if quantified.is_empty() {
+            conjunction
+        } else {
+            vir::Expr::weighted_forall(
+                quantified,
+                trigger_set
+                    .triggers()
+                    .iter()
+                    .map(|x| self.encode_trigger(x))
+                    .collect(),
+                conjunction,
+                vars.weight,
+            )
+        }
     }
 
     fn encode_trigger(&self, trigger: &typed::Trigger) -> vir::Trigger {
         trace!("encode_trigger {:?}", trigger);
-        // TODO: `encode_hir_expr` generated also the final `.val_int` field access, that we may not want...
-        // vir::Trigger::new(
-        //     trigger
-        //         .terms()
-        //         .iter()
-        //         .map(|expr| self.encode_hir_expr(&expr.expr))
-        //         .collect(),
-        // )
-        unimplemented!();
+        // The terms are encoded like the expressions of the body, so that they
+        // match the function applications of the encoded body.
+        vir::Trigger::new(
+            trigger
+                .terms()
+                .iter()
+                .map(|term| self.encode_expression(term))
+                .collect(),
+        )
     }
 
     /// Encode a specification item as a single expression.
     pub fn encode_assertion(&self, assertion: &typed::Assertion<'tcx>) -> vir::Expr {
         trace!("encode_assertion {:?}", assertion);
         match assertion.kind {
-            box typed::AssertionKind::Expr(ref assertion_expr) =>
-                self.encode_expression(assertion_expr),
+            box typed::AssertionKind::Expr(ref assertion_expr) => {
+                // Constant expressions, such as trivially-true bounds, are
+                // encoded directly instead of encoding their closure.
+                let constant = assertion_expr
+                    .try_eval_const(self.encoder.env().tcx())
+                    .and_then(|value| match value {
+                        ConstValue::Scalar(scalar) => scalar.to_bool().ok(),
+                        _ => None,
+                    });
+                match constant {
+                    Some(value) => value.into(),
+                    None => self.encode_expression(assertion_expr),
+                }
+            }
+            box typed::AssertionKind::Matches(ref scrutinee, ref pattern) => {
+                // The check of the pattern is a `match` on the discriminant of
+                // the scrutinee, which is encoded like any other expression.
+                self.encode_expression(&typed::pattern_check(scrutinee, pattern))
+            }
             box typed::AssertionKind::And(ref assertions) => assertions
                 .iter()
                 .map(|x| self.encode_assertion(x))
                 .collect::<Vec<vir::Expr>>()
                 .into_iter()
                 .conjoin(),
+            box typed::AssertionKind::Or(ref assertions) => assertions
+                .iter()
+                .map(|x| self.encode_assertion(x))
+                .collect::<Vec<vir::Expr>>()
+                .into_iter()
+                .disjoin(),
             box typed::AssertionKind::Implies(ref lhs, ref rhs) => {
                 vir::Expr::implies(self.encode_assertion(lhs), self.encode_assertion(rhs))
             }
+            box typed::AssertionKind::Iff(ref lhs, ref rhs) => {
+                vir::Expr::eq_cmp(self.encode_assertion(lhs), self.encode_assertion(rhs))
+            }
             box typed::AssertionKind::TypeCond(ref vars, ref assertion) => {
                 let enc = |ty: ty::Ty<'tcx>| -> vir::Expr {
                     // FIXME: this is a hack to support generics. See issue #187.
@@ -159,7 +285,12 @@ impl<'p, 'v: 'p, 'tcx: 'v> SpecEncoder<'p, 'v, 'tcx> {
                     vir::Expr::eq_cmp(enc(vars.vars[0].1), enc(vars.vars[1].1));
                 vir::Expr::implies(typecond, self.encode_assertion(assertion))
             }
-            box typed::AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => vir::Expr::forall(
+            box typed::AssertionKind::ForAll(ref vars, ref trigger_set, ref body)
+                if !vars.enumerated.is_empty() =>
+            {
+                self.encode_enumerated_forall(vars, trigger_set, body)
+            }
+            box typed::AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => vir::Expr::weighted_forall(
                 vars.vars.iter()
                     .map(|(arg, ty)|
                         self.encode_forall_arg(*arg, ty, &format!("{}_{}", vars.spec_id, vars.id))
@@ -170,7 +301,59 @@ impl<'p, 'v: 'p, 'tcx: 'v> SpecEncoder<'p, 'v, 'tcx> {
                     .map(|x| self.encode_trigger(x))
                     .collect(),
                 self.encode_assertion(body),
+                vars.weight,
             ),
+            box typed::AssertionKind::Exists(ref vars, ref trigger_set, ref body) => {
+                // VIR has no existential quantifier, so `exists x :: P` is
+                // encoded as `!(forall x :: !P)`.
+                vir::Expr::not(vir::Expr::weighted_forall(
+                    vars.vars.iter()
+                        .map(|(arg, ty)|
+                            self.encode_forall_arg(*arg, ty, &format!("{}_{}", vars.spec_id, vars.id))
+                        ).collect(),
+                    trigger_set
+                        .triggers()
+                        .iter()
+                        .map(|x| self.encode_trigger(x))
+                        .collect(),
+                    vir::Expr::not(self.encode_assertion(body)),
+                    vars.weight,
+                ))
+            }
+            box typed::AssertionKind::Let(ref vars, ref value, ref body) => {
+                // The bound variable is renamed in the same way as the
+                // variables of a quantifier.
+                let (arg, ty) = vars.vars[0];
+                vir::Expr::LetExpr(
+                    self.encode_bound_var(arg, ty, &format!("{}_{}", vars.spec_id, vars.id)),
+                    box self.encode_expression(value),
+                    box self.encode_assertion(body),
+                    vir::Position::default(),
+                )
+            }
+            box typed::AssertionKind::BoundedForAll(ref vars, ref lower, ref upper, ref body) => {
+                let encoded_vars: Vec<_> = vars.vars.iter()
+                    .map(|(arg, ty)|
+                        self.encode_forall_arg(*arg, ty, &format!("{}_{}", vars.spec_id, vars.id))
+                    ).collect();
+                let encoded_body = self.encode_assertion(body);
+                // The bounds are comparisons, which cannot be triggers, so the
+                // triggers are the function applications of the body that
+                // mention all the variables.
+                let triggers = encoded_body.trigger_candidates(&encoded_vars)
+                    .into_iter()
+                    .map(|term| vir::Trigger::new(vec![term]))
+                    .collect();
+                vir::Expr::weighted_forall(
+                    encoded_vars,
+                    triggers,
+                    vir::Expr::implies(
+                        vir::Expr::and(self.encode_expression(lower), self.encode_expression(upper)),
+                        encoded_body,
+                    ),
+                    vars.weight,
+                )
+            }
         }
     }
 
@@ -179,7 +362,7 @@ impl<'p, 'v: 'p, 'tcx: 'v> SpecEncoder<'p, 'v, 'tcx> {
     /// During the translation:
     /// * Usages of the closure's captured state will be translated to the captured place.
     /// * Closure arguments will be treated as quantified variables and will be translated using
-    ///   the `self.encode_forall_arg(..)` method.
+    ///   the `self.encode_bound_var(..)` method.
     ///
     /// The result is a tuple with:
     /// * the translated expression,
@@ -276,14 +459,19 @@ impl<'p, 'v: 'p, 'tcx: 'v> SpecEncoder<'p, 'v, 'tcx> {
             for local_arg_index in inner_mir.args_iter().skip(1) {
                 let local_arg = &inner_mir.local_decls[local_arg_index];
                 assert!(!local_arg.internal);
-                let quantified_var = self.encode_forall_arg(
+                let quantified_var = self.encode_bound_var(
                     local_arg_index,
                     local_arg.ty,
                     &forall_id
                 );
                 let encoded_arg = inner_mir_encoder.encode_local(local_arg_index).unwrap();
-                let value_field = self.encoder.encode_value_field(local_arg.ty);
-                let encoded_arg_value = vir::Expr::local(encoded_arg).field(value_field);
+                // The value of a variable of a fieldless enum is the variable
+                // itself, whose discriminant is replaced by the expansion of
+                // the quantifier.
+                let encoded_arg_value = self.encoder.encode_value_expr(
+                    vir::Expr::local(encoded_arg),
+                    local_arg.ty,
+                );
                 trace!(
                     "Place {}: {} will be renamed to {} because a quantifier introduced it",
                     encoded_arg_value,
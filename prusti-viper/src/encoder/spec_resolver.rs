@@ -0,0 +1,166 @@
+// © 2020, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use ::log::debug;
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::Environment;
+use prusti_interface::specs::typed;
+use prusti_interface::specs::typed::SpecificationId;
+use prusti_interface::utils::read_prusti_attrs;
+use rustc_hir::def_id::DefId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+/// A reference to a procedure specification.
+#[derive(Debug)]
+enum SpecIdRef {
+    Precondition(SpecificationId),
+    Postcondition(SpecificationId),
+    /// Definition of a predicate, encoded as a postcondition of its function.
+    Predicate(SpecificationId),
+    Pledge { lhs: Option<SpecificationId>, rhs: SpecificationId },
+}
+
+/// Resolves the specification of a procedure, following external
+/// specifications to the procedure that declares them. The specifications
+/// are collected from the `prusti::*_spec_id_ref` attributes only once per
+/// procedure.
+pub struct SpecResolver<'v, 'tcx: 'v> {
+    env: &'v Environment<'tcx>,
+    spec: &'v typed::SpecificationMap<'tcx>,
+    extern_spec: &'v typed::ExternSpecificationMap<'tcx>,
+    procedure_specs: RefCell<HashMap<DefId, Option<typed::SpecificationSet<'tcx>>>>,
+}
+
+impl<'v, 'tcx> SpecResolver<'v, 'tcx> {
+    pub fn new(
+        env: &'v Environment<'tcx>,
+        spec: &'v typed::SpecificationMap<'tcx>,
+        extern_spec: &'v typed::ExternSpecificationMap<'tcx>,
+    ) -> Self {
+        SpecResolver {
+            env,
+            spec,
+            extern_spec,
+            procedure_specs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the def_id of the element containing the specifications of
+    /// `def_id`, which is different from `def_id` if the specifications were
+    /// externally declared.
+    pub fn get_specification_def_id(&self, def_id: &'v ProcedureDefId) -> &'v ProcedureDefId {
+        self.extern_spec.get(def_id).map(|(_, spec_def_id, _)| spec_def_id).unwrap_or(def_id)
+    }
+
+    /// Returns the specifications of `def_id`, including the ones declared
+    /// externally.
+    pub fn resolve(&self, def_id: ProcedureDefId) -> Option<typed::SpecificationSet<'tcx>> {
+        let spec_def_id = self.extern_spec.get(&def_id)
+            .map(|(_, spec_def_id, _)| *spec_def_id)
+            .unwrap_or(def_id);
+        self.get_procedure_specs(spec_def_id)
+    }
+
+    /// Returns the specifications attached to the `def_id` function, without
+    /// following external specifications.
+    pub fn get_procedure_specs(&self, def_id: DefId) -> Option<typed::SpecificationSet<'tcx>> {
+        if let Some(specs) = self.procedure_specs.borrow().get(&def_id) {
+            return specs.clone();
+        }
+        let specs = self.compute_procedure_specs(def_id);
+        self.procedure_specs.borrow_mut().insert(def_id, specs.clone());
+        specs
+    }
+
+    /// Return the specification ids that are attached to `def_id` with one of the following
+    /// attributes:
+    /// * `prusti::pre_spec_id_ref="..."` for preconditions,
+    /// * `prusti::post_spec_id_ref="..."` for postconditions,
+    /// * `prusti::pred_spec_id_ref="..."` for the definitions of predicates,
+    /// * `prusti::pledge_spec_id_ref="..."` for pledges.
+    fn get_procedure_spec_ids(&self, def_id: DefId) -> Vec<SpecIdRef> {
+        let mut spec_id_refs = vec![];
+        let attrs = self.env.tcx().get_attrs(def_id);
+
+        let parse_spec_id = |spec_id: String| -> SpecificationId {
+            spec_id.try_into().expect(
+                &format!("cannot parse the spec_id attached to {:?}", def_id)
+            )
+        };
+
+        spec_id_refs.extend(
+            read_prusti_attrs("pre_spec_id_ref", attrs).into_iter().map(
+                |raw_spec_id| SpecIdRef::Precondition(parse_spec_id(raw_spec_id))
+            )
+        );
+        spec_id_refs.extend(
+            read_prusti_attrs("post_spec_id_ref", attrs).into_iter().map(
+                |raw_spec_id| SpecIdRef::Postcondition(parse_spec_id(raw_spec_id))
+            )
+        );
+        spec_id_refs.extend(
+            read_prusti_attrs("pred_spec_id_ref", attrs).into_iter().map(
+                |raw_spec_id| SpecIdRef::Predicate(parse_spec_id(raw_spec_id))
+            )
+        );
+        spec_id_refs.extend(
+            read_prusti_attrs("pledge_spec_id_ref", attrs).into_iter().map(
+                |value| {
+                    let mut value = value.splitn(2, ":");
+                    let raw_lhs_spec_id = value.next().unwrap();
+                    let raw_rhs_spec_id = value.next().unwrap();
+                    let lhs_spec_id = if !raw_lhs_spec_id.is_empty() {
+                        Some(parse_spec_id(raw_lhs_spec_id.to_string()))
+                    } else {
+                        None
+                    };
+                    let rhs_spec_id = parse_spec_id(raw_rhs_spec_id.to_string());
+                    SpecIdRef::Pledge{ lhs: lhs_spec_id, rhs: rhs_spec_id }
+                }
+            )
+        );
+        debug!("Function {:?} has specification ids {:?}", def_id, spec_id_refs);
+        spec_id_refs
+    }
+
+    fn compute_procedure_specs(&self, def_id: DefId) -> Option<typed::SpecificationSet<'tcx>> {
+        debug!("get spec for: {:?}", def_id);
+        // Currently, we don't support specifications for external functions.
+        // Since we have a collision of PRUSTI_SPEC_ATTR between different crates, we manually check
+        // that the def_id does not point to an external crate.
+        if !def_id.is_local() {
+            return None;
+        }
+        let refs = self.get_procedure_spec_ids(def_id);
+        if refs.is_empty() {
+            None
+        } else {
+            let mut pres = Vec::new();
+            let mut posts = Vec::new();
+            let mut pledges = Vec::new();
+            for spec_id_ref in refs {
+                match spec_id_ref {
+                    SpecIdRef::Precondition(spec_id) => {
+                        pres.push(self.spec.get(&spec_id).unwrap().clone());
+                    }
+                    SpecIdRef::Postcondition(spec_id) | SpecIdRef::Predicate(spec_id) => {
+                        posts.push(self.spec.get(&spec_id).unwrap().clone());
+                    }
+                    SpecIdRef::Pledge{ lhs, rhs } => {
+                        pledges.push(typed::Pledge {
+                            reference: None,    // FIXME: Currently only `result` is supported.
+                            lhs: lhs.map(|spec_id| self.spec.get(&spec_id).unwrap().clone()),
+                            rhs: self.spec.get(&rhs).unwrap().clone(),
+                        })
+                    }
+                }
+            }
+            Some(typed::SpecificationSet::Procedure(typed::ProcedureSpecification::new(pres, posts, pledges)))
+        }
+    }
+}
@@ -4,10 +4,27 @@ use rustc_driver::Compilation;
 use rustc_hir::intravisit;
 use rustc_interface::interface::Compiler;
 use rustc_interface::Queries;
+use rustc_span::BytePos;
 use regex::Regex;
 use prusti_common::config;
 use crate::verifier::verify;
 
+/// Replaces the UUIDs of the specifications in `values`, which differ in every
+/// run, with placeholders.
+fn hide_uuids(values: Vec<String>) -> Vec<String> {
+    let uuid = Regex::new(
+        "[a-z0-9]{8}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{12}"
+    ).unwrap();
+    let num_uuid = Regex::new("[a-z0-9]{32}").unwrap();
+    let mut replaced_values: Vec<String> = vec![];
+    for item in values {
+        let item = num_uuid.replace_all(&item, "$(NUM_UUID)");
+        let item = uuid.replace_all(&item, "$(UUID)");
+        replaced_values.push(String::from(item));
+    }
+    replaced_values
+}
+
 pub struct PrustiCompilerCalls {
     flags: ConfigFlags,
 }
@@ -52,24 +69,14 @@ impl rustc_driver::Callbacks for PrustiCompilerCalls {
             intravisit::walk_crate(&mut visitor, &krate);
             let env = Environment::new(tcx);
             let extern_specs = visitor.determine_extern_procedure_specs(&env);
-            let type_map = visitor.determine_typed_procedure_specs();
+            let mut type_map = visitor.determine_typed_procedure_specs(&env);
             if self.flags.print_typeckd_specs {
                 let mut values: Vec<_> = type_map
                     .values()
                     .map(|spec| format!("{:?}", spec))
                     .collect();
                 if self.flags.hide_uuids {
-                    let uuid = Regex::new(
-                        "[a-z0-9]{8}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{4}-[a-z0-9]{12}"
-                    ).unwrap();
-                    let num_uuid = Regex::new("[a-z0-9]{32}").unwrap();
-                    let mut replaced_values: Vec<String> = vec![];
-                    for item in values {
-                        let item = num_uuid.replace_all(&item, "$(NUM_UUID)");
-                        let item = uuid.replace_all(&item, "$(UUID)");
-                        replaced_values.push(String::from(item));
-                    }
-                    values = replaced_values;
+                    values = hide_uuids(values);
                 }
                 // We sort in this strange way so that the output is
                 // determinstic enough to be used in tests.
@@ -78,8 +85,71 @@ impl rustc_driver::Callbacks for PrustiCompilerCalls {
                     println!("{}", value);
                 }
             }
+            if self.flags.dump_spec_captures {
+                // The lines are ordered by the position of the specifications,
+                // which does not depend on their ids.
+                let mut values = specs::typed::dump_spec_captures(&type_map, tcx);
+                if self.flags.hide_uuids {
+                    values = hide_uuids(values);
+                }
+                for value in values {
+                    println!("{}", value);
+                }
+            }
+            if self.flags.dump_spec_hashes {
+                for value in specs::typed::dump_spec_hashes(&type_map, tcx) {
+                    println!("{}", value);
+                }
+            }
+            if let Some((pos, removed, inserted)) = self.flags.remap_spec_spans {
+                let edit = specs::typed::SourceEdit { pos: BytePos(pos), removed, inserted };
+                specs::typed::remap_spans(&mut type_map, edit);
+            }
+            if self.flags.dump_spec_spans {
+                for value in specs::typed::dump_spec_spans(&type_map, tcx) {
+                    println!("{}", value);
+                }
+            }
+            if self.flags.dump_spec_details {
+                for value in specs::typed::dump_spec_details(&type_map, tcx) {
+                    println!("{}", value);
+                }
+            }
             if !self.flags.skip_verify {
-                verify(self.flags, env, type_map, extern_specs);
+                specs::checks::check_trigger_coverage(&type_map, &env);
+                specs::checks::check_old_result(&type_map, &env);
+                specs::checks::check_closure_calls(&type_map, &env);
+                if config::warn_missing_triggers() {
+                    specs::checks::check_missing_triggers(&type_map, &env);
+                }
+                if config::warn_unguarded_subtractions() {
+                    specs::checks::check_unsigned_subtractions(
+                        &env.get_annotated_procedures(),
+                        &type_map,
+                        &env,
+                    );
+                }
+                specs::checks::check_pure_function_calls(
+                    &env.get_annotated_procedures(),
+                    &extern_specs,
+                    &env,
+                );
+                specs::checks::check_trusted_pure_side_effects(
+                    &env.get_annotated_procedures(),
+                    &env,
+                );
+                specs::checks::check_ghost_function_calls(
+                    &env.get_annotated_procedures(),
+                    &env,
+                );
+                specs::checks::check_duplicate_contracts(
+                    &env.get_annotated_procedures(),
+                    &type_map,
+                    &env,
+                );
+                if !env.has_errors() {
+                    verify(self.flags, env, type_map, extern_specs);
+                }
             }
         });
 
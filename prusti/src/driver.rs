@@ -146,6 +146,21 @@ fn main() {
             flags.print_desugared_specs = true;
         } else if arg == "-Zprint-typeckd-specs" {
             flags.print_typeckd_specs = true;
+        } else if arg == "-Zdump-spec-captures" {
+            flags.dump_spec_captures = true;
+        } else if arg == "-Zdump-spec-hashes" {
+            flags.dump_spec_hashes = true;
+        } else if arg == "-Zdump-spec-spans" {
+            flags.dump_spec_spans = true;
+        } else if arg == "-Zdump-spec-details" {
+            flags.dump_spec_details = true;
+        } else if arg.starts_with("-Zremap-spec-spans=") {
+            let edit: Vec<u32> = arg["-Zremap-spec-spans=".len()..]
+                .split(':')
+                .map(|value| value.parse().expect("expected -Zremap-spec-spans=POS:REMOVED:INSERTED"))
+                .collect();
+            assert_eq!(edit.len(), 3, "expected -Zremap-spec-spans=POS:REMOVED:INSERTED");
+            flags.remap_spec_spans = Some((edit[0], edit[1], edit[2]));
         } else if arg == "-Zprint-collected-verification-items" {
             flags.print_collected_verfication_items = true;
         } else if arg == "-Zskip-verify" {
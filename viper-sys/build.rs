@@ -585,6 +585,9 @@ fn main() {
             java_class!("viper.silver.ast.Wand$", vec![
                 object_getter!(),
             ]),
+            java_class!("viper.silver.ast.WeightedQuantifier", vec![
+                constructor!(),
+            ]),
             java_class!("viper.silver.ast.While", vec![
                 constructor!(),
             ]),
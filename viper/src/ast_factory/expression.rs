@@ -697,6 +697,29 @@ impl<'a> AstFactory<'a> {
         self.forall_with_pos(variables, triggers, expr, self.no_position())
     }
 
+    /// A quantifier with the given instantiation weight, which the SMT
+    /// solver uses to limit the instantiations of the quantifier.
+    pub fn weighted_forall_with_pos(
+        &self,
+        variables: &[LocalVarDecl],
+        triggers: &[Trigger],
+        expr: Expr,
+        weight: u32,
+        pos: Position,
+    ) -> Expr<'a> {
+        let obj = self.jni.unwrap_result(self.env.with_local_frame(16, move || {
+            ast::Forall::with(self.env).new(
+                self.jni.new_seq(&map_to_jobjects!(variables)),
+                self.jni.new_seq(&map_to_jobjects!(triggers)),
+                expr.to_jobject(),
+                pos.to_jobject(),
+                self.weighted_quantifier_info(weight),
+                self.no_trafos(),
+            )
+        }));
+        Expr::new(obj)
+    }
+
     pub fn exists_with_pos(
         &self,
         variables: &[LocalVarDecl],
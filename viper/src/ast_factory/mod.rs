@@ -55,6 +55,13 @@ impl<'a> AstFactory<'a> {
         )
     }
 
+    /// The info that sets the instantiation weight of a quantifier.
+    fn weighted_quantifier_info(&self, weight: u32) -> JObject {
+        self.jni.unwrap_result(
+            ast::WeightedQuantifier::with(self.env).new(weight as i32),
+        )
+    }
+
     fn no_trafos(&self) -> JObject {
         self.jni
             .unwrap_result(ast::NoTrafos_object::with(self.env).singleton())